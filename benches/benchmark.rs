@@ -4,12 +4,14 @@
 use std::hint::black_box;
 
 use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
-use rkn::natural::Natural;
+use rkn::natural::{Natural, algorithms};
 
 // TODO: Come up with less terrible benchmarks.
 fn benchmark_addition(c: &mut Criterion) {
   c.bench_function("add two small natural numbers without overflow", |b| {
-    b.iter(|| Natural::from(black_box(10)) + Natural::from(black_box(5)))
+    b.iter(|| {
+      Natural::from(black_box(10_u64)) + Natural::from(black_box(5_u64))
+    })
   });
 
   c.bench_function("add two small natural numbers with overflow", |b| {
@@ -29,5 +31,40 @@ fn benchmark_addition(c: &mut Criterion) {
   });
 }
 
-criterion_group!(benches, benchmark_addition);
+fn benchmark_from_digits(c: &mut Criterion) {
+  let digits: Vec<u8> = (0..10_000).map(|i| (i * 7 % 10) as u8).collect();
+  c.bench_function("construct a natural number from 10,000 digits", |b| {
+    b.iter(|| Natural::from_digits(black_box(&digits), 10))
+  });
+}
+
+fn benchmark_pow10(c: &mut Criterion) {
+  c.bench_function("compute 10^1000", |b| {
+    b.iter(|| Natural::pow10(black_box(1000)))
+  });
+}
+
+fn benchmark_multiplication(c: &mut Criterion) {
+  let mut group = c.benchmark_group("multiply two large natural numbers");
+  for len in [16, 32, 64, 256, 1024] {
+    let x: Vec<u64> = (0..len).map(|i| u64::MAX - i).collect();
+    let y: Vec<u64> = (0..len).map(|i| i * 0x9e37_79b9 + 1).collect();
+
+    group.bench_function(format!("schoolbook, {len} limbs"), |b| {
+      b.iter(|| algorithms::mul_schoolbook(black_box(&x), black_box(&y)))
+    });
+    group.bench_function(format!("Karatsuba, {len} limbs"), |b| {
+      b.iter(|| algorithms::mul_karatsuba(black_box(&x), black_box(&y)))
+    });
+  }
+  group.finish();
+}
+
+criterion_group!(
+  benches,
+  benchmark_addition,
+  benchmark_from_digits,
+  benchmark_pow10,
+  benchmark_multiplication
+);
 criterion_main!(benches);
@@ -0,0 +1,214 @@
+// SPDX-FileCopyrightText: 2025 Severen Redwood <sev@severen.dev>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Prime factorisation of 64-bit integers.
+
+/// The bound below which factors are found by trial division before Pollard's
+/// rho algorithm is used to split what remains.
+const TRIAL_DIVISION_LIMIT: u64 = 1000;
+
+/// The bases that suffice for the Miller–Rabin test to be deterministic for
+/// every 64-bit integer.
+const MILLER_RABIN_BASES: [u64; 12] =
+  [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Compute the prime factorisation of a positive integer.
+///
+/// The factorisation is returned as a list of primes paired with their
+/// multiplicities, in increasing order of the primes.
+///
+/// Small factors are found by trial division, and the rest by Pollard's rho
+/// algorithm with the Miller–Rabin primality test, so that even the largest
+/// 64-bit integers are factorised almost instantly.
+pub fn factorise(mut n: u64) -> Vec<(u64, u32)> {
+  let mut primes = Vec::new();
+
+  let mut p = 2;
+  while p < TRIAL_DIVISION_LIMIT && p <= n / p {
+    while n.is_multiple_of(p) {
+      n /= p;
+      primes.push(p);
+    }
+
+    p += if p == 2 { 1 } else { 2 };
+  }
+  if n > 1 {
+    split(n, &mut primes);
+  }
+  primes.sort_unstable();
+
+  let mut factors: Vec<(u64, u32)> = Vec::new();
+  for p in primes {
+    match factors.last_mut() {
+      Some((q, multiplicity)) if *q == p => *multiplicity += 1,
+      _ => factors.push((p, 1)),
+    }
+  }
+
+  factors
+}
+
+/// Add the prime factors of `n`, which is greater than 1 and has no factors
+/// below [`TRIAL_DIVISION_LIMIT`], to `primes`.
+fn split(n: u64, primes: &mut Vec<u64>) {
+  if is_prime(n) {
+    primes.push(n);
+    return;
+  }
+
+  let d = pollard_rho(n);
+  split(d, primes);
+  split(n / d, primes);
+}
+
+/// Find a nontrivial factor of an odd composite integer with Pollard's rho
+/// algorithm, using Floyd's cycle detection.
+fn pollard_rho(n: u64) -> u64 {
+  // The sequence x^2 + c fails to find a factor for some choices of c, in
+  // which case the next one is tried.
+  for c in 1.. {
+    let f =
+      |x: u64| ((u128::from(x) * u128::from(x) + c) % u128::from(n)) as u64;
+    let (mut x, mut y, mut d) = (2, 2, 1);
+    while d == 1 {
+      x = f(x);
+      y = f(f(y));
+      d = gcd(x.abs_diff(y), n);
+    }
+    if d != n {
+      return d;
+    }
+  }
+
+  unreachable!("some sequence should find a factor")
+}
+
+/// Determine whether an integer is prime with the Miller–Rabin test, which is
+/// deterministic for 64-bit integers with the bases in [`MILLER_RABIN_BASES`].
+fn is_prime(n: u64) -> bool {
+  if n < 2 {
+    return false;
+  }
+  for p in MILLER_RABIN_BASES {
+    if n.is_multiple_of(p) {
+      return n == p;
+    }
+  }
+
+  // Write n - 1 = d * 2^s with d odd.
+  let s = (n - 1).trailing_zeros();
+  let d = (n - 1) >> s;
+  MILLER_RABIN_BASES.into_iter().all(|a| {
+    let mut x = pow_mod(a, d, n);
+    if x == 1 || x == n - 1 {
+      return true;
+    }
+    for _ in 1..s {
+      x = mul_mod(x, x, n);
+      if x == n - 1 {
+        return true;
+      }
+    }
+    false
+  })
+}
+
+/// Compute `a * b mod m` without overflow.
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+  (u128::from(a) * u128::from(b) % u128::from(m)) as u64
+}
+
+/// Compute `base^exp mod m` by repeated squaring.
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+  let mut result = 1;
+  base %= m;
+  while exp > 0 {
+    if exp & 1 == 1 {
+      result = mul_mod(result, base, m);
+    }
+    base = mul_mod(base, base, m);
+    exp >>= 1;
+  }
+
+  result
+}
+
+/// Compute the greatest common divisor of two integers with Euclid's
+/// algorithm.
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+  while b != 0 {
+    (a, b) = (b, a % b);
+  }
+
+  a
+}
+
+/// Format a prime factorisation as a product of powers, as in `2^3 * 3^2 * 5`.
+///
+/// The empty factorisation, which is that of 1, is formatted as `1`.
+pub fn format_factors(factors: &[(u64, u32)]) -> String {
+  if factors.is_empty() {
+    return "1".to_string();
+  }
+
+  factors
+    .iter()
+    .map(|&(p, k)| if k == 1 { p.to_string() } else { format!("{p}^{k}") })
+    .collect::<Vec<_>>()
+    .join(" * ")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_factorise() {
+    assert_eq!(factorise(360), [(2, 3), (3, 2), (5, 1)]);
+    assert_eq!(factorise(1), []);
+    assert_eq!(factorise(97), [(97, 1)]);
+    assert_eq!(factorise(1 << 20), [(2, 20)]);
+    assert_eq!(factorise(4_294_967_291 * 3), [(3, 1), (4_294_967_291, 1)]);
+    assert_eq!(factorise(1009 * 1009 * 1013), [(1009, 2), (1013, 1)]);
+    assert_eq!(
+      factorise(u64::MAX),
+      [(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65537, 1), (6700417, 1)]
+    );
+  }
+
+  #[test]
+  fn test_factorise_large() {
+    // The largest 64-bit prime, and the product of the two largest 32-bit
+    // primes, which are the slowest cases for trial division.
+    assert_eq!(
+      factorise(18_446_744_073_709_551_557),
+      [(18_446_744_073_709_551_557, 1)]
+    );
+    assert_eq!(
+      factorise(4_294_967_291 * 4_294_967_279),
+      [(4_294_967_279, 1), (4_294_967_291, 1)]
+    );
+  }
+
+  #[test]
+  fn test_is_prime() {
+    let primes: Vec<_> = (0..50).filter(|&n| is_prime(n)).collect();
+    assert_eq!(
+      primes,
+      [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47]
+    );
+    assert!(is_prime(18_446_744_073_709_551_557));
+    // The smallest strong pseudoprime to the first few bases.
+    assert!(!is_prime(3_215_031_751));
+    assert!(!is_prime(4_294_967_291 * 4_294_967_279));
+  }
+
+  #[test]
+  fn test_format_factors() {
+    assert_eq!(format_factors(&factorise(360)), "2^3 * 3^2 * 5");
+    assert_eq!(format_factors(&factorise(1)), "1");
+    assert_eq!(format_factors(&factorise(97)), "97");
+    assert_eq!(format_factors(&factorise(1 << 20)), "2^20");
+    assert_eq!(format_factors(&factorise(4_294_967_291 * 3)), "3 * 4294967291");
+  }
+}
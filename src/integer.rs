@@ -0,0 +1,460 @@
+// SPDX-FileCopyrightText: 2025 Severen Redwood <sev@severen.dev>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+  cmp::Ordering,
+  fmt::{self, Display, Formatter},
+  ops::{Add, Mul, Neg, Sub},
+};
+
+use crate::natural::Natural;
+
+/// An arbitrary-precision signed integer, stored as a sign and a magnitude.
+///
+/// Zero is never negative, so that each integer has exactly one representation.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Integer {
+  negative: bool,
+  magnitude: Natural,
+}
+
+impl Integer {
+  /// The integer 0.
+  pub const ZERO: Self = Self { negative: false, magnitude: Natural::ZERO };
+  /// The integer 1.
+  pub const ONE: Self = Self { negative: false, magnitude: Natural::ONE };
+
+  /// Construct an integer from its sign and magnitude, where the sign is
+  /// ignored if the magnitude is zero.
+  pub fn new(negative: bool, magnitude: Natural) -> Self {
+    Self { negative: negative && !magnitude.is_zero(), magnitude }
+  }
+
+  /// Whether this integer is less than zero.
+  pub fn is_negative(&self) -> bool {
+    self.negative
+  }
+
+  /// The absolute value of this integer.
+  pub fn abs(&self) -> Natural {
+    self.magnitude.clone()
+  }
+
+  /// The sign of this integer, which is -1 if it is negative, 0 if it is zero,
+  /// and 1 if it is positive.
+  pub fn signum(&self) -> i32 {
+    if self.negative {
+      -1
+    } else if self.magnitude.is_zero() {
+      0
+    } else {
+      1
+    }
+  }
+
+  /// The greatest common divisor of two integers, which is always nonnegative
+  /// since it ignores their signs.
+  ///
+  /// As with [`Natural::gcd`], `gcd(0, n) = |n|`.
+  pub fn gcd(&self, other: &Integer) -> Natural {
+    self.abs().gcd(other.abs())
+  }
+
+  /// Divide this integer by another, returning both the quotient and the
+  /// remainder.
+  ///
+  /// As for the primitive integer types, the quotient is truncated towards
+  /// zero, so the remainder has the same sign as this integer.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the divisor is zero.
+  pub fn div_rem(self, divisor: Integer) -> (Integer, Integer) {
+    let (quotient, remainder) = self.magnitude.div_rem(divisor.magnitude);
+    (
+      Integer::new(self.negative != divisor.negative, quotient),
+      Integer::new(self.negative, remainder),
+    )
+  }
+
+  /// Divide this integer by another, rounding the quotient down towards
+  /// negative infinity, as in `-7 / 2 = -4`.
+  ///
+  /// This only differs from the quotient of [`Integer::div_rem`] when the
+  /// division is inexact and the signs differ. It also differs from Euclidean
+  /// division when the divisor is negative, as in `7 / -2 = -4`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the divisor is zero.
+  pub fn div_floor(self, divisor: Integer) -> Integer {
+    let signs_differ = self.negative != divisor.negative;
+    let (quotient, remainder) = self.div_rem(divisor);
+    if signs_differ && remainder.signum() != 0 {
+      quotient - Integer::ONE
+    } else {
+      quotient
+    }
+  }
+
+  /// Divide this integer by another, rounding the quotient up towards positive
+  /// infinity, as in `7 / 2 = 4`.
+  ///
+  /// This only differs from the quotient of [`Integer::div_rem`] when the
+  /// division is inexact and the signs agree.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the divisor is zero.
+  pub fn div_ceil(self, divisor: Integer) -> Integer {
+    let signs_agree = self.negative == divisor.negative;
+    let (quotient, remainder) = self.div_rem(divisor);
+    if signs_agree && remainder.signum() != 0 {
+      quotient + Integer::ONE
+    } else {
+      quotient
+    }
+  }
+
+  /// Divide this integer by another, rounding the quotient to the nearest
+  /// integer and breaking ties towards the even one, as in `5 / 2 = 2` and
+  /// `7 / 2 = 4`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the divisor is zero.
+  pub fn div_round_half_even(self, divisor: Integer) -> Integer {
+    self.div_round(divisor, true)
+  }
+
+  /// Divide this integer by another, rounding the quotient to the nearest
+  /// integer and breaking ties away from zero, as in `5 / 2 = 3` and
+  /// `-5 / 2 = -3`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the divisor is zero.
+  pub fn div_round_half_up(self, divisor: Integer) -> Integer {
+    self.div_round(divisor, false)
+  }
+
+  /// Divide this integer by another, rounding the quotient to the nearest
+  /// integer and breaking ties towards the even one if `ties_to_even` is set
+  /// or away from zero otherwise.
+  fn div_round(self, divisor: Integer, ties_to_even: bool) -> Integer {
+    let negative = self.negative != divisor.negative;
+    let divisor_magnitude = divisor.magnitude.clone();
+    let (quotient, remainder) = self.div_rem(divisor);
+
+    // The truncated quotient is off by at most one, and it should move away
+    // from zero when the remainder is more than half the divisor.
+    let twice_remainder = remainder.magnitude.clone() + remainder.magnitude;
+    let away = match twice_remainder.cmp(&divisor_magnitude) {
+      Ordering::Less => false,
+      Ordering::Equal => !ties_to_even || !quotient.magnitude.is_even(),
+      Ordering::Greater => true,
+    };
+
+    match (away, negative) {
+      (false, _) => quotient,
+      (true, false) => quotient + Integer::ONE,
+      (true, true) => quotient - Integer::ONE,
+    }
+  }
+
+  /// Divide this integer by another, rounding the quotient so that the
+  /// remainder is nonnegative, as in `-7 / 2 = -4` and `-7 / -2 = 4`.
+  ///
+  /// This is the quotient that goes with [`Integer::rem_euclid`].
+  ///
+  /// # Panics
+  ///
+  /// Panics if the divisor is zero.
+  pub fn div_euclid(self, divisor: Integer) -> Integer {
+    let divisor_negative = divisor.negative;
+    let (quotient, remainder) = self.div_rem(divisor);
+    match (remainder.negative, divisor_negative) {
+      (true, false) => quotient - Integer::ONE,
+      (true, true) => quotient + Integer::ONE,
+      (false, _) => quotient,
+    }
+  }
+
+  /// The remainder of Euclidean division, which is always nonnegative, as in
+  /// `-7 mod 3 = 2`.
+  ///
+  /// Unlike the remainder of [`Integer::div_rem`], this does not depend on the
+  /// sign of either integer, so it is the least nonnegative member of the
+  /// residue class of this integer modulo the divisor.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the divisor is zero.
+  pub fn rem_euclid(self, divisor: Integer) -> Integer {
+    let modulus = Integer::from(divisor.abs());
+    let (_, remainder) = self.div_rem(divisor);
+    if remainder.negative { remainder + modulus } else { remainder }
+  }
+
+  /// Raise this integer to a power, which is negative only if this integer is
+  /// negative and the exponent is odd.
+  pub fn pow(self, exp: u64) -> Integer {
+    Integer::new(self.negative && exp % 2 == 1, self.magnitude.pow(exp))
+  }
+}
+
+impl Add for Integer {
+  type Output = Integer;
+
+  fn add(self, other: Integer) -> Integer {
+    if self.negative == other.negative {
+      return Integer::new(self.negative, self.magnitude + other.magnitude);
+    }
+
+    // The sum of integers with opposite signs has the sign of the one with the
+    // larger magnitude.
+    let negative = if self.magnitude >= other.magnitude {
+      self.negative
+    } else {
+      other.negative
+    };
+    Integer::new(negative, self.magnitude.abs_diff(&other.magnitude))
+  }
+}
+
+impl Sub for Integer {
+  type Output = Integer;
+
+  fn sub(self, other: Integer) -> Integer {
+    self + -other
+  }
+}
+
+impl Mul for Integer {
+  type Output = Integer;
+
+  fn mul(self, other: Integer) -> Integer {
+    Integer::new(
+      self.negative != other.negative,
+      self.magnitude * other.magnitude,
+    )
+  }
+}
+
+impl Neg for Integer {
+  type Output = Integer;
+
+  fn neg(self) -> Integer {
+    Integer::new(!self.negative, self.magnitude)
+  }
+}
+
+impl From<i64> for Integer {
+  fn from(n: i64) -> Self {
+    Integer::new(n < 0, Natural::from(n.unsigned_abs()))
+  }
+}
+
+impl From<Natural> for Integer {
+  fn from(n: Natural) -> Self {
+    Integer::new(false, n)
+  }
+}
+
+impl Display for Integer {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.pad_integral(!self.negative, "", &self.magnitude.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn int(n: i64) -> Integer {
+    Integer::from(n)
+  }
+
+  #[test]
+  fn test_new() {
+    assert_eq!(Integer::new(true, Natural::ZERO), Integer::ZERO);
+    assert!(!Integer::new(true, Natural::ZERO).is_negative());
+    assert!(Integer::new(true, Natural::ONE).is_negative());
+    assert_eq!(int(0), Integer::ZERO);
+  }
+
+  #[test]
+  fn test_abs() {
+    assert_eq!(int(-5).abs(), Natural::from(5_u64));
+    assert_eq!(int(5).abs(), Natural::from(5_u64));
+    assert_eq!(int(0).abs(), Natural::ZERO);
+    assert_eq!(int(i64::MIN).abs(), Natural::from(1_u64 << 63));
+  }
+
+  #[test]
+  fn test_signum() {
+    assert_eq!(int(-3).signum(), -1);
+    assert_eq!(int(0).signum(), 0);
+    assert_eq!(int(7).signum(), 1);
+    assert_eq!(Integer::from(Natural::from(u128::MAX)).signum(), 1);
+  }
+
+  #[test]
+  fn test_gcd() {
+    assert_eq!(int(-12).gcd(&int(18)), Natural::from(6_u64));
+    assert_eq!(int(12).gcd(&int(-18)), Natural::from(6_u64));
+    assert_eq!(int(-12).gcd(&int(-18)), Natural::from(6_u64));
+    assert_eq!(int(0).gcd(&int(-5)), Natural::from(5_u64));
+    assert_eq!(int(0).gcd(&int(0)), Natural::ZERO);
+  }
+
+  #[test]
+  fn test_add_and_sub() {
+    let cases = [(7, 5), (7, -5), (-7, 5), (-7, -5), (5, -5), (0, -3), (0, 0)];
+    for (a, b) in cases {
+      assert_eq!(int(a) + int(b), int(a + b), "{a} + {b}");
+      assert_eq!(int(a) - int(b), int(a - b), "{a} - {b}");
+    }
+
+    // Mixing signs across the range of an `i64`.
+    let big = Integer::from(Natural::from(1_u128 << 64));
+    let below = Integer::from(Natural::from(u64::MAX));
+    assert_eq!(int(-1) + big.clone(), below);
+    assert_eq!(big.clone() + int(-1), below);
+    assert_eq!(
+      big.clone() - int(-1),
+      Integer::from(Natural::from((1_u128 << 64) + 1))
+    );
+    assert_eq!(int(1) - big.clone(), -below);
+    assert_eq!(big.clone() - big, Integer::ZERO);
+  }
+
+  #[test]
+  fn test_mul_and_neg() {
+    assert_eq!(int(-3) * int(4), int(-12));
+    assert_eq!(int(-3) * int(-4), int(12));
+    assert_eq!(int(-3) * int(0), Integer::ZERO);
+    assert_eq!(
+      int(i64::MIN) * int(-1),
+      Integer::from(Natural::from(1_u64 << 63))
+    );
+    assert_eq!(-int(5), int(-5));
+    assert_eq!(-int(0), Integer::ZERO);
+  }
+
+  #[test]
+  fn test_div_rem() {
+    let cases = [(7, 2), (-7, 2), (7, -2), (-7, -2), (6, 3), (-6, 3), (0, -5)];
+    for (a, b) in cases {
+      assert_eq!(int(a).div_rem(int(b)), (int(a / b), int(a % b)), "{a}, {b}");
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "attempt to divide by zero")]
+  fn test_div_rem_by_zero() {
+    let _ = int(1).div_rem(Integer::ZERO);
+  }
+
+  #[test]
+  fn test_div_floor() {
+    let cases = [
+      (7, 2, 3),
+      (-7, 2, -4),
+      (7, -2, -4),
+      (-7, -2, 3),
+      (6, 3, 2),
+      (-6, 3, -2),
+      (0, -5, 0),
+    ];
+    for (a, b, quotient) in cases {
+      assert_eq!(int(a).div_floor(int(b)), int(quotient), "{a}, {b}");
+    }
+
+    let big = Integer::from(Natural::from((1_u128 << 64) + 1));
+    assert_eq!(int(-1).div_floor(big.clone()), int(-1));
+    assert_eq!(big.div_floor(int(-2)), int(i64::MIN) - int(1));
+  }
+
+  #[test]
+  fn test_div_ceil() {
+    let cases = [
+      (7, 2, 4),
+      (-7, 2, -3),
+      (7, -2, -3),
+      (-7, -2, 4),
+      (6, 3, 2),
+      (-6, 3, -2),
+      (0, -5, 0),
+    ];
+    for (a, b, quotient) in cases {
+      assert_eq!(int(a).div_ceil(int(b)), int(quotient), "{a}, {b}");
+    }
+  }
+
+  #[test]
+  fn test_div_round() {
+    // Each case lists the quotient rounded half to even and then half up.
+    let cases = [
+      (5, 2, 2, 3),
+      (7, 2, 4, 4),
+      (-5, 2, -2, -3),
+      (-7, -2, 4, 4),
+      (1, 2, 0, 1),
+      (-1, 2, 0, -1),
+      (7, 3, 2, 2),
+      (8, 3, 3, 3),
+      (-8, 3, -3, -3),
+      (6, 3, 2, 2),
+      (0, -5, 0, 0),
+    ];
+    for (a, b, half_even, half_up) in cases {
+      assert_eq!(
+        int(a).div_round_half_even(int(b)),
+        int(half_even),
+        "{a}, {b}"
+      );
+      assert_eq!(int(a).div_round_half_up(int(b)), int(half_up), "{a}, {b}");
+    }
+  }
+
+  #[test]
+  fn test_euclid() {
+    let cases = [(7, 3), (-7, 3), (7, -3), (-7, -3), (6, 3), (-6, -3), (0, -5)];
+    for (a, b) in cases {
+      let (quotient, remainder) =
+        (int(a).div_euclid(int(b)), int(a).rem_euclid(int(b)));
+      assert_eq!(quotient, int(a.div_euclid(b)), "{a}, {b}");
+      assert_eq!(remainder, int(a.rem_euclid(b)), "{a}, {b}");
+    }
+
+    // The truncated and Euclidean remainders only differ for negative
+    // dividends.
+    assert_eq!(int(-7).div_rem(int(3)).1, int(-1));
+    assert_eq!(int(-7).rem_euclid(int(3)), int(2));
+
+    let big = Integer::from(Natural::from(1_u128 << 64));
+    assert_eq!(
+      int(-1).rem_euclid(big.clone()),
+      Integer::from(Natural::from(u64::MAX))
+    );
+    assert_eq!(int(-1).div_euclid(-big), int(1));
+  }
+
+  #[test]
+  fn test_pow() {
+    assert_eq!(int(-2).pow(3), int(-8));
+    assert_eq!(int(-2).pow(4), int(16));
+    assert_eq!(int(-2).pow(0), int(1));
+    assert_eq!(int(0).pow(0), int(1));
+    assert_eq!(int(-2).pow(65), -Integer::from(Natural::from(1_u128 << 65)));
+  }
+
+  #[test]
+  fn test_display() {
+    assert_eq!(int(-42).to_string(), "-42");
+    assert_eq!(int(42).to_string(), "42");
+    assert_eq!(format!("{:+}", int(42)), "+42");
+    assert_eq!(format!("{:>5}", int(-7)), "   -7");
+  }
+}
@@ -3,23 +3,1988 @@
 
 #![feature(widening_mul)]
 
-use crate::syntax::Expr;
+use std::{
+  collections::HashMap,
+  f64::consts,
+  fmt::{self, Display, Formatter},
+};
 
+use thiserror::Error;
+
+use crate::{
+  factor::{factorise, format_factors},
+  integer::Integer,
+  natural::{Natural, simplify_fraction},
+  random::Rng,
+  roman::format_roman,
+  syntax::Expr,
+  unit::Unit,
+};
+
+pub mod factor;
+pub mod integer;
 pub mod natural;
+mod random;
+pub mod roman;
 pub mod syntax;
+pub mod unit;
+
+/// The result of evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+  Integer(i64),
+  /// An integer that is too large to fit in an `i64`.
+  ///
+  /// Integers are only stored this way when they are larger than `i64::MAX`,
+  /// so that each integer has exactly one representation. Converting a
+  /// [`Natural`] with [`Value::from`] takes care of this.
+  Natural(Natural),
+  /// A fraction with a signed numerator and a denominator, which is only
+  /// produced by division in [`DivMode::Rational`].
+  ///
+  /// Both parts are arbitrary-precision, so any fraction of integers can be
+  /// represented exactly. Fractions are always in lowest terms with a
+  /// denominator greater than 1, so that a fraction is never equal to an
+  /// integer.
+  Rational(Integer, Natural),
+  Float(f64),
+  /// The prime factorisation of a positive integer, as returned by `factor`,
+  /// which is a list of primes paired with their multiplicities in increasing
+  /// order of the primes.
+  ///
+  /// A factorisation is not a number, so arithmetic on one is a type error.
+  Factors(Vec<(u64, u32)>),
+  /// A string of text, such as the Roman numeral returned by `roman`.
+  ///
+  /// As for factorisations, arithmetic on text is a type error.
+  Text(String),
+}
+
+impl Value {
+  fn to_f64(&self) -> f64 {
+    match self {
+      Value::Integer(n) => *n as f64,
+      Value::Natural(n) => natural_to_f64(n),
+      Value::Rational(numer, denom) => {
+        // Dropping the same number of low bits from both parts keeps them in
+        // the range of a float, while barely changing their ratio.
+        let bits = numer.abs().bit_length().max(denom.bit_length());
+        let shift = bits.saturating_sub(1000);
+        let magnitude = natural_to_f64(&(numer.abs() >> shift))
+          / natural_to_f64(&(denom.clone() >> shift));
+        if numer.is_negative() { -magnitude } else { magnitude }
+      },
+      Value::Float(x) => *x,
+      Value::Factors(_) | Value::Text(_) => unreachable!("not a number"),
+    }
+  }
+
+  /// The value as a numerator and denominator, if it is a rational number.
+  fn to_ratio(&self) -> Option<(Integer, Natural)> {
+    match self {
+      Value::Integer(_) | Value::Natural(_) => {
+        Some((self.to_integer()?, Natural::ONE))
+      },
+      Value::Rational(numer, denom) => Some((numer.clone(), denom.clone())),
+      Value::Float(_) | Value::Factors(_) | Value::Text(_) => None,
+    }
+  }
+
+  /// The value as an arbitrary-precision integer, if it is an integer.
+  fn to_integer(&self) -> Option<Integer> {
+    match self {
+      Value::Integer(n) => Some(Integer::from(*n)),
+      Value::Natural(n) => Some(Integer::from(n.clone())),
+      Value::Rational(..)
+      | Value::Float(_)
+      | Value::Factors(_)
+      | Value::Text(_) => None,
+    }
+  }
+
+  /// Convert an arbitrary-precision integer to a value, which fails if it is a
+  /// negative integer that does not fit in an `i64`.
+  fn from_integer(n: Integer) -> Result<Value, EvalError> {
+    if !n.is_negative() {
+      return Ok(Value::from(n.abs()));
+    }
+
+    // The magnitude of a negative `i64` is at most 2^63, which wraps around to
+    // `i64::MIN` when cast, and negating that leaves it unchanged.
+    match u64::try_from(n.abs()) {
+      Ok(magnitude) if magnitude <= 1 << 63 => {
+        Ok(Value::Integer((magnitude as i64).wrapping_neg()))
+      },
+      _ => Err(EvalError::Overflow),
+    }
+  }
+
+  /// The fraction `numer / denom` in lowest terms, which is an integer if the
+  /// denominator divides the numerator.
+  ///
+  /// Like any other integer result, such an integer keeps only its low 64 bits
+  /// if integers wrap, and otherwise fails if it is negative and does not fit
+  /// in an `i64`.
+  fn ratio(
+    numer: Integer,
+    denom: Integer,
+    wrap: bool,
+  ) -> Result<Value, EvalError> {
+    if denom.signum() == 0 {
+      return Err(EvalError::DivisionByZero);
+    }
+
+    let negative = numer.is_negative() != denom.is_negative();
+    let (numer, denom) = simplify_fraction(&numer.abs(), &denom.abs());
+    let numer = Integer::new(negative, numer);
+    if denom.is_one() {
+      return integer_result(numer, wrap);
+    }
+
+    Ok(Value::Rational(numer, denom))
+  }
+
+  /// A human-readable name for the type of this value.
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Value::Integer(_) => "integer",
+      Value::Natural(_) => "large integer",
+      Value::Rational(..) => "rational",
+      Value::Float(_) => "float",
+      Value::Factors(_) => "factorisation",
+      Value::Text(_) => "string",
+    }
+  }
+}
+
+impl From<i64> for Value {
+  fn from(n: i64) -> Self {
+    Value::Integer(n)
+  }
+}
+
+impl From<Natural> for Value {
+  fn from(n: Natural) -> Self {
+    match i64::try_from(&n) {
+      Ok(n) => Value::Integer(n),
+      Err(_) => Value::Natural(n),
+    }
+  }
+}
+
+impl From<f64> for Value {
+  fn from(x: f64) -> Self {
+    Value::Float(x)
+  }
+}
+
+/// The error returned when converting a [`Value`] to a primitive type that does
+/// not match the type of the value.
+#[derive(Debug, Clone, Error, PartialEq)]
+#[error("Expected {expected} but found {found}")]
+pub struct ValueTypeError {
+  pub expected: &'static str,
+  pub found: &'static str,
+}
+
+impl TryFrom<Value> for i64 {
+  type Error = ValueTypeError;
+
+  fn try_from(value: Value) -> Result<Self, Self::Error> {
+    match value {
+      Value::Integer(n) => Ok(n),
+      value => {
+        Err(ValueTypeError { expected: "integer", found: value.type_name() })
+      },
+    }
+  }
+}
+
+impl TryFrom<Value> for Integer {
+  type Error = ValueTypeError;
+
+  fn try_from(value: Value) -> Result<Self, Self::Error> {
+    value
+      .to_integer()
+      .ok_or(ValueTypeError { expected: "integer", found: value.type_name() })
+  }
+}
+
+impl TryFrom<Value> for f64 {
+  type Error = ValueTypeError;
+
+  fn try_from(value: Value) -> Result<Self, Self::Error> {
+    match value {
+      Value::Float(x) => Ok(x),
+      value => {
+        Err(ValueTypeError { expected: "float", found: value.type_name() })
+      },
+    }
+  }
+}
+
+impl Display for Value {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Value::Integer(n) => write!(f, "{n}"),
+      Value::Natural(n) => write!(f, "{n}"),
+      Value::Rational(numer, denom) => write!(f, "{numer}/{denom}"),
+      Value::Float(x) => write!(f, "{x}"),
+      Value::Factors(factors) => write!(f, "{}", format_factors(factors)),
+      Value::Text(text) => write!(f, "{text}"),
+    }
+  }
+}
+
+/// An error that occurred while evaluating an expression.
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum EvalError {
+  #[error(
+    "Cannot convert from {from} ({}) to {to} ({})",
+    from.dimension(),
+    to.dimension()
+  )]
+  IncompatibleUnits { from: Unit, to: Unit },
+  #[error("Unknown variable '{0}'")]
+  UnknownVariable(String),
+  #[error("Unknown function '{0}'")]
+  UnknownFunction(String),
+  #[error(
+    "Function '{name}' takes {expected} argument(s) but {found} were given"
+  )]
+  ArityMismatch { name: String, expected: usize, found: usize },
+  #[error("Functions can only be defined at the top level of an expression")]
+  NestedDefinition,
+  #[error("Exceeded the maximum depth of {0} nested function calls")]
+  RecursionLimit(usize),
+  #[error(transparent)]
+  Type(#[from] ValueTypeError),
+  #[error("Cannot choose an integer between {low} and {high}")]
+  EmptyRange { low: i64, high: i64 },
+  #[error("Cannot raise an integer to the negative power {0}")]
+  NegativeExponent(i64),
+  #[error("Integer overflow: the result is out of range")]
+  Overflow,
+  #[error("Division by zero")]
+  DivisionByZero,
+  #[error("Cannot factorise {0}, which must be between 1 and 2^64 - 1")]
+  CannotFactorise(Value),
+  #[error(
+    "Cannot write {0} as a Roman numeral, which must be between 1 and 3999"
+  )]
+  NotRoman(Value),
+}
+
+/// The named constants that can be used in expressions, such as `pi`.
+///
+/// A function parameter with the same name as a constant shadows it.
+pub const CONSTANTS: &[(&str, f64)] = &[
+  ("pi", consts::PI),
+  ("tau", consts::TAU),
+  ("e", consts::E),
+  ("phi", 1.618_033_988_749_895),
+];
+
+/// Look up the value of the named constant, if there is one.
+pub fn constant(name: &str) -> Option<f64> {
+  CONSTANTS.iter().find(|(n, _)| *n == name).map(|(_, value)| *value)
+}
+
+/// The built-in functions that can be called in expressions and the number of
+/// arguments each takes, or `None` for functions that take any number of
+/// arguments.
+///
+/// A user-defined function with the same name as a built-in function shadows
+/// it.
+pub const BUILTINS: &[(&str, Option<usize>)] = &[
+  ("randint", Some(2)),
+  ("sum", None),
+  ("prod", None),
+  ("abs", Some(1)),
+  ("signum", Some(1)),
+  ("gcd", Some(2)),
+  ("factor", Some(1)),
+  ("roman", Some(1)),
+];
+
+/// The default maximum depth of nested function calls during evaluation.
+pub const DEFAULT_RECURSION_LIMIT: usize = 256;
+
+/// How dividing one integer by another behaves when the quotient is not an
+/// integer, as in `5 / 2`.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum DivMode {
+  /// Round the quotient towards zero, as in `5 / 2 = 2`.
+  #[default]
+  Truncate,
+  /// Round the quotient down towards negative infinity, as in `-5 / 2 = -3`.
+  Floor,
+  /// Round the quotient up towards positive infinity, as in `5 / 2 = 3`.
+  Ceil,
+  /// Round the quotient to the nearest integer, breaking ties towards the even
+  /// one, as in `5 / 2 = 2` and `7 / 2 = 4`.
+  RoundHalfEven,
+  /// Round the quotient to the nearest integer, breaking ties away from zero,
+  /// as in `5 / 2 = 3` and `-5 / 2 = -3`.
+  RoundHalfUp,
+  /// Promote the quotient to a float, as in `5 / 2 = 2.5`.
+  Float,
+  /// Keep the quotient exact as a fraction, as in `5 / 2 = 5/2`.
+  Rational,
+}
+
+impl DivMode {
+  /// Every division mode, in the order they are documented.
+  pub const ALL: [DivMode; 7] = [
+    DivMode::Truncate,
+    DivMode::Floor,
+    DivMode::Ceil,
+    DivMode::RoundHalfEven,
+    DivMode::RoundHalfUp,
+    DivMode::Float,
+    DivMode::Rational,
+  ];
+
+  /// Look up the division mode with the given name, such as `float`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|mode| mode.name() == name)
+  }
+
+  /// The name used to refer to this division mode in options and settings.
+  pub fn name(self) -> &'static str {
+    match self {
+      DivMode::Truncate => "truncate",
+      DivMode::Floor => "floor",
+      DivMode::Ceil => "ceil",
+      DivMode::RoundHalfEven => "round-half-even",
+      DivMode::RoundHalfUp => "round-half-up",
+      DivMode::Float => "float",
+      DivMode::Rational => "rational",
+    }
+  }
+}
+
+/// A set of user-defined functions that expressions can be evaluated against,
+/// along with options that control how they are evaluated.
+#[derive(Debug)]
+pub struct Environment {
+  functions: HashMap<String, Function>,
+  recursion_limit: usize,
+  wrapping: bool,
+  div_mode: DivMode,
+  rng: Rng,
+}
+
+impl Default for Environment {
+  fn default() -> Self {
+    Self {
+      functions: HashMap::new(),
+      recursion_limit: DEFAULT_RECURSION_LIMIT,
+      wrapping: false,
+      div_mode: DivMode::default(),
+      rng: Rng::from_entropy(),
+    }
+  }
+}
+
+/// A user-defined function, as in `f(x) = x^2 + 1`.
+#[derive(Debug)]
+struct Function {
+  params: Vec<String>,
+  body: Expr,
+}
+
+impl Environment {
+  /// Define a function, replacing any existing function with the same name.
+  pub fn define_function(
+    &mut self,
+    name: String,
+    params: Vec<String>,
+    body: Expr,
+  ) {
+    self.functions.insert(name, Function { params, body });
+  }
+
+  /// The names and parameters of the user-defined functions, in no particular
+  /// order.
+  pub fn functions(&self) -> impl Iterator<Item = (&str, &[String])> {
+    self
+      .functions
+      .iter()
+      .map(|(name, function)| (name.as_str(), function.params.as_slice()))
+  }
+
+  /// Set the maximum depth of nested function calls.
+  ///
+  /// Evaluating an expression that nests calls any deeper fails with
+  /// [`EvalError::RecursionLimit`], which guards against runaway recursion in
+  /// definitions like `f(x) = f(x)` overflowing the stack.
+  pub fn set_recursion_limit(&mut self, limit: usize) {
+    self.recursion_limit = limit;
+  }
+
+  /// Set whether integer arithmetic wraps around on overflow.
+  ///
+  /// When enabled, integers behave like fixed-width 64-bit two's complement
+  /// machine integers, so that, for example, adding 1 to the largest integer
+  /// produces the smallest integer. Fractions stay exact, but arithmetic on
+  /// them that gives an integer wraps it in the same way. This does not
+  /// affect float arithmetic.
+  pub fn set_wrapping(&mut self, wrapping: bool) {
+    self.wrapping = wrapping;
+  }
+
+  /// The way that integer division is performed when the quotient is not an
+  /// integer.
+  pub fn div_mode(&self) -> DivMode {
+    self.div_mode
+  }
+
+  /// Set the way that integer division is performed when the quotient is not
+  /// an integer.
+  ///
+  /// This only affects division of one integer by another, since dividing
+  /// floats always produces a float.
+  pub fn set_div_mode(&mut self, mode: DivMode) {
+    self.div_mode = mode;
+  }
+
+  /// Seed the random number generator used by random functions like
+  /// `randint`.
+  ///
+  /// By default, the generator is seeded from system entropy, so random
+  /// functions produce different results each time. Evaluating the same
+  /// expressions after seeding with the same seed produces the same results.
+  pub fn set_seed(&mut self, seed: u64) {
+    self.rng = Rng::from_seed(seed);
+  }
+}
+
+/// Evaluate an expression in an empty environment.
+pub fn eval(expr: &Expr) -> Result<Value, EvalError> {
+  eval_with(expr, &Environment::default())
+}
 
-pub fn eval(expr: Expr) -> i64 {
+/// Evaluate an expression against the functions defined in `env`.
+pub fn eval_with(expr: &Expr, env: &Environment) -> Result<Value, EvalError> {
+  eval_scoped(expr, env, &HashMap::new(), 0)
+}
+
+/// Evaluate an expression with the given bindings for the parameters of the
+/// function currently being called, if any, where `depth` is the number of
+/// function calls that we are currently nested within.
+fn eval_scoped<'env>(
+  expr: &Expr,
+  env: &'env Environment,
+  scope: &HashMap<&'env str, Value>,
+  depth: usize,
+) -> Result<Value, EvalError> {
   use Expr::*;
 
+  let eval = |expr| eval_scoped(expr, env, scope, depth);
+  let wrap = env.wrapping;
+
   match expr {
-    Literal(n) => n,
-    Neg(e) => -eval(*e),
-    Add(l, r) => eval(*l) + eval(*r),
-    Sub(l, r) => eval(*l) - eval(*r),
-    Mul(l, r) => eval(*l) * eval(*r),
-    // TODO: Support negative exponents.
-    Pow(b, e) => {
-      eval(*b).pow(eval(*e).try_into().expect("exponents must be positive"))
+    Literal(n) => Ok(literal(n, wrap)),
+    Float(x) => Ok(Value::Float(*x)),
+    Var(name) => scope
+      .get(name.as_str())
+      .cloned()
+      .or_else(|| constant(name).map(Value::Float))
+      .ok_or_else(|| EvalError::UnknownVariable(name.clone())),
+    Neg(e) => negate(eval(e)?, wrap),
+    Add(l, r)
+    | Sub(l, r)
+    | Mul(l, r)
+    | Div(l, r)
+    | FloorDiv(l, r)
+    | Rem(l, r)
+    | Mod(l, r)
+    | Pow(l, r) => binary(expr, eval(l)?, eval(r)?, env),
+    Convert(e, from, to) => convert(eval(e)?, *from, *to),
+    Call(name, args) => {
+      let Some(function) = env.functions.get(name) else {
+        return call_builtin(name, args, env, scope, depth);
+      };
+
+      if depth >= env.recursion_limit {
+        return Err(EvalError::RecursionLimit(env.recursion_limit));
+      }
+
+      if args.len() != function.params.len() {
+        return Err(EvalError::ArityMismatch {
+          name: name.clone(),
+          expected: function.params.len(),
+          found: args.len(),
+        });
+      }
+
+      let bindings = function
+        .params
+        .iter()
+        .map(String::as_str)
+        .zip(args.iter().map(eval))
+        .map(|(param, arg)| Ok((param, arg?)))
+        .collect::<Result<_, EvalError>>()?;
+
+      eval_scoped(&function.body, env, &bindings, depth + 1)
+    },
+    FnDef(..) => Err(EvalError::NestedDefinition),
+  }
+}
+
+/// Apply the binary operator at the root of `expr` to its evaluated operands.
+///
+/// This is kept out of [`eval_scoped`] so that each level of recursion uses as
+/// little stack as possible.
+fn binary(
+  expr: &Expr,
+  l: Value,
+  r: Value,
+  env: &Environment,
+) -> Result<Value, EvalError> {
+  let wrap = env.wrapping;
+  let (l, r) = (number(l)?, number(r)?);
+
+  match expr {
+    Expr::Add(..) => add(l, r, wrap),
+    Expr::Sub(..) => sub(l, r, wrap),
+    Expr::Mul(..) => mul(l, r, wrap),
+    Expr::Div(..) => div(l, r, env.div_mode, wrap),
+    Expr::FloorDiv(..) => floor_div(l, r, wrap),
+    Expr::Rem(..) => rem(l, r, wrap),
+    Expr::Mod(..) => modulo(l, r, wrap),
+    Expr::Pow(..) => pow(l, r, wrap),
+    _ => unreachable!("only binary operators have two operands"),
+  }
+}
+
+/// Convert a value from one unit to another, which gives a float.
+fn convert(value: Value, from: Unit, to: Unit) -> Result<Value, EvalError> {
+  if from.dimension() != to.dimension() {
+    return Err(EvalError::IncompatibleUnits { from, to });
+  }
+
+  Ok(Value::Float(number(value)?.to_f64() * from.scale() / to.scale()))
+}
+
+/// Check that a value is a number, which every value other than a
+/// factorisation or text is, so that arithmetic can be applied to it.
+fn number(value: Value) -> Result<Value, EvalError> {
+  match value {
+    Value::Factors(_) | Value::Text(_) => {
+      Err(EvalError::Type(ValueTypeError {
+        expected: "number",
+        found: value.type_name(),
+      }))
+    },
+    value => Ok(value),
+  }
+}
+
+/// Evaluate an integer literal, keeping only its low 64 bits if integers wrap.
+///
+/// Wrapping integers are 64 bits wide, so this truncates larger literals in the
+/// same way as casting to a narrower integer type.
+fn literal(n: &Natural, wrap: bool) -> Value {
+  if !wrap {
+    return Value::from(n.clone());
+  }
+
+  let low = n.clone() & Natural::from(u64::MAX);
+  Value::Integer(u64::try_from(low).expect("the low limb fits in a u64") as i64)
+}
+
+/// Negate a value.
+///
+/// Negative integers must fit in an `i64`, so the only large integer that can
+/// be negated is 2^63, whose negation is `i64::MIN`.
+fn negate(value: Value, wrap: bool) -> Result<Value, EvalError> {
+  let value = number(value)?;
+  Ok(match value {
+    Value::Integer(n) if wrap => Value::Integer(n.wrapping_neg()),
+    Value::Integer(_) | Value::Natural(_) => {
+      let n = value.to_integer().expect("value should be an integer");
+      return Value::from_integer(-n);
+    },
+    Value::Rational(numer, denom) => Value::Rational(-numer, denom),
+    Value::Float(x) => Value::Float(-x),
+    Value::Factors(_) | Value::Text(_) => unreachable!("not a number"),
+  })
+}
+
+/// Add two values.
+///
+/// This and the other arithmetic operations are kept out of [`eval_scoped`] for
+/// the same reason as [`call_builtin`].
+fn add(l: Value, r: Value, wrap: bool) -> Result<Value, EvalError> {
+  if let Some(((a, b), (c, d))) = ratios(&l, &r) {
+    let (b, d) = (Integer::from(b), Integer::from(d));
+    return Value::ratio(a * d.clone() + c * b.clone(), b * d, wrap);
+  }
+
+  arith(
+    l,
+    r,
+    |a, b| if wrap { Some(a.wrapping_add(b)) } else { a.checked_add(b) },
+    |a, b| Some(a + b),
+    |a, b| a + b,
+  )
+}
+
+/// Subtract one value from another.
+fn sub(l: Value, r: Value, wrap: bool) -> Result<Value, EvalError> {
+  if let Some(((a, b), (c, d))) = ratios(&l, &r) {
+    let (b, d) = (Integer::from(b), Integer::from(d));
+    return Value::ratio(a * d.clone() - c * b.clone(), b * d, wrap);
+  }
+
+  arith(
+    l,
+    r,
+    |a, b| if wrap { Some(a.wrapping_sub(b)) } else { a.checked_sub(b) },
+    |a, b| Some(a - b),
+    |a, b| a - b,
+  )
+}
+
+/// Multiply two values.
+fn mul(l: Value, r: Value, wrap: bool) -> Result<Value, EvalError> {
+  if let Some(((a, b), (c, d))) = ratios(&l, &r) {
+    return Value::ratio(a * c, Integer::from(b * d), wrap);
+  }
+
+  arith(
+    l,
+    r,
+    |a, b| if wrap { Some(a.wrapping_mul(b)) } else { a.checked_mul(b) },
+    |a, b| Some(a * b),
+    |a, b| a * b,
+  )
+}
+
+/// Divide one value by another, where `mode` decides what happens when one
+/// integer does not divide another exactly.
+///
+/// Dividing by the integer zero is an error, but dividing by the float zero
+/// gives an infinity or NaN, as usual for floats.
+fn div(
+  l: Value,
+  r: Value,
+  mode: DivMode,
+  wrap: bool,
+) -> Result<Value, EvalError> {
+  if matches!(l, Value::Float(_)) || matches!(r, Value::Float(_)) {
+    return Ok(Value::Float(l.to_f64() / r.to_f64()));
+  }
+  if r == Value::Integer(0) {
+    return Err(EvalError::DivisionByZero);
+  }
+
+  let rational =
+    matches!(l, Value::Rational(..)) || matches!(r, Value::Rational(..));
+  if mode == DivMode::Rational || rational {
+    let (Some((a, b)), Some((c, d))) = (l.to_ratio(), r.to_ratio()) else {
+      unreachable!("neither operand is a float");
+    };
+    return Value::ratio(a * Integer::from(d), Integer::from(b) * c, wrap);
+  }
+
+  let (Some(a), Some(b)) = (l.to_integer(), r.to_integer()) else {
+    unreachable!("only integers remain");
+  };
+  let quotient = match mode {
+    DivMode::Truncate | DivMode::Float => {
+      let (quotient, remainder) = a.div_rem(b);
+      if mode == DivMode::Float && remainder.signum() != 0 {
+        return Ok(Value::Float(l.to_f64() / r.to_f64()));
+      }
+      quotient
+    },
+    DivMode::Floor => a.div_floor(b),
+    DivMode::Ceil => a.div_ceil(b),
+    DivMode::RoundHalfEven => a.div_round_half_even(b),
+    DivMode::RoundHalfUp => a.div_round_half_up(b),
+    DivMode::Rational => unreachable!("rational quotients are handled above"),
+  };
+
+  // The only quotient of two `i64`s that overflows is `i64::MIN / -1`, which
+  // wraps around to itself.
+  integer_result(quotient, wrap)
+}
+
+/// Divide one value by another, rounding the quotient down towards negative
+/// infinity.
+///
+/// Unlike `/`, the quotient of integers or rationals is always an integer
+/// whatever the division mode, so that `7 // 2 = 3` and `-7 // 2 = -4`. The
+/// quotient of floats is rounded down to a whole float, as in Python.
+fn floor_div(l: Value, r: Value, wrap: bool) -> Result<Value, EvalError> {
+  if matches!(l, Value::Float(_)) || matches!(r, Value::Float(_)) {
+    return Ok(Value::Float((l.to_f64() / r.to_f64()).floor()));
+  }
+  if r == Value::Integer(0) {
+    return Err(EvalError::DivisionByZero);
+  }
+
+  if matches!(l, Value::Rational(..)) || matches!(r, Value::Rational(..)) {
+    let (Some((a, b)), Some((c, d))) = (l.to_ratio(), r.to_ratio()) else {
+      unreachable!("neither operand is a float");
+    };
+    // a/b // c/d = ad // bc, where bc is nonzero since c/d is.
+    let (ad, bc) = (a * Integer::from(d), Integer::from(b) * c);
+    return integer_result(ad.div_floor(bc), wrap);
+  }
+
+  let (Some(a), Some(b)) = (l.to_integer(), r.to_integer()) else {
+    unreachable!("only integers remain");
+  };
+  integer_result(a.div_floor(b), wrap)
+}
+
+/// Convert the result of an integer operation to a value, keeping only its low
+/// 64 bits in two's complement if integers wrap.
+fn integer_result(n: Integer, wrap: bool) -> Result<Value, EvalError> {
+  if !wrap {
+    return Value::from_integer(n);
+  }
+
+  // The low bits of the negation of an integer are the wrapping negation of
+  // its low bits.
+  let Value::Integer(low) = literal(&n.abs(), true) else {
+    unreachable!("wrapped literals should fit in an i64");
+  };
+  Ok(Value::Integer(if n.is_negative() { low.wrapping_neg() } else { low }))
+}
+
+/// Take the remainder of dividing one value by another.
+///
+/// As with Rust's `%`, the remainder has the same sign as the dividend, so that
+/// `l = (l / r) * r + l % r` when the quotient is truncated towards zero. For
+/// example, `-7 % 2 = -1` and `7 % -2 = 1`. The remainder is exact, so it
+/// does not depend on the division mode. As for division, floats follow IEEE
+/// 754, so a float remainder by zero is NaN rather than an error.
+fn rem(l: Value, r: Value, wrap: bool) -> Result<Value, EvalError> {
+  if matches!(l, Value::Float(_)) || matches!(r, Value::Float(_)) {
+    return Ok(Value::Float(l.to_f64() % r.to_f64()));
+  }
+  if r == Value::Integer(0) {
+    return Err(EvalError::DivisionByZero);
+  }
+
+  if matches!(l, Value::Rational(..)) || matches!(r, Value::Rational(..)) {
+    let (Some((a, b)), Some((c, d))) = (l.to_ratio(), r.to_ratio()) else {
+      unreachable!("neither operand is a float");
+    };
+    // a/b % c/d = (ad % bc) / bd, where bc is nonzero since c/d is.
+    let (ad, bc) = (a * Integer::from(d.clone()), Integer::from(b.clone()) * c);
+    let (_, remainder) = ad.div_rem(bc);
+    return Value::ratio(remainder, Integer::from(b * d), wrap);
+  }
+
+  // The only remainder of two `i64`s that overflows is `i64::MIN % -1`, which
+  // is mathematically zero, and that is what wrapping gives.
+  if let (Value::Integer(a), Value::Integer(b)) = (&l, &r) {
+    return Ok(Value::Integer(a.wrapping_rem(*b)));
+  }
+
+  let (Some(a), Some(b)) = (l.to_integer(), r.to_integer()) else {
+    unreachable!("only integers remain");
+  };
+  let remainder = Value::from(a.abs() % b.abs());
+  if a.is_negative() { negate(remainder, false) } else { Ok(remainder) }
+}
+
+/// Take the remainder of the Euclidean division of one value by another.
+///
+/// Unlike [`rem`], the remainder is never negative, whatever the signs of the
+/// operands, so that `-7 mod 3 = 2` where `-7 % 3 = -1`. This is the result
+/// that modular arithmetic expects.
+fn modulo(l: Value, r: Value, wrap: bool) -> Result<Value, EvalError> {
+  if matches!(l, Value::Float(_)) || matches!(r, Value::Float(_)) {
+    return Ok(Value::Float(l.to_f64().rem_euclid(r.to_f64())));
+  }
+  if r == Value::Integer(0) {
+    return Err(EvalError::DivisionByZero);
+  }
+
+  if matches!(l, Value::Rational(..)) || matches!(r, Value::Rational(..)) {
+    let (Some((a, b)), Some((c, d))) = (l.to_ratio(), r.to_ratio()) else {
+      unreachable!("neither operand is a float");
+    };
+    // a/b mod c/d = (ad mod bc) / bd, where bc is nonzero since c/d is.
+    let (ad, bc) = (a * Integer::from(d.clone()), Integer::from(b.clone()) * c);
+    return Value::ratio(ad.rem_euclid(bc), Integer::from(b * d), wrap);
+  }
+
+  let (Some(a), Some(b)) = (l.to_integer(), r.to_integer()) else {
+    unreachable!("only integers remain");
+  };
+  Value::from_integer(a.rem_euclid(b))
+}
+
+/// Raise a value to a power.
+///
+/// Following the standard library, 0^0 = 1 for both integers and floats, which
+/// is the convention that makes the empty product work. An integer cannot be
+/// raised to a negative integer power, since the result is not an integer, but
+/// a fraction can, since that is a power of its reciprocal.
+fn pow(base: Value, exp: Value, wrap: bool) -> Result<Value, EvalError> {
+  if let Value::Integer(exp) = exp
+    && exp < 0
+    && matches!(base, Value::Integer(_) | Value::Natural(_))
+  {
+    return Err(EvalError::NegativeExponent(exp));
+  }
+
+  match (&base, &exp) {
+    // As for integers, exponents beyond the range of a `u32` are out of range.
+    (Value::Rational(numer, denom), Value::Integer(exp)) => {
+      let power = u32::try_from(exp.unsigned_abs())
+        .map_err(|_| EvalError::Overflow)?
+        .into();
+      let numer = numer.clone().pow(power);
+      let denom = Integer::from(denom.clone().pow(power));
+      return if *exp < 0 {
+        Value::ratio(denom, numer, wrap)
+      } else {
+        Value::ratio(numer, denom, wrap)
+      };
+    },
+    // Only a fraction of magnitude 1 has a power this large that is in range,
+    // and that is 1 or -1 depending on the parity of the exponent. Fractions
+    // are kept in lowest terms with a denominator greater than 1, so this only
+    // guards against the base being ±1 in disguise.
+    (Value::Rational(numer, denom), Value::Natural(exp)) => {
+      if numer.abs() != *denom {
+        return Err(EvalError::Overflow);
+      }
+
+      let negative = numer.is_negative() && !exp.is_even();
+      return Ok(Value::Integer(if negative { -1 } else { 1 }));
+    },
+    // A rational power is generally irrational, so it can only be
+    // approximated by a float.
+    (_, Value::Rational(..)) => {
+      return Ok(Value::Float(base.to_f64().powf(exp.to_f64())));
     },
+    _ => {},
+  }
+
+  arith(
+    base,
+    exp,
+    |b, e| {
+      let e = u64::try_from(e).expect("exponent should be nonnegative");
+      if wrap {
+        Some(wrapping_pow(b, e))
+      } else {
+        u32::try_from(e).ok().and_then(|e| b.checked_pow(e))
+      }
+    },
+    // Exponents beyond the range of a `u32` are out of range for any base but
+    // 0, 1 and -1, since the result would take at least 512 MiB of memory.
+    |b, e| match u64::try_from(e.abs()) {
+      Ok(e) if e <= u64::from(u32::MAX) => Some(b.pow(e)),
+      _ if b.abs() <= Natural::ONE => {
+        Some(if e.abs().is_even() { Integer::from(b.abs()) } else { b })
+      },
+      _ => None,
+    },
+    f64::powf,
+  )
+}
+
+/// Raise an integer to a power, wrapping around on overflow.
+///
+/// Unlike [`i64::wrapping_pow`], this accepts exponents that do not fit in a
+/// `u32`.
+fn wrapping_pow(mut base: i64, mut exp: u64) -> i64 {
+  let mut result: i64 = 1;
+  while exp > 0 {
+    if exp & 1 == 1 {
+      result = result.wrapping_mul(base);
+    }
+    base = base.wrapping_mul(base);
+    exp >>= 1;
+  }
+
+  result
+}
+
+/// Call the built-in function with the given name.
+///
+/// This is kept out of [`eval_scoped`] so as not to grow its stack frame, which
+/// would lower the depth of user-defined function calls that fit on the stack.
+fn call_builtin<'env>(
+  name: &str,
+  args: &[Expr],
+  env: &'env Environment,
+  scope: &HashMap<&'env str, Value>,
+  depth: usize,
+) -> Result<Value, EvalError> {
+  let &(_, arity) = BUILTINS
+    .iter()
+    .find(|(builtin, _)| *builtin == name)
+    .ok_or_else(|| EvalError::UnknownFunction(name.to_string()))?;
+
+  if let Some(arity) = arity
+    && args.len() != arity
+  {
+    return Err(EvalError::ArityMismatch {
+      name: name.to_string(),
+      expected: arity,
+      found: args.len(),
+    });
+  }
+
+  let args = args
+    .iter()
+    .map(|arg| eval_scoped(arg, env, scope, depth).and_then(number))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  match (name, &args[..]) {
+    ("randint", [low, high]) => {
+      let (low, high) =
+        (i64::try_from(low.clone())?, i64::try_from(high.clone())?);
+      if low > high {
+        return Err(EvalError::EmptyRange { low, high });
+      }
+
+      Ok(Value::Integer(env.rng.int_between(low, high)))
+    },
+    // These are folds of addition and multiplication, so they follow the same
+    // rules, and the sum and product of no values are the identities 0 and 1.
+    ("sum", args) => args
+      .iter()
+      .try_fold(Value::Integer(0), |sum, x| add(sum, x.clone(), env.wrapping)),
+    ("prod", args) => args.iter().try_fold(Value::Integer(1), |product, x| {
+      mul(product, x.clone(), env.wrapping)
+    }),
+    ("abs", [x]) => match x {
+      Value::Rational(numer, denom) => {
+        Ok(Value::Rational(Integer::from(numer.abs()), denom.clone()))
+      },
+      Value::Float(x) => Ok(Value::Float(x.abs())),
+      x => {
+        let n = x.to_integer().expect("x should be an integer");
+        Ok(Value::from(n.abs()))
+      },
+    },
+    // Unlike `f64::signum`, the sign of a float zero is zero.
+    ("signum", [x]) => match x {
+      Value::Rational(numer, _) => Ok(Value::Integer(numer.signum().into())),
+      Value::Float(x) if *x == 0.0 => Ok(Value::Float(*x)),
+      Value::Float(x) => Ok(Value::Float(x.signum())),
+      x => {
+        let n = x.to_integer().expect("x should be an integer");
+        Ok(Value::Integer(n.signum().into()))
+      },
+    },
+    ("gcd", [a, b]) => {
+      let (a, b) =
+        (Integer::try_from(a.clone())?, Integer::try_from(b.clone())?);
+      Ok(Value::from(a.gcd(&b)))
+    },
+    ("factor", [x]) => {
+      let n = Integer::try_from(x.clone())?;
+      match u64::try_from(n.abs()) {
+        Ok(m) if m > 0 && !n.is_negative() => Ok(Value::Factors(factorise(m))),
+        _ => Err(EvalError::CannotFactorise(x.clone())),
+      }
+    },
+    ("roman", [x]) => {
+      let numeral = match x {
+        Value::Integer(n) => format_roman(*n),
+        Value::Natural(_) => None,
+        x => {
+          return Err(EvalError::Type(ValueTypeError {
+            expected: "integer",
+            found: x.type_name(),
+          }));
+        },
+      };
+
+      numeral.map(Value::Text).ok_or_else(|| EvalError::NotRoman(x.clone()))
+    },
+    _ => unreachable!("every built-in function should be implemented"),
+  }
+}
+
+/// The operands of an arithmetic operation as fractions, if either is a
+/// rational and neither is a float.
+fn ratios(
+  l: &Value,
+  r: &Value,
+) -> Option<((Integer, Natural), (Integer, Natural))> {
+  if !matches!(l, Value::Rational(..)) && !matches!(r, Value::Rational(..)) {
+    return None;
+  }
+
+  l.to_ratio().zip(r.to_ratio())
+}
+
+/// Convert a natural number to the nearest float, or to infinity if it is too
+/// large.
+///
+/// Parsing the decimal representation rounds correctly, which is simpler than
+/// rounding the limbs by hand.
+fn natural_to_f64(n: &Natural) -> f64 {
+  n.to_string().parse().expect("a natural should be a valid float")
+}
+
+/// Apply a binary arithmetic operation to two values.
+///
+/// If either value is a float, both values are converted to floats and the
+/// float operation is used. Otherwise, the `i64` operation is used if both
+/// values fit in an `i64`, which returns `None` on overflow. Failing that, the
+/// arbitrary-precision operation is used, which returns `None` if the result
+/// would be too large to compute. Only a result that is too large or that is
+/// negative and does not fit in an `i64` is out of range.
+fn arith(
+  l: Value,
+  r: Value,
+  int_op: impl FnOnce(i64, i64) -> Option<i64>,
+  integer_op: impl FnOnce(Integer, Integer) -> Option<Integer>,
+  float_op: impl FnOnce(f64, f64) -> f64,
+) -> Result<Value, EvalError> {
+  if matches!(l, Value::Float(_)) || matches!(r, Value::Float(_)) {
+    return Ok(Value::Float(float_op(l.to_f64(), r.to_f64())));
+  }
+
+  if let (Value::Integer(a), Value::Integer(b)) = (&l, &r)
+    && let Some(n) = int_op(*a, *b)
+  {
+    return Ok(Value::Integer(n));
+  }
+
+  let (Some(a), Some(b)) = (l.to_integer(), r.to_integer()) else {
+    unreachable!("only integers remain");
+  };
+  integer_op(a, b).ok_or(EvalError::Overflow).and_then(Value::from_integer)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::syntax::parse;
+
+  fn parse_ok(input: &str) -> Expr {
+    parse(input).into_result().expect("input should parse")
+  }
+
+  fn eval_str(input: &str) -> Result<Value, EvalError> {
+    eval(&parse_ok(input))
+  }
+
+  fn rational(numer: i64, denom: u64) -> Value {
+    Value::Rational(Integer::from(numer), Natural::from(denom))
+  }
+
+  /// Parse a function definition and add it to the environment.
+  fn define(env: &mut Environment, input: &str) {
+    let Expr::FnDef(name, params, body) = parse_ok(input) else {
+      panic!("{input:?} should parse as a function definition");
+    };
+    env.define_function(name, params, *body);
+  }
+
+  #[test]
+  fn test_value_conversions() {
+    assert_eq!(Value::from(42), Value::Integer(42));
+    assert_eq!(i64::try_from(Value::from(-7)), Ok(-7));
+    assert_eq!(Value::from(1.5), Value::Float(1.5));
+    assert_eq!(f64::try_from(Value::from(1.5)), Ok(1.5));
+  }
+
+  #[test]
+  fn test_value_conversion_type_mismatch() {
+    assert_eq!(
+      i64::try_from(Value::Float(1.0)),
+      Err(ValueTypeError { expected: "integer", found: "float" })
+    );
+    assert_eq!(
+      f64::try_from(Value::Integer(1)),
+      Err(ValueTypeError { expected: "float", found: "integer" })
+    );
+  }
+
+  #[test]
+  fn test_scientific_literals() {
+    assert_eq!(eval_str("1e6"), Ok(Value::Integer(1_000_000)));
+    assert_eq!(eval_str("1e-3"), Ok(Value::Float(0.001)));
+    assert_eq!(eval_str("2e3 + 1"), Ok(Value::Integer(2001)));
+    assert_eq!(eval_str("2e-1 * 10"), Ok(Value::Float(2.0)));
+  }
+
+  #[test]
+  fn test_pow_zero() {
+    assert_eq!(eval_str("0 ^ 0"), Ok(Value::Integer(1)));
+    assert_eq!(eval_str("0 ^ 5"), Ok(Value::Integer(0)));
+    assert_eq!(eval_str("5 ^ 0"), Ok(Value::Integer(1)));
+    assert_eq!(eval_str("1 + 0 ^ 0"), Ok(Value::Integer(2)));
+    assert_eq!(eval_str("0.0 ^ 0"), Ok(Value::Float(1.0)));
+    assert_eq!(eval_str("0.0 ^ 5"), Ok(Value::Float(0.0)));
+  }
+
+  #[test]
+  fn test_constants() {
+    assert_eq!(eval_str("pi"), Ok(Value::Float(consts::PI)));
+    assert_eq!(eval_str("2 * pi"), Ok(Value::Float(consts::TAU)));
+    assert_eq!(eval_str("e"), Ok(Value::Float(consts::E)));
+    assert_eq!(
+      eval_str("pie"),
+      Err(EvalError::UnknownVariable("pie".to_string()))
+    );
+
+    let mut env = Environment::default();
+    define(&mut env, "f(pi) = pi + 1");
+    assert_eq!(eval_with(&parse_ok("f(2)"), &env), Ok(Value::Integer(3)));
+  }
+
+  #[test]
+  fn test_inf_and_nan() {
+    assert_eq!(eval_str("inf + 1"), Ok(Value::Float(f64::INFINITY)));
+    assert_eq!(eval_str("-inf * 2"), Ok(Value::Float(f64::NEG_INFINITY)));
+    assert_eq!(eval_str("1 * inf ^ (-1)"), Ok(Value::Float(0.0)));
+    assert!(matches!(eval_str("inf - inf"), Ok(Value::Float(x)) if x.is_nan()));
+
+    // NaN is not equal to itself, even as the same value.
+    let nan = eval_str("nan").unwrap();
+    assert!(matches!(nan, Value::Float(x) if x.is_nan()));
+    assert_ne!(nan, nan);
+  }
+
+  #[test]
+  fn test_div_mode_names() {
+    for mode in DivMode::ALL {
+      assert_eq!(DivMode::from_name(mode.name()), Some(mode));
+    }
+    assert_eq!(DivMode::from_name("float"), Some(DivMode::Float));
+    assert_eq!(DivMode::from_name("floor"), Some(DivMode::Floor));
+    assert_eq!(
+      DivMode::from_name("round-half-even"),
+      Some(DivMode::RoundHalfEven)
+    );
+    assert_eq!(DivMode::from_name("round"), None);
+
+    let mut env = Environment::default();
+    assert_eq!(env.div_mode(), DivMode::Truncate);
+    env.set_div_mode(DivMode::Rational);
+    assert_eq!(env.div_mode(), DivMode::Rational);
+  }
+
+  /// Evaluate an expression with the given division mode.
+  fn eval_div(input: &str, mode: DivMode) -> Result<Value, EvalError> {
+    let mut env = Environment::default();
+    env.set_div_mode(mode);
+    eval_with(&parse_ok(input), &env)
+  }
+
+  #[test]
+  fn test_div() {
+    let natural = |s: &str| Value::Natural(s.parse().unwrap());
+    let cases = [
+      ("6 / 2", Value::Integer(3)),
+      ("7 / 2", Value::Integer(3)),
+      ("-7 / 2", Value::Integer(-3)),
+      ("7 / -2", Value::Integer(-3)),
+      ("8 / 4 / 2", Value::Integer(1)),
+      ("1 + 6 / 2 * 3", Value::Integer(10)),
+      ("7 / 2.0", Value::Float(3.5)),
+      ("1.0 / 0", Value::Float(f64::INFINITY)),
+      ("36893488147419103232 / 2", natural("18446744073709551616")),
+      ("18446744073709551616 / -2", Value::Integer(i64::MIN)),
+      ("(-9223372036854775807 - 1) / -1", natural("9223372036854775808")),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_str(input), Ok(expected), "{input}");
+    }
+  }
+
+  #[test]
+  fn test_div_by_zero() {
+    for mode in DivMode::ALL {
+      for input in ["1 / 0", "0 / 0", "18446744073709551616 / (1 - 1)"] {
+        assert_eq!(
+          eval_div(input, mode),
+          Err(EvalError::DivisionByZero),
+          "{input} in {} mode",
+          mode.name()
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn test_floor_div() {
+    let natural = |s: &str| Value::Natural(s.parse().unwrap());
+    let cases = [
+      ("7 // 2", Value::Integer(3)),
+      ("6 // 2", Value::Integer(3)),
+      ("(-7) // 2", Value::Integer(-4)),
+      ("7 // -2", Value::Integer(-4)),
+      ("(-7) // -2", Value::Integer(3)),
+      ("(-6) // 2", Value::Integer(-3)),
+      ("0 // -5", Value::Integer(0)),
+      ("7.5 // 2", Value::Float(3.0)),
+      ("(-7.5) // 2", Value::Float(-4.0)),
+      ("(-9223372036854775807 - 1) // -1", natural("9223372036854775808")),
+      ("36893488147419103233 // 2", natural("18446744073709551616")),
+      ("(-1) // 18446744073709551616", Value::Integer(-1)),
+      ("18446744073709551615 // -2", Value::Integer(i64::MIN)),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_str(input), Ok(expected), "{input}");
+    }
+
+    // The quotient is an integer whatever the division mode.
+    for mode in DivMode::ALL {
+      assert_eq!(eval_div("7 // 2", mode), Ok(Value::Integer(3)));
+      assert_eq!(eval_div("(-7) // 2", mode), Ok(Value::Integer(-4)));
+    }
+    assert_eq!(
+      eval_div("(7 / 2) // (1 / 3)", DivMode::Rational),
+      Ok(Value::Integer(10))
+    );
+    assert_eq!(
+      eval_div("(-7 / 2) // 1", DivMode::Rational),
+      Ok(Value::Integer(-4))
+    );
+  }
+
+  #[test]
+  fn test_floor_div_by_zero() {
+    for mode in DivMode::ALL {
+      for input in ["5 // 0", "0 // 0", "18446744073709551616 // (1 - 1)"] {
+        assert_eq!(
+          eval_div(input, mode),
+          Err(EvalError::DivisionByZero),
+          "{input} in {} mode",
+          mode.name()
+        );
+      }
+    }
+    assert_eq!(eval_str("-1 // 0.0"), Ok(Value::Float(f64::NEG_INFINITY)));
+  }
+
+  #[test]
+  fn test_rem() {
+    let natural = |s: &str| Value::Natural(s.parse().unwrap());
+    let cases = [
+      ("10 % 3", Value::Integer(1)),
+      ("10 % 5", Value::Integer(0)),
+      ("(-7) % 2", Value::Integer(-1)),
+      ("7 % -2", Value::Integer(1)),
+      ("(-7) % -2", Value::Integer(-1)),
+      ("1 + 10 % 4 * 3", Value::Integer(7)),
+      ("7.5 % 2", Value::Float(1.5)),
+      ("(-9223372036854775807 - 1) % -1", Value::Integer(0)),
+      ("36893488147419103233 % 36893488147419103232", Value::Integer(1)),
+      ("73786976294838206465 % 36893488147419103232", Value::Integer(1)),
+      ("(2 ^ 65 + 1) % 2 ^ 64", Value::Integer(1)),
+      ("(2 ^ 64 + 1) % 3", Value::Integer(2)),
+      ("(-5) % 18446744073709551616", Value::Integer(-5)),
+      ("(2 ^ 66) % (2 ^ 65 + 1)", natural("36893488147419103231")),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_str(input), Ok(expected), "{input}");
+    }
+
+    // The remainder is exact, so the division mode makes no difference.
+    assert_eq!(eval_div("10 % 3", DivMode::Float), Ok(Value::Integer(1)));
+    assert_eq!(eval_div("(7 / 2) % 1", DivMode::Rational), Ok(rational(1, 2)));
+    assert_eq!(
+      eval_div("(-7 / 2) % (2 / 3)", DivMode::Rational),
+      Ok(rational(-1, 6))
+    );
+  }
+
+  #[test]
+  fn test_rem_by_zero() {
+    for mode in DivMode::ALL {
+      for input in ["5 % 0", "0 % 0", "18446744073709551616 % (1 - 1)"] {
+        assert_eq!(
+          eval_div(input, mode),
+          Err(EvalError::DivisionByZero),
+          "{input} in {} mode",
+          mode.name()
+        );
+      }
+    }
+    assert!(matches!(eval_str("5 % 0.0"), Ok(Value::Float(x)) if x.is_nan()));
+  }
+
+  #[test]
+  fn test_modulo() {
+    let natural = |s: &str| Value::Natural(s.parse().unwrap());
+    let cases = [
+      ("10 mod 3", Value::Integer(1)),
+      ("(-7) % 3", Value::Integer(-1)),
+      ("(-7) mod 3", Value::Integer(2)),
+      ("7 mod -3", Value::Integer(1)),
+      ("(-7) mod -3", Value::Integer(2)),
+      ("(-6) mod 3", Value::Integer(0)),
+      ("(-1) mod 18446744073709551616", natural("18446744073709551615")),
+      ("(-9223372036854775807 - 1) mod -1", Value::Integer(0)),
+      ("(-7.5) mod 2", Value::Float(0.5)),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_str(input), Ok(expected), "{input}");
+    }
+
+    assert_eq!(
+      eval_div("(-7 / 2) mod (2 / 3)", DivMode::Rational),
+      Ok(rational(1, 2))
+    );
+    for mode in DivMode::ALL {
+      assert_eq!(eval_div("5 mod 0", mode), Err(EvalError::DivisionByZero));
+    }
+  }
+
+  #[test]
+  fn test_div_rounding_modes() {
+    // Each case lists the quotient when truncating, rounding down, rounding
+    // up, rounding half to even, and rounding half up.
+    let cases = [
+      ("7 / 2", [3, 3, 4, 4, 4]),
+      ("5 / 2", [2, 2, 3, 2, 3]),
+      ("(-5) / 2", [-2, -3, -2, -2, -3]),
+      ("(-7) / 2", [-3, -4, -3, -4, -4]),
+      ("7 / -3", [-2, -3, -2, -2, -2]),
+      ("(-8) / -3", [2, 2, 3, 3, 3]),
+      ("6 / 3", [2, 2, 2, 2, 2]),
+    ];
+    let modes = [
+      DivMode::Truncate,
+      DivMode::Floor,
+      DivMode::Ceil,
+      DivMode::RoundHalfEven,
+      DivMode::RoundHalfUp,
+    ];
+
+    for (input, quotients) in cases {
+      for (mode, quotient) in modes.into_iter().zip(quotients) {
+        assert_eq!(
+          eval_div(input, mode),
+          Ok(Value::Integer(quotient)),
+          "{input} in {} mode",
+          mode.name()
+        );
+      }
+    }
+
+    // Quotients beyond the range of an `i64` are rounded too.
+    assert_eq!(
+      eval_div("18446744073709551617 / 2", DivMode::Ceil),
+      Ok(Value::Natural("9223372036854775809".parse().unwrap()))
+    );
+    assert_eq!(
+      eval_div("(-1) / 18446744073709551616", DivMode::Floor),
+      Ok(Value::Integer(-1))
+    );
+  }
+
+  #[test]
+  fn test_div_float_mode() {
+    assert_eq!(eval_div("7 / 2", DivMode::Float), Ok(Value::Float(3.5)));
+    assert_eq!(eval_div("-1 / 4", DivMode::Float), Ok(Value::Float(-0.25)));
+    // Quotients that are integers stay integers.
+    assert_eq!(eval_div("6 / 2", DivMode::Float), Ok(Value::Integer(3)));
+  }
+
+  #[test]
+  fn test_div_rational_mode() {
+    let cases = [
+      ("7 / 2", rational(7, 2)),
+      ("6 / 4", rational(3, 2)),
+      ("1 / -2", rational(-1, 2)),
+      ("-(1 / 2)", rational(-1, 2)),
+      ("6 / 3", Value::Integer(2)),
+      ("1 / 2 + 1 / 3", rational(5, 6)),
+      ("1 / 2 - 1 / 3", rational(1, 6)),
+      ("1 / 2 * 2", Value::Integer(1)),
+      ("(2 / 3) / (4 / 9)", rational(3, 2)),
+      ("(2 / 3) ^ 2", rational(4, 9)),
+      ("1 / 2 + 0.25", Value::Float(0.75)),
+      ("4 ^ (1 / 2)", Value::Float(2.0)),
+      ("sum(1 / 2, 1 / 4, 1 / 4)", Value::Integer(1)),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_div(input, DivMode::Rational), Ok(expected), "{input}");
+    }
+
+    assert_eq!(rational(-7, 2).to_string(), "-7/2");
+  }
+
+  #[test]
+  fn test_div_rational_mode_large() {
+    let fraction = |numer: &str, denom: &str| {
+      let (numer, denom): (Natural, Natural) =
+        (numer.parse().unwrap(), denom.parse().unwrap());
+      Value::Rational(Integer::from(numer), denom)
+    };
+    let cases = [
+      ("2 ^ 70 / 3", fraction("1180591620717411303424", "3")),
+      (
+        "1 / 9223372036854775807 / 9223372036854775807",
+        fraction("1", "85070591730234615847396907784232501249"),
+      ),
+      (
+        "(2 ^ 70 / 3) * 3",
+        Value::Natural("1180591620717411303424".parse().unwrap()),
+      ),
+      ("2 ^ 70 / 3 - 2 ^ 70 / 3", Value::Integer(0)),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_div(input, DivMode::Rational), Ok(expected), "{input}");
+    }
+
+    assert_eq!(
+      eval_div("2 ^ 70 / 3", DivMode::Rational).map(|x| x.to_string()),
+      Ok("1180591620717411303424/3".to_string())
+    );
+
+    // Negative fractions can be large, but negative integers must still fit in
+    // an `i64`.
+    assert_eq!(
+      eval_div("(-1) / 3 * 2 ^ 70", DivMode::Rational).map(|x| x.to_string()),
+      Ok("-1180591620717411303424/3".to_string())
+    );
+    assert_eq!(
+      eval_div("(-1) / 3 * 2 ^ 70 * 3", DivMode::Rational),
+      Err(EvalError::Overflow)
+    );
+    assert_eq!(
+      eval_div("(2 ^ 2000 + 1) / 2 ^ 2000", DivMode::Rational)
+        .map(|x| x.to_f64()),
+      Ok(1.0)
+    );
+  }
+
+  #[test]
+  fn test_pow_rational_negative_exponent() {
+    let cases = [
+      ("(1 / 2) ^ (-1)", Value::Integer(2)),
+      ("(2 / 3) ^ -2", rational(9, 4)),
+      ("(-1 / 2) ^ -3", Value::Integer(-8)),
+      ("(-2 / 3) ^ -3", rational(-27, 8)),
+      ("(1 / 2) ^ 0", Value::Integer(1)),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_div(input, DivMode::Rational), Ok(expected), "{input}");
+    }
+
+    // Exponents beyond the range of an `i64` are out of range for fractions.
+    for input in ["(-1 / 2) ^ (2 ^ 64)", "(2 / 3) ^ 9223372036854775808"] {
+      assert_eq!(
+        eval_div(input, DivMode::Rational),
+        Err(EvalError::Overflow),
+        "{input}"
+      );
+    }
+
+    // Integers still cannot be raised to negative powers.
+    assert_eq!(
+      eval_div("2 ^ -1", DivMode::Rational),
+      Err(EvalError::NegativeExponent(-1))
+    );
+  }
+
+  #[test]
+  fn test_decimal_literals() {
+    assert_eq!(eval_str(".5 + .5"), Ok(Value::Float(1.0)));
+    assert_eq!(eval_str("5. * 2"), Ok(Value::Float(10.0)));
+  }
+
+  #[test]
+  fn test_large_integers() {
+    let natural = |s: &str| Value::Natural(s.parse().unwrap());
+    let cases = [
+      ("9223372036854775808", natural("9223372036854775808")),
+      ("18446744073709551616", natural("18446744073709551616")),
+      (
+        "123456789012345678901234567890",
+        natural("123456789012345678901234567890"),
+      ),
+      ("99999999999999999999 + 1", natural("100000000000000000000")),
+      ("3 * 10000000000000000000", natural("30000000000000000000")),
+      (
+        "18446744073709551616 ^ 2",
+        natural("340282366920938463463374607431768211456"),
+      ),
+      ("sum(1, 18446744073709551615)", natural("18446744073709551616")),
+      ("1e30 - 1", natural("999999999999999999999999999999")),
+      // Results that fit in an `i64` are ordinary integers again.
+      ("18446744073709551616 - 18446744073709551615", Value::Integer(1)),
+      ("-9223372036854775808", Value::Integer(i64::MIN)),
+      // Integer results that overflow an `i64` become large integers.
+      ("9223372036854775807 + 1", natural("9223372036854775808")),
+      ("9223372036854775807 * 4", natural("36893488147419103228")),
+      ("2 ^ 64", natural("18446744073709551616")),
+      ("-(-9223372036854775807 - 1)", natural("9223372036854775808")),
+      ("18446744073709551616 + 0.5", Value::Float(18446744073709551616.5)),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_str(input), Ok(expected), "{input}");
+    }
+  }
+
+  #[test]
+  fn test_mixed_sign_large_integers() {
+    let natural = |s: &str| Value::Natural(s.parse().unwrap());
+    let cases = [
+      ("(-1) + 18446744073709551616", natural("18446744073709551615")),
+      ("18446744073709551616 + (-1)", natural("18446744073709551615")),
+      ("18446744073709551616 - (-1)", natural("18446744073709551617")),
+      ("(-9223372036854775807 - 1) * (-1)", natural("9223372036854775808")),
+      ("(-2) * (-9223372036854775807)", natural("18446744073709551614")),
+      ("(-2) ^ 64", natural("18446744073709551616")),
+      ("(-1) ^ 18446744073709551616", Value::Integer(1)),
+      ("(-1) ^ 18446744073709551617", Value::Integer(-1)),
+      // Results that are negative again fit in an `i64`.
+      (
+        "(-5) + 18446744073709551616 - 18446744073709551616",
+        Value::Integer(-5),
+      ),
+      ("9223372036854775808 * (-1)", Value::Integer(i64::MIN)),
+      ("(-1) - 9223372036854775807", Value::Integer(i64::MIN)),
+      (
+        "sum(-1, 18446744073709551616, -9223372036854775807)",
+        natural("9223372036854775808"),
+      ),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_str(input), Ok(expected), "{input}");
+    }
+  }
+
+  #[test]
+  fn test_overflow() {
+    // Negative integers must fit in an `i64`.
+    for input in [
+      "1 - 18446744073709551616",
+      "-18446744073709551616",
+      "-9223372036854775807 - 2",
+      "-9223372036854775807 * 2",
+      "(-2) ^ 65",
+      "(-1) - 18446744073709551616",
+      "9223372036854775809 * (-1)",
+      "prod(-1, 9223372036854775807, 2)",
+      "2 ^ 4294967296",
+    ] {
+      assert_eq!(eval_str(input), Err(EvalError::Overflow), "{input}");
+    }
+
+    // Powers of 0 and 1 are still small, however large the exponent.
+    assert_eq!(eval_str("1 ^ 4294967296"), Ok(Value::Integer(1)));
+    assert_eq!(eval_str("0 ^ 18446744073709551616"), Ok(Value::Integer(0)));
+  }
+
+  #[test]
+  fn test_negative_exponent() {
+    assert_eq!(eval_str("2 ^ -3"), Err(EvalError::NegativeExponent(-3)));
+    assert_eq!(
+      eval_str("18446744073709551616 ^ -1"),
+      Err(EvalError::NegativeExponent(-1))
+    );
+    assert_eq!(
+      EvalError::NegativeExponent(-3).to_string(),
+      "Cannot raise an integer to the negative power -3"
+    );
+
+    // Floats can be raised to negative powers.
+    assert_eq!(eval_str("2.0 ^ -3"), Ok(Value::Float(0.125)));
+    assert_eq!(eval_str("2 ^ -3.0"), Ok(Value::Float(0.125)));
+  }
+
+  #[test]
+  fn test_wrapping_arithmetic() {
+    let mut env = Environment::default();
+    env.set_wrapping(true);
+
+    let cases = [
+      ("9223372036854775807 + 1", i64::MIN),
+      ("-9223372036854775807 - 2", i64::MAX),
+      ("9223372036854775807 * 2", -2),
+      ("2 ^ 64", 0),
+      ("2 ^ 63", i64::MIN),
+      ("-(-9223372036854775807 - 1)", i64::MIN),
+      ("1 + 2 * 3", 7),
+      ("3 ^ 4294967297", 3_i64.wrapping_pow(u32::MAX).wrapping_mul(9)),
+      ("18446744073709551617", 1),
+      ("18446744073709551615 + 2", 1),
+      ("(-9223372036854775807 - 1) // -1", i64::MIN),
+      ("(-9223372036854775807 - 1) // 3", -3074457345618258603),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(
+        eval_with(&parse_ok(input), &env),
+        Ok(Value::Integer(expected)),
+        "{input}"
+      );
+    }
+
+    // Fractions are exact, but integers that arithmetic on them gives wrap.
+    env.set_div_mode(DivMode::Rational);
+    let cases = [
+      ("(9223372036854775807 / 2) * 4", Value::Integer(-2)),
+      ("((-9223372036854775807) / 2) * 4", Value::Integer(2)),
+      ("(1 / 2) ^ -64", Value::Integer(0)),
+      (
+        "((-9223372036854775807) / 2 * 3) // 1",
+        Value::Integer(4611686018427387905),
+      ),
+      (
+        "(9223372036854775807 / 2) * 3",
+        Value::Rational(
+          Integer::from("27670116110564327421".parse::<Natural>().unwrap()),
+          Natural::from(2_u64),
+        ),
+      ),
+    ];
+    for (input, expected) in cases {
+      assert_eq!(eval_with(&parse_ok(input), &env), Ok(expected), "{input}");
+    }
+  }
+
+  #[test]
+  fn test_convert() {
+    assert_eq!(eval_str("1 km in m"), Ok(Value::Float(1000.0)));
+    assert_eq!(eval_str("250 cm in m"), Ok(Value::Float(2.5)));
+    assert_eq!(eval_str("2 kg in g"), Ok(Value::Float(2000.0)));
+    assert_eq!(eval_str("(1 + 2) km in m"), Ok(Value::Float(3000.0)));
+  }
+
+  #[test]
+  fn test_convert_incompatible_units() {
+    assert_eq!(
+      eval_str("1 km in kg"),
+      Err(EvalError::IncompatibleUnits {
+        from: Unit::Kilometre,
+        to: Unit::Kilogram
+      })
+    );
+  }
+
+  #[test]
+  fn test_user_defined_function() {
+    let mut env = Environment::default();
+    define(&mut env, "f(x) = x*x");
+    define(&mut env, "g(x, y) = f(x) + f(y) + 1");
+    define(&mut env, "h() = 42");
+
+    assert_eq!(eval_with(&parse_ok("f(5)"), &env), Ok(Value::Integer(25)));
+    assert_eq!(eval_with(&parse_ok("g(2, 3)"), &env), Ok(Value::Integer(14)));
+    assert_eq!(eval_with(&parse_ok("f(h())"), &env), Ok(Value::Integer(1764)));
+  }
+
+  #[test]
+  fn test_user_defined_function_redefinition() {
+    let mut env = Environment::default();
+    define(&mut env, "f(x) = x + 1");
+    define(&mut env, "f(x) = x + 2");
+
+    assert_eq!(eval_with(&parse_ok("f(1)"), &env), Ok(Value::Integer(3)));
+  }
+
+  #[test]
+  fn test_user_defined_function_errors() {
+    let mut env = Environment::default();
+    define(&mut env, "f(x) = x*x");
+    define(&mut env, "g(x) = y");
+
+    assert_eq!(
+      eval_with(&parse_ok("f(1, 2)"), &env),
+      Err(EvalError::ArityMismatch {
+        name: "f".to_string(),
+        expected: 1,
+        found: 2
+      })
+    );
+    assert_eq!(
+      eval_with(&parse_ok("h(1)"), &env),
+      Err(EvalError::UnknownFunction("h".to_string()))
+    );
+    assert_eq!(
+      eval_with(&parse_ok("g(1)"), &env),
+      Err(EvalError::UnknownVariable("y".to_string()))
+    );
+    assert_eq!(
+      eval_with(&parse_ok("x"), &env),
+      Err(EvalError::UnknownVariable("x".to_string()))
+    );
+  }
+
+  #[test]
+  fn test_randint_same_seed() {
+    let expr = parse_ok("randint(1, 100) + randint(-5, 5) * 1000");
+    let results = |seed| {
+      let mut env = Environment::default();
+      env.set_seed(seed);
+      (0..20).map(|_| eval_with(&expr, &env)).collect::<Vec<_>>()
+    };
+
+    assert_eq!(results(42), results(42));
+    assert_ne!(results(42), results(43));
+  }
+
+  #[test]
+  fn test_randint() {
+    let mut env = Environment::default();
+    env.set_seed(0);
+
+    for _ in 0..100 {
+      let Ok(Value::Integer(n)) = eval_with(&parse_ok("randint(1, 6)"), &env)
+      else {
+        panic!("randint should return an integer");
+      };
+      assert!((1..=6).contains(&n), "{n} is out of range");
+    }
+    assert_eq!(
+      eval_with(&parse_ok("randint(3, 3)"), &env),
+      Ok(Value::Integer(3))
+    );
+
+    // User-defined functions shadow built-in functions.
+    define(&mut env, "randint(a, b) = a");
+    assert_eq!(
+      eval_with(&parse_ok("randint(3, 9)"), &env),
+      Ok(Value::Integer(3))
+    );
+  }
+
+  #[test]
+  fn test_sum_and_prod() {
+    assert_eq!(eval_str("sum(1, 2, 3, 4)"), Ok(Value::Integer(10)));
+    assert_eq!(eval_str("prod(2, 3, 4)"), Ok(Value::Integer(24)));
+    assert_eq!(eval_str("sum()"), Ok(Value::Integer(0)));
+    assert_eq!(eval_str("prod()"), Ok(Value::Integer(1)));
+    assert_eq!(eval_str("sum(7)"), Ok(Value::Integer(7)));
+    assert_eq!(eval_str("sum(1, 0.5)"), Ok(Value::Float(1.5)));
+    assert_eq!(eval_str("prod(2, sum(1, 2), 0.5)"), Ok(Value::Float(3.0)));
+
+    let mut env = Environment::default();
+    env.set_wrapping(true);
+    assert_eq!(
+      eval_with(&parse_ok("sum(9223372036854775807, 1)"), &env),
+      Ok(Value::Integer(i64::MIN))
+    );
+  }
+
+  #[test]
+  fn test_abs_signum_and_gcd() {
+    let cases = [
+      ("abs(-5)", Value::Integer(5)),
+      ("abs(5)", Value::Integer(5)),
+      (
+        "abs(-9223372036854775807 - 1)",
+        Value::Natural(Natural::from(1_u64 << 63)),
+      ),
+      ("abs(-2.5)", Value::Float(2.5)),
+      ("signum(-3)", Value::Integer(-1)),
+      ("signum(0)", Value::Integer(0)),
+      ("signum(18446744073709551616)", Value::Integer(1)),
+      ("signum(-0.5)", Value::Float(-1.0)),
+      ("signum(0.0)", Value::Float(0.0)),
+      ("gcd(-12, 18)", Value::Integer(6)),
+      ("gcd(0, -7)", Value::Integer(7)),
+      ("gcd(18446744073709551616, 6)", Value::Integer(2)),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(eval_str(input), Ok(expected), "{input}");
+    }
+
+    let mut env = Environment::default();
+    env.set_div_mode(DivMode::Rational);
+    let eval = |input| eval_with(&parse_ok(input), &env);
+    assert_eq!(eval("abs(-1 / 2)"), Ok(rational(1, 2)));
+    assert_eq!(eval("signum(-1 / 2)"), Ok(Value::Integer(-1)));
+
+    assert_eq!(
+      eval_str("gcd(1.5, 3)"),
+      Err(EvalError::Type(ValueTypeError {
+        expected: "integer",
+        found: "float"
+      }))
+    );
+  }
+
+  #[test]
+  fn test_factor() {
+    let factor = |input| eval_str(input).map(|x| x.to_string());
+    assert_eq!(factor("factor(360)"), Ok("2^3 * 3^2 * 5".to_string()));
+    assert_eq!(factor("factor(1)"), Ok("1".to_string()));
+    assert_eq!(factor("factor(97)"), Ok("97".to_string()));
+    assert_eq!(
+      eval_str("factor(12)"),
+      Ok(Value::Factors(vec![(2, 2), (3, 1)]))
+    );
+    assert_eq!(
+      factor("factor(18446744073709551615)"),
+      Ok("3 * 5 * 17 * 257 * 641 * 65537 * 6700417".to_string())
+    );
+    // This is the largest 64-bit prime, which is slow to factorise by trial
+    // division.
+    assert_eq!(
+      factor("factor(18446744073709551557)"),
+      Ok("18446744073709551557".to_string())
+    );
+
+    for (input, n) in [
+      ("factor(0)", Value::Integer(0)),
+      ("factor(-6)", Value::Integer(-6)),
+      (
+        "factor(18446744073709551616)",
+        Value::Natural("18446744073709551616".parse().unwrap()),
+      ),
+    ] {
+      assert_eq!(
+        eval_str(input),
+        Err(EvalError::CannotFactorise(n)),
+        "{input}"
+      );
+    }
+    assert_eq!(
+      eval_str("factor(2.5)"),
+      Err(EvalError::Type(ValueTypeError {
+        expected: "integer",
+        found: "float"
+      }))
+    );
+
+    // A factorisation is not a number.
+    let not_a_number = Err(EvalError::Type(ValueTypeError {
+      expected: "number",
+      found: "factorisation",
+    }));
+    for input in [
+      "factor(6) + 1",
+      "2 * factor(6)",
+      "-factor(6)",
+      "abs(factor(6))",
+      "factor(factor(6))",
+      "factor(6) km in m",
+    ] {
+      assert_eq!(eval_str(input), not_a_number, "{input}");
+    }
+  }
+
+  #[test]
+  fn test_roman() {
+    let text = |s: &str| Ok(Value::Text(s.to_string()));
+    assert_eq!(eval_str("roman(1994)"), text("MCMXCIV"));
+    assert_eq!(eval_str("roman(4)"), text("IV"));
+    assert_eq!(eval_str("roman(3999)"), text("MMMCMXCIX"));
+    assert_eq!(eval_str("roman(1994)").unwrap().to_string(), "MCMXCIV");
+
+    for (input, n) in [
+      ("roman(0)", Value::Integer(0)),
+      ("roman(4000)", Value::Integer(4000)),
+      ("roman(-4)", Value::Integer(-4)),
+      (
+        "roman(18446744073709551616)",
+        Value::Natural("18446744073709551616".parse().unwrap()),
+      ),
+    ] {
+      assert_eq!(eval_str(input), Err(EvalError::NotRoman(n)), "{input}");
+    }
+    assert_eq!(
+      eval_str("roman(4000)").unwrap_err().to_string(),
+      "Cannot write 4000 as a Roman numeral, which must be between 1 and 3999"
+    );
+    assert_eq!(
+      eval_str("roman(2.5)"),
+      Err(EvalError::Type(ValueTypeError {
+        expected: "integer",
+        found: "float"
+      }))
+    );
+
+    // Text is not a number.
+    assert_eq!(
+      eval_str("roman(4) + 1"),
+      Err(EvalError::Type(ValueTypeError {
+        expected: "number",
+        found: "string"
+      }))
+    );
+  }
+
+  #[test]
+  fn test_randint_errors() {
+    assert_eq!(
+      eval_str("randint(5, 1)"),
+      Err(EvalError::EmptyRange { low: 5, high: 1 })
+    );
+    assert_eq!(
+      eval_str("randint(1.5, 2)"),
+      Err(EvalError::Type(ValueTypeError {
+        expected: "integer",
+        found: "float"
+      }))
+    );
+    assert_eq!(
+      eval_str("randint(1)"),
+      Err(EvalError::ArityMismatch {
+        name: "randint".to_string(),
+        expected: 2,
+        found: 1
+      })
+    );
+    assert_eq!(
+      eval_str("randint(x, 1, 2)"),
+      Err(EvalError::ArityMismatch {
+        name: "randint".to_string(),
+        expected: 2,
+        found: 3
+      })
+    );
+  }
+
+  #[test]
+  fn test_recursion_limit() {
+    let mut env = Environment::default();
+    define(&mut env, "f(x) = f(x)");
+    define(&mut env, "g(x) = g(x + 1) + 1");
+
+    for input in ["f(1)", "g(1)", "1 + f(g(1))"] {
+      assert_eq!(
+        eval_with(&parse_ok(input), &env),
+        Err(EvalError::RecursionLimit(DEFAULT_RECURSION_LIMIT))
+      );
+    }
+  }
+
+  #[test]
+  fn test_set_recursion_limit() {
+    let mut env = Environment::default();
+    define(&mut env, "f(x) = x");
+    define(&mut env, "g(x) = f(x)");
+    env.set_recursion_limit(2);
+
+    assert_eq!(eval_with(&parse_ok("g(1)"), &env), Ok(Value::Integer(1)));
+    assert_eq!(
+      eval_with(&parse_ok("g(g(1))"), &env),
+      Ok(Value::Integer(1)),
+      "sequential calls should not count towards the limit"
+    );
+
+    define(&mut env, "h(x) = g(x)");
+    assert_eq!(
+      eval_with(&parse_ok("h(1)"), &env),
+      Err(EvalError::RecursionLimit(2))
+    );
   }
 }
@@ -1,13 +1,43 @@
 // SPDX-FileCopyrightText: 2025 Severen Redwood <sev@severen.dev>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use anyhow::{Context, Error, Result, anyhow};
-use clap::Parser;
+use std::{
+  borrow::Cow,
+  collections::VecDeque,
+  fmt::Write as _,
+  fs::{File, OpenOptions},
+  hint::black_box,
+  io::{self, BufRead, IsTerminal, Write},
+  num::NonZeroU32,
+  path::{Path, PathBuf},
+  rc::Rc,
+  sync::mpsc::{self, RecvTimeoutError},
+  thread,
+  time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Error, Result, anyhow, bail};
+use chumsky::Parser as _;
+use clap::{Parser, Subcommand, ValueEnum};
 use directories::ProjectDirs;
 use mimalloc::MiMalloc;
-use rkn::{eval, syntax::parse};
+use rkn::{
+  BUILTINS, CONSTANTS, DivMode, Environment, EvalError, Value, eval_with,
+  factor::{factorise, format_factors},
+  natural::Natural,
+  roman::format_roman,
+  syntax::{
+    self, Expr, ParseOptions, format_error, parse_prefix, parse_with,
+    to_compact, to_dot, to_sexpr,
+  },
+};
 use rustyline::{
-  DefaultEditor, config::Configurer, error::ReadlineError, history::History,
+  Completer, EditMode, Editor, Helper, Validator,
+  config::Configurer,
+  error::ReadlineError,
+  highlight::Highlighter,
+  hint::{Hint, Hinter},
+  history::{DefaultHistory, History},
 };
 
 #[global_allocator]
@@ -17,6 +47,163 @@ static GLOBAL: MiMalloc = MiMalloc;
 #[derive(Parser)]
 #[command(author, version, about)]
 struct Args {
+  #[command(subcommand)]
+  command: Option<Command>,
+
+  #[arg(
+    short,
+    long,
+    value_name = "PATH",
+    help = "Write results to a file instead of standard output"
+  )]
+  output_file: Option<PathBuf>,
+
+  #[arg(
+    short,
+    long = "file",
+    value_name = "PATH",
+    help = "Evaluate each line of a file before any expression"
+  )]
+  files: Vec<PathBuf>,
+
+  #[arg(
+    long,
+    value_name = "PATH",
+    help = "Append a JSON record of each evaluation to a log file"
+  )]
+  log_file: Option<PathBuf>,
+
+  #[arg(
+    long,
+    help = "Wrap around on integer overflow like 64-bit machine integers; \
+            fractions stay exact, but integer results of arithmetic on them \
+            wrap too"
+  )]
+  wrap: bool,
+
+  #[arg(
+    long,
+    value_name = "MODE",
+    default_value = "truncate",
+    value_parser = parse_div_mode,
+    help = "How to divide integers that do not divide exactly: truncate, \
+            floor, ceil, round-half-even, round-half-up, float, or rational"
+  )]
+  div_mode: DivMode,
+
+  #[arg(
+    long,
+    value_name = "N",
+    help = "Seed random functions like randint to make results reproducible"
+  )]
+  seed: Option<u64>,
+
+  #[arg(long, help = "Allow single spaces between digits, as in 1 000 000")]
+  space_grouping: bool,
+
+  #[arg(long, help = "Reject numbers with leading zeros, such as 0123")]
+  strict_leading_zeros: bool,
+
+  #[arg(
+    long,
+    help = "Evaluate the longest valid prefix of the input and ignore the rest"
+  )]
+  allow_trailing_garbage: bool,
+
+  #[arg(long, help = "Show a progress indicator for slow evaluations")]
+  progress: bool,
+
+  #[arg(long, help = "Use Vi rather than Emacs key bindings in the REPL")]
+  vi: bool,
+
+  #[arg(long, help = "Underline the location of parse errors in the input")]
+  underline_errors: bool,
+
+  #[arg(long, help = "Display parse trees as indented s-expressions")]
+  sexpr: bool,
+
+  #[arg(
+    long,
+    help = "Display parse trees in full, with the name of every node"
+  )]
+  verbose: bool,
+
+  #[arg(long, help = "Display float results using SI prefixes, as in 1.5k")]
+  si: bool,
+
+  #[arg(
+    short,
+    long,
+    value_enum,
+    default_value_t = Base::Decimal,
+    help = "The base to display integer results in"
+  )]
+  base: Base,
+
+  #[arg(
+    long,
+    help = "Prefix integer results in bases other than 10 with 0b, 0o, or 0x"
+  )]
+  prefix: bool,
+
+  #[arg(
+    long,
+    value_name = "N",
+    help = "Summarise integer results with more than N digits"
+  )]
+  max_digits: Option<usize>,
+
+  #[arg(
+    long,
+    value_name = "WIDTH",
+    value_parser = clap::value_parser!(u32).range(1..=64),
+    help = "Display negative integer results in bases other than 10 in \
+            two's complement with the given number of bits"
+  )]
+  twos_complement: Option<u32>,
+
+  #[arg(
+    long,
+    help = "Show the prime factorisation of integer results, as in 360 = 2^3 * \
+            3^2 * 5"
+  )]
+  with_factors: bool,
+
+  #[arg(
+    long,
+    value_name = "N",
+    default_value_t = DEFAULT_CONFIRM_COMPLEXITY,
+    help = "Ask before evaluating expensive-looking expressions in the REPL \
+            with a complexity above N"
+  )]
+  confirm_complexity: usize,
+
+  #[arg(long, help = "List the named constants and their values")]
+  list_constants: bool,
+
+  #[arg(
+    long,
+    requires = "EXPR",
+    help = "Report the time spent building the parser, parsing, and evaluating"
+  )]
+  profile_parse: bool,
+
+  #[arg(
+    long,
+    requires = "EXPR",
+    help = "Print the parse tree of the expression as a Graphviz DOT graph \
+            instead of evaluating it"
+  )]
+  dump_tree_dot: bool,
+
+  #[arg(
+    long,
+    value_name = "N",
+    requires = "EXPR",
+    help = "Time N evaluations of the expression and report statistics"
+  )]
+  benchmark_expr: Option<NonZeroU32>,
+
   #[arg(
     name = "EXPR",
     help = "An expression to execute",
@@ -25,26 +212,1181 @@ struct Args {
   expr: Vec<String>,
 }
 
-fn main() -> Result<()> {
-  let args = Args::parse();
-  if !args.expr.is_empty() { run(&args.expr.join("")) } else { repl() }
+/// The modes that the calculator can be run in.
+///
+/// Running the calculator without a subcommand is equivalent to `calc`, so
+/// that `rkn <EXPR>` continues to work.
+#[derive(Debug, PartialEq, Subcommand)]
+enum Command {
+  #[command(
+    about = "Evaluate an expression, or start the REPL if none is given"
+  )]
+  Calc {
+    #[arg(
+      name = "EXPR",
+      help = "An expression to execute",
+      trailing_var_arg = true
+    )]
+    expr: Vec<String>,
+  },
+  #[command(about = "Print the prime factorisation of a positive integer")]
+  Factor {
+    #[arg(help = "The integer to factor")]
+    n: u64,
+  },
+  #[command(about = "Convert a quantity from one unit to another")]
+  Convert {
+    #[arg(help = "The quantity to convert, without its unit")]
+    expr: String,
+    #[arg(help = "The unit to convert from")]
+    from: String,
+    #[arg(help = "The unit to convert to")]
+    to: String,
+  },
+}
+
+/// A base that integer results can be displayed in.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum Base {
+  #[value(name = "bin", alias = "2")]
+  Binary,
+  #[value(name = "oct", alias = "8")]
+  Octal,
+  #[default]
+  #[value(name = "dec", alias = "10")]
+  Decimal,
+  #[value(name = "hex", alias = "16")]
+  Hexadecimal,
+  /// Roman numerals, as in `MCMXCIV`, which can only represent the integers
+  /// from 1 to 3999.
+  #[value(name = "roman")]
+  Roman,
+}
+
+/// Settings that control how results are evaluated and displayed.
+///
+/// Each setting can be changed during a session with the `:set` command, using
+/// the name of the corresponding command-line option.
+#[derive(Debug, Default)]
+struct Settings {
+  underline_errors: bool,
+  progress: bool,
+  sexpr: bool,
+  verbose: bool,
+  si: bool,
+  base: Base,
+  prefix: bool,
+  max_digits: Option<usize>,
+  twos_complement: Option<u32>,
+  with_factors: bool,
+  div_mode: DivMode,
+  /// The complexity above which the REPL asks for confirmation before
+  /// evaluating an expensive-looking expression, if any.
+  confirm_complexity: Option<usize>,
+}
+
+/// The default complexity above which the REPL asks for confirmation before
+/// evaluating an expensive-looking expression.
+const DEFAULT_CONFIRM_COMPLEXITY: usize = 1000;
+
+impl Settings {
+  /// The name and current value of every setting.
+  fn list(&self) -> Vec<(&'static str, String)> {
+    let flag = |enabled: bool| if enabled { "on" } else { "off" }.to_string();
+    let base = self.base.to_possible_value().expect("bases are never skipped");
+
+    vec![
+      ("underline-errors", flag(self.underline_errors)),
+      ("progress", flag(self.progress)),
+      ("sexpr", flag(self.sexpr)),
+      ("verbose", flag(self.verbose)),
+      ("si", flag(self.si)),
+      ("base", base.get_name().to_string()),
+      ("prefix", flag(self.prefix)),
+      (
+        "max-digits",
+        self.max_digits.map_or("off".to_string(), |n| n.to_string()),
+      ),
+      (
+        "twos-complement",
+        self.twos_complement.map_or("off".to_string(), |w| w.to_string()),
+      ),
+      ("with-factors", flag(self.with_factors)),
+      ("div-mode", self.div_mode.name().to_string()),
+      (
+        "confirm-complexity",
+        self.confirm_complexity.map_or("off".to_string(), |n| n.to_string()),
+      ),
+    ]
+  }
+
+  /// Change the setting with the given name, as in `:set base hex`.
+  fn set(&mut self, key: &str, value: &str) -> Result<()> {
+    let flag = || match value {
+      "on" | "true" | "yes" => Ok(true),
+      "off" | "false" | "no" => Ok(false),
+      _ => {
+        Err(anyhow!("Expected 'on' or 'off' for '{key}' but found '{value}'"))
+      },
+    };
+
+    match key {
+      "underline-errors" => self.underline_errors = flag()?,
+      "progress" => self.progress = flag()?,
+      "sexpr" => self.sexpr = flag()?,
+      "verbose" => self.verbose = flag()?,
+      "si" => self.si = flag()?,
+      "base" => {
+        self.base = Base::from_str(value, true)
+          .map_err(|_| anyhow!("Unknown base '{value}'"))?;
+      },
+      "prefix" => self.prefix = flag()?,
+      "max-digits" => {
+        self.max_digits = match value {
+          "off" => None,
+          _ => Some(value.parse().map_err(|_| {
+            anyhow!("Expected a number of digits or 'off' but found '{value}'")
+          })?),
+        };
+      },
+      "twos-complement" => {
+        self.twos_complement = match value {
+          "off" => None,
+          _ => Some(
+            value
+              .parse()
+              .ok()
+              .filter(|width| (1..=64).contains(width))
+              .ok_or_else(|| {
+                anyhow!(
+                  "Expected a width between 1 and 64 or 'off' but found \
+                   '{value}'"
+                )
+              })?,
+          ),
+        };
+      },
+      "with-factors" => self.with_factors = flag()?,
+      "div-mode" => self.div_mode = parse_div_mode(value)?,
+      "confirm-complexity" => {
+        self.confirm_complexity = match value {
+          "off" => None,
+          _ => Some(value.parse().map_err(|_| {
+            anyhow!("Expected a complexity or 'off' but found '{value}'")
+          })?),
+        };
+      },
+      _ => bail!("Unknown setting '{key}'"),
+    }
+
+    Ok(())
+  }
+}
+
+/// Parse the name of a division mode, as in `--div-mode float`.
+fn parse_div_mode(name: &str) -> Result<DivMode> {
+  DivMode::from_name(name).ok_or_else(|| {
+    anyhow!(
+      "Expected 'truncate', 'floor', 'ceil', 'round-half-even', \
+       'round-half-up', 'float', or 'rational' for 'div-mode' but found \
+       '{name}'"
+    )
+  })
+}
+
+/// The state of a calculator session that persists between expressions.
+#[derive(Debug, Default)]
+struct Session {
+  env: Environment,
+  parse_options: ParseOptions,
+  /// Whether to ignore input that follows a valid expression, rather than
+  /// treating it as an error.
+  allow_trailing_garbage: bool,
+  settings: Settings,
+  /// The file to append a record of each evaluation to, if any.
+  log: Option<File>,
+  /// The file that inputs are being recorded to with `:record`, if any.
+  recording: Option<File>,
+  /// Where the input currently being run came from, such as a line of a file,
+  /// which is included in error messages.
+  location: Option<String>,
+  /// The results of recently evaluated inputs, which are reused when the same
+  /// input is entered again.
+  cache: LruCache<(Rc<Expr>, Value)>,
+}
+
+/// The number of results kept in the cache of recent evaluations.
+const CACHE_CAPACITY: usize = 64;
+
+/// A small cache that discards the least recently used entry when full.
+///
+/// Entries are kept in a list ordered from most to least recently used, which
+/// is fast enough since the cache only holds a handful of entries.
+#[derive(Debug)]
+struct LruCache<V> {
+  capacity: usize,
+  entries: VecDeque<(String, V)>,
+}
+
+impl<V> LruCache<V> {
+  fn new(capacity: usize) -> Self {
+    Self { capacity, entries: VecDeque::with_capacity(capacity) }
+  }
+
+  /// Look up the value for `key`, marking it as the most recently used entry.
+  fn get(&mut self, key: &str) -> Option<&V> {
+    let index = self.entries.iter().position(|(k, _)| k == key)?;
+    let entry = self.entries.remove(index)?;
+    self.entries.push_front(entry);
+    self.entries.front().map(|(_, value)| value)
+  }
+
+  /// Add an entry to the cache, discarding the least recently used entry if
+  /// the cache is full.
+  fn insert(&mut self, key: String, value: V) {
+    self.entries.retain(|(k, _)| *k != key);
+    if self.entries.len() == self.capacity {
+      self.entries.pop_back();
+    }
+    self.entries.push_front((key, value));
+  }
+}
+
+impl<V> Default for LruCache<V> {
+  fn default() -> Self {
+    Self::new(CACHE_CAPACITY)
+  }
+}
+
+fn main() -> Result<()> {
+  let args = Args::parse();
+  let mut env = Environment::default();
+  env.set_wrapping(args.wrap);
+  if let Some(seed) = args.seed {
+    env.set_seed(seed);
+  }
+
+  let mut session = Session {
+    env,
+    parse_options: ParseOptions {
+      space_grouping: args.space_grouping,
+      strict_leading_zeros: args.strict_leading_zeros,
+    },
+    allow_trailing_garbage: args.allow_trailing_garbage,
+    settings: Settings {
+      underline_errors: args.underline_errors,
+      progress: args.progress,
+      sexpr: args.sexpr,
+      verbose: args.verbose,
+      si: args.si,
+      base: args.base,
+      prefix: args.prefix,
+      max_digits: args.max_digits,
+      twos_complement: args.twos_complement,
+      with_factors: args.with_factors,
+      div_mode: args.div_mode,
+      confirm_complexity: Some(args.confirm_complexity),
+    },
+    log: args.log_file.as_deref().map(open_log).transpose()?,
+    recording: None,
+    location: None,
+    cache: LruCache::default(),
+  };
+
+  let mut output: Box<dyn Write> = match &args.output_file {
+    Some(path) => Box::new(File::create(path).with_context(|| {
+      format!("Failed to create output file '{}'", path.display())
+    })?),
+    None => Box::new(io::stdout()),
+  };
+
+  if args.list_constants {
+    write!(output, "{}", format_constants())?;
+    return Ok(());
+  }
+
+  let mut expr = match args.command {
+    None => args.expr,
+    Some(Command::Calc { expr }) => expr,
+    Some(Command::Factor { n }) => return run_factor(n, &mut output),
+    Some(Command::Convert { expr, from, to }) => {
+      vec![format!("({expr}) {from} in {to}")]
+    },
+  };
+
+  // A trailing `-` means that more input follows on standard input.
+  let then_stdin = expr.last().is_some_and(|arg| arg == "-");
+  if then_stdin {
+    expr.pop();
+  }
+  let input = expr.join(" ");
+
+  for path in &args.files {
+    run_file(path, &mut session, &mut output)?;
+  }
+
+  if let Some(iterations) = args.benchmark_expr {
+    run_benchmark(&input, iterations, &session, &mut output)
+  } else if args.dump_tree_dot {
+    run_dump_dot(&input, &session, &mut output)
+  } else if args.profile_parse {
+    run_profile(&input, &session, &mut output)
+  } else if !input.is_empty() || then_stdin {
+    if !input.is_empty() {
+      run(&input, &mut session, &mut output)?;
+    }
+    if then_stdin {
+      run_lines(io::stdin().lock(), "<stdin>", &mut session, &mut output)?;
+    }
+    Ok(())
+  } else if args.files.is_empty() {
+    repl(&mut session, args.vi, &mut output)
+  } else {
+    Ok(())
+  }
+}
+
+/// Run each nonblank line of a file in order, as if it were entered in the
+/// REPL.
+fn run_file(
+  path: &Path,
+  session: &mut Session,
+  output: &mut dyn Write,
+) -> Result<()> {
+  let file = File::open(path)
+    .with_context(|| format!("Failed to read file '{}'", path.display()))?;
+  run_lines(
+    io::BufReader::new(file),
+    &path.display().to_string(),
+    session,
+    output,
+  )
+}
+
+/// Run each nonblank line read from `lines` in order, as if it were entered in
+/// the REPL.
+///
+/// The only REPL command that can be run this way is `:set`, so that the
+/// settings written to a recording made with `:record` take effect when it is
+/// replayed. Errors are reported with the line number and the name of the
+/// `source` of the lines, such as a file path.
+fn run_lines(
+  lines: impl BufRead,
+  source: &str,
+  session: &mut Session,
+  output: &mut dyn Write,
+) -> Result<()> {
+  for (index, line) in lines.lines().enumerate() {
+    let line = line.with_context(|| format!("Failed to read from {source}"))?;
+    if line.trim().is_empty() {
+      continue;
+    }
+
+    let location = format!("{source}:{}", index + 1);
+    if let Some((":set", setting)) = line.trim().split_once(char::is_whitespace)
+    {
+      if let Err(err) = run_set(setting.trim(), session) {
+        eprintln!("Error: {location}: {err}");
+      }
+      continue;
+    }
+
+    session.location = Some(location);
+    let result = run(&line, session, output);
+    session.location = None;
+    result?;
+  }
+
+  Ok(())
+}
+
+/// Parse and evaluate an expression, writing the results to `output`.
+///
+/// If the expression is a function definition, the function is added to the
+/// session's environment instead. Errors are always written to standard error
+/// so that they are not mixed in with the results.
+///
+/// Inputs that have been evaluated recently are not parsed or evaluated again
+/// if their result cannot have changed since, which is the case when they do
+/// not call any user-defined functions.
+fn run(
+  input: &str,
+  session: &mut Session,
+  output: &mut dyn Write,
+) -> Result<()> {
+  // The division mode is a setting so that it can be changed with `:set`, but
+  // it takes effect in the environment, where any results evaluated under the
+  // old mode are no longer valid.
+  if session.env.div_mode() != session.settings.div_mode {
+    session.env.set_div_mode(session.settings.div_mode);
+    session.cache = LruCache::default();
+  }
+
+  let key = input.trim();
+  let cached = session.cache.get(key).cloned();
+
+  let (parsed, errs) = if cached.is_some() {
+    (None, Vec::new())
+  } else if session.allow_trailing_garbage {
+    let (parsed, errs) =
+      parse_prefix(input, session.parse_options).into_output_errors();
+    let parsed = parsed.map(|(expr, rest)| {
+      if !rest.is_empty() {
+        eprintln!("Warning: Ignoring trailing input '{rest}'");
+      }
+      expr
+    });
+    (parsed, errs)
+  } else {
+    parse_with(input, session.parse_options).into_output_errors()
+  };
+  let mut errors: Vec<String> = errs.iter().map(ToString::to_string).collect();
+  let mut value = None;
+
+  if let Some((expr, cached_value)) = cached {
+    write_parse_tree(&expr, &session.settings, output)?;
+    value = Some(cached_value);
+  }
+
+  if let Some(expr) = parsed {
+    write_parse_tree(&expr, &session.settings, output)?;
+
+    if let Expr::FnDef(name, params, body) = expr {
+      session.env.define_function(name, params, *body);
+    } else {
+      let evaluated = with_progress(session.settings.progress, || {
+        eval_with(&expr, &session.env)
+      });
+      match evaluated {
+        Ok(evaluated) => {
+          if is_cacheable(&expr) {
+            session
+              .cache
+              .insert(key.to_string(), (Rc::new(expr), evaluated.clone()));
+          }
+          value = Some(evaluated);
+        },
+        Err(err) => errors.push(err.to_string()),
+      }
+    }
+  }
+
+  let mut result = None;
+  if let Some(value) = value {
+    match format_value(value, &session.settings) {
+      Ok(value) => {
+        writeln!(output, "Result: {value}")?;
+        result = Some(value);
+      },
+      Err(err) => errors.push(err.to_string()),
+    }
+  }
+
+  // The parse errors come first, so they can be underlined in the input while
+  // the log records only their messages.
+  let location =
+    session.location.as_ref().map_or(String::new(), |at| format!("{at}: "));
+  for (index, err) in errors.iter().enumerate() {
+    match errs.get(index) {
+      Some(err) if session.settings.underline_errors => {
+        eprintln!("Error: {location}{}", format_error(input, err));
+      },
+      _ => eprintln!("Error: {location}{err}"),
+    }
+  }
+
+  if let Some(log) = &mut session.log
+    && let Err(err) = write_log_record(log, input, result.as_deref(), &errors)
+      .context("Failed to write to the log file")
+  {
+    eprintln!("Warning: {err:#}");
+  }
+
+  // Only inputs that succeeded are recorded, so that the recording is a clean
+  // script that can be run with `--file`.
+  if let Some(recording) = &mut session.recording
+    && errors.is_empty()
+    && let Err(err) = writeln!(recording, "{}", input.trim())
+      .context("Failed to write to the recording")
+  {
+    eprintln!("Warning: {err:#}");
+  }
+
+  Ok(())
+}
+
+/// Write the parse tree of an expression in the format chosen by `settings`.
+fn write_parse_tree(
+  expr: &Expr,
+  settings: &Settings,
+  output: &mut dyn Write,
+) -> Result<()> {
+  if settings.sexpr {
+    writeln!(output, "Parse tree: {}", to_sexpr(expr))?;
+  } else if settings.verbose {
+    writeln!(output, "Parse tree: {expr:?}")?;
+  } else {
+    writeln!(output, "Parse tree: {}", to_compact(expr))?;
+  }
+
+  Ok(())
+}
+
+/// Whether the result of evaluating an expression can be cached.
+///
+/// Calls to user-defined functions are not cacheable since the functions can
+/// be redefined. Every other expression always evaluates to the same value.
+fn is_cacheable(expr: &Expr) -> bool {
+  expr.fold(&mut |expr, children: Vec<bool>| {
+    !matches!(expr, Expr::Call(..) | Expr::FnDef(..))
+      && children.into_iter().all(|cacheable| cacheable)
+  })
+}
+
+/// Timing statistics from evaluating an expression repeatedly.
+#[derive(Debug)]
+struct BenchmarkStats {
+  iterations: u32,
+  min: Duration,
+  mean: Duration,
+  max: Duration,
+}
+
+/// Evaluate an already parsed expression `iterations` times, returning its
+/// value along with statistics on how long each evaluation took.
+fn benchmark(
+  expr: &Expr,
+  env: &Environment,
+  iterations: NonZeroU32,
+) -> Result<(Value, BenchmarkStats), EvalError> {
+  let mut value = None;
+  let mut total = Duration::ZERO;
+  let mut min = Duration::MAX;
+  let mut max = Duration::ZERO;
+
+  for _ in 0..iterations.get() {
+    let start = Instant::now();
+    let result = black_box(eval_with(black_box(expr), env));
+    let elapsed = start.elapsed();
+
+    value = Some(result?);
+    total += elapsed;
+    min = min.min(elapsed);
+    max = max.max(elapsed);
+  }
+
+  let value = value.expect("there should be at least one iteration");
+  let stats = BenchmarkStats {
+    iterations: iterations.get(),
+    min,
+    mean: total / iterations.get(),
+    max,
+  };
+
+  Ok((value, stats))
+}
+
+/// How long each stage of evaluating an expression took.
+#[derive(Debug)]
+struct Profile {
+  build: Duration,
+  parse: Duration,
+  eval: Duration,
+}
+
+/// Build a parser, parse an expression, and evaluate it, timing each stage.
+fn profile(input: &str, session: &Session) -> Result<(Value, Profile)> {
+  let start = Instant::now();
+  let parser = syntax::parser(session.parse_options);
+  let build = start.elapsed();
+
+  let start = Instant::now();
+  let parsed = parser.parse(input).into_result();
+  let parse = start.elapsed();
+
+  let expr = match parsed {
+    Ok(Expr::FnDef(..)) => bail!("Cannot profile a function definition"),
+    Ok(expr) => expr,
+    Err(errs) => {
+      let errs: Vec<_> = errs.iter().map(ToString::to_string).collect();
+      bail!(errs.join("\n"));
+    },
+  };
+
+  let start = Instant::now();
+  let value = eval_with(&expr, &session.env)?;
+  let eval = start.elapsed();
+
+  Ok((value, Profile { build, parse, eval }))
+}
+
+/// Evaluate an expression and report how long each stage took on standard
+/// error.
+fn run_profile(
+  input: &str,
+  session: &Session,
+  output: &mut dyn Write,
+) -> Result<()> {
+  let (value, profile) = profile(input, session)?;
+  writeln!(output, "Result: {}", format_value(value, &session.settings)?)?;
+  eprintln!(
+    "Built parser in {:?}, parsed in {:?}, evaluated in {:?}",
+    profile.build, profile.parse, profile.eval
+  );
+
+  Ok(())
+}
+
+/// Parse an expression and write its parse tree to `output` as a Graphviz DOT
+/// graph, without evaluating it.
+fn run_dump_dot(
+  input: &str,
+  session: &Session,
+  output: &mut dyn Write,
+) -> Result<()> {
+  match parse_with(input, session.parse_options).into_result() {
+    Ok(expr) => write!(output, "{}", to_dot(&expr))?,
+    Err(errs) => {
+      for err in errs {
+        eprintln!("Error: {err}");
+      }
+    },
+  }
+
+  Ok(())
+}
+
+/// Write the prime factorisation of `n` to `output`, as in `360 = 2^3 * 3^2 *
+/// 5`.
+fn run_factor(n: u64, output: &mut dyn Write) -> Result<()> {
+  if n == 0 {
+    bail!("0 has no prime factorisation");
+  }
+
+  writeln!(output, "{n} = {}", format_factors(&factorise(n)))?;
+  Ok(())
+}
+
+/// Describe the prime factorisation of an integer result, as in `= 2^3 * 3^2 *
+/// 5` for 360, to be written after the result itself.
+///
+/// Negative integers are factorised as -1 times the factorisation of their
+/// magnitude. Returns `None` for -1, 0, and 1, which have no prime factors to
+/// show.
+fn describe_factors(n: i64) -> Option<String> {
+  let magnitude = n.unsigned_abs();
+  if magnitude < 2 {
+    return None;
+  }
+
+  let sign = if n < 0 { "-1 * " } else { "" };
+  Some(format!("= {sign}{}", format_factors(&factorise(magnitude))))
+}
+
+/// Parse an expression once and then report how long it takes to evaluate it
+/// `iterations` times.
+fn run_benchmark(
+  input: &str,
+  iterations: NonZeroU32,
+  session: &Session,
+  output: &mut dyn Write,
+) -> Result<()> {
+  let expr = match parse_with(input, session.parse_options).into_result() {
+    Ok(Expr::FnDef(..)) => bail!("Cannot benchmark a function definition"),
+    Ok(expr) => expr,
+    Err(errs) => {
+      for err in errs {
+        eprintln!("Error: {err}");
+      }
+      return Ok(());
+    },
+  };
+
+  let (value, stats) = benchmark(&expr, &session.env, iterations)?;
+  writeln!(output, "Result: {}", format_value(value, &session.settings)?)?;
+  writeln!(
+    output,
+    "Evaluated {} times: min {:?}, mean {:?}, max {:?}",
+    stats.iterations, stats.min, stats.mean, stats.max
+  )?;
+
+  Ok(())
+}
+
+/// Open a log file for appending, creating it if it does not yet exist.
+fn open_log(path: &Path) -> Result<File> {
+  OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)
+    .with_context(|| format!("Failed to open log file '{}'", path.display()))
+}
+
+/// Append a record of an evaluation to a log as a single line of JSON.
+///
+/// The record is an object with the keys `timestamp` (in seconds since the
+/// Unix epoch), `input`, and either `result` (which is `null` for function
+/// definitions) or `error` (with multiple errors separated by newlines).
+fn write_log_record(
+  log: &mut impl Write,
+  input: &str,
+  result: Option<&str>,
+  errors: &[String],
+) -> Result<()> {
+  let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+  let mut record =
+    format!(r#"{{"timestamp":{timestamp},"input":{}"#, json_string(input));
+  if errors.is_empty() {
+    match result {
+      Some(result) => write!(record, r#","result":{}"#, json_string(result))?,
+      None => record.push_str(r#","result":null"#),
+    }
+  } else {
+    write!(record, r#","error":{}"#, json_string(&errors.join("\n")))?;
+  }
+  record.push('}');
+
+  writeln!(log, "{record}")?;
+  Ok(())
+}
+
+/// Quote and escape a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+  let mut quoted = String::with_capacity(s.len() + 2);
+  quoted.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => quoted.push_str(r#"\""#),
+      '\\' => quoted.push_str(r"\\"),
+      '\n' => quoted.push_str(r"\n"),
+      '\r' => quoted.push_str(r"\r"),
+      '\t' => quoted.push_str(r"\t"),
+      c if c.is_control() => quoted.push_str(&format!(r"\u{:04x}", c as u32)),
+      c => quoted.push(c),
+    }
+  }
+  quoted.push('"');
+  quoted
+}
+
+/// How long an evaluation may take before a progress indicator is shown.
+const PROGRESS_DELAY: Duration = Duration::from_millis(200);
+
+/// How often the progress indicator is redrawn.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The frames of the progress indicator's spinner.
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Call `f`, showing a spinner on standard error if it takes longer than
+/// [`PROGRESS_DELAY`] to return.
+///
+/// The spinner is cleared once `f` returns. Nothing is shown unless `enabled`
+/// is set and standard error is a terminal, so that the indicator never ends
+/// up in redirected output.
+fn with_progress<T>(enabled: bool, f: impl FnOnce() -> T) -> T {
+  if !enabled || !io::stderr().is_terminal() {
+    return f();
+  }
+
+  let (done, finished) = mpsc::channel::<()>();
+  thread::scope(|scope| {
+    scope.spawn(move || {
+      let start = Instant::now();
+      let mut shown = false;
+
+      for frame in SPINNER.iter().cycle() {
+        match finished.recv_timeout(PROGRESS_INTERVAL) {
+          Err(RecvTimeoutError::Timeout) => {
+            if should_show_progress(start.elapsed()) {
+              eprint!("\r{frame} Evaluating...");
+              shown = true;
+            }
+          },
+          _ => break,
+        }
+      }
+
+      if shown {
+        eprint!("\r\x1b[K");
+      }
+    });
+
+    let result = f();
+    drop(done);
+    result
+  })
+}
+
+/// Whether an evaluation that has taken `elapsed` so far is slow enough to show
+/// a progress indicator for.
+fn should_show_progress(elapsed: Duration) -> bool {
+  elapsed >= PROGRESS_DELAY
+}
+
+/// Format a result for display according to the given settings.
+///
+/// This fails if the result cannot be written in the chosen base, which is only
+/// possible for Roman numerals.
+///
+/// If the `with-factors` setting is on, the prime factorisation of an integer
+/// result is written after it in decimal, whatever the base of the result.
+fn format_value(value: Value, settings: &Settings) -> Result<String> {
+  let factors = match value {
+    Value::Integer(n) if settings.with_factors => describe_factors(n),
+    Value::Natural(_) if settings.with_factors => {
+      Some("(too large to factorise)".to_string())
+    },
+    _ => None,
+  };
+
+  let formatted = match value {
+    Value::Integer(_) | Value::Natural(_) if settings.base == Base::Roman => {
+      let numeral = match value {
+        Value::Integer(n) => format_roman(n),
+        _ => None,
+      };
+      numeral.ok_or_else(|| {
+        anyhow!(
+          "Cannot write {value} as a Roman numeral, which must be between 1 \
+           and 3999"
+        )
+      })?
+    },
+    Value::Integer(n) => settings
+      .twos_complement
+      .and_then(|width| {
+        format_twos_complement(n, width, settings.base, settings.prefix)
+      })
+      .unwrap_or_else(|| {
+        format_integer(n, settings.base, settings.prefix, settings.max_digits)
+      }),
+    Value::Natural(n) => {
+      format_natural(&n, settings.base, settings.prefix, settings.max_digits)
+    },
+    Value::Float(x) if settings.si => format_si(x),
+    value => value.to_string(),
+  };
+
+  Ok(match factors {
+    Some(factors) => format!("{formatted} {factors}"),
+    None => formatted,
+  })
+}
+
+/// Format an integer in the given base, optionally with the conventional `0b`,
+/// `0o`, or `0x` prefix for bases other than 10.
+///
+/// Negative integers are formatted as a minus sign followed by the magnitude,
+/// as in `-0xff`; see [`format_twos_complement`] for the alternative. If there
+/// are more than `max_digits` digits, only the first and last few are shown.
+fn format_integer(
+  n: i64,
+  base: Base,
+  show_prefix: bool,
+  max_digits: Option<usize>,
+) -> String {
+  let sign = if n < 0 { "-" } else { "" };
+  let n = n.unsigned_abs();
+
+  let digits = match base {
+    Base::Binary => format!("{n:b}"),
+    Base::Octal => format!("{n:o}"),
+    Base::Decimal => n.to_string(),
+    Base::Hexadecimal => format!("{n:x}"),
+    Base::Roman => unreachable!("Roman numerals are formatted separately"),
+  };
+
+  format_digits(sign, digits, base, show_prefix, max_digits)
+}
+
+/// Format a natural number in the given base in the same way as
+/// [`format_integer`].
+fn format_natural(
+  n: &Natural,
+  base: Base,
+  show_prefix: bool,
+  max_digits: Option<usize>,
+) -> String {
+  let radix = match base {
+    Base::Binary => 2,
+    Base::Octal => 8,
+    Base::Decimal => 10,
+    Base::Hexadecimal => 16,
+    Base::Roman => unreachable!("Roman numerals are formatted separately"),
+  };
+
+  format_digits("", n.to_str_radix(radix), base, show_prefix, max_digits)
+}
+
+/// Join the sign, base prefix, and digits of a formatted integer, summarising
+/// the digits if there are more than `max_digits` of them.
+fn format_digits(
+  sign: &str,
+  digits: String,
+  base: Base,
+  show_prefix: bool,
+  max_digits: Option<usize>,
+) -> String {
+  let prefix = match base {
+    Base::Binary => "0b",
+    Base::Octal => "0o",
+    Base::Hexadecimal => "0x",
+    Base::Decimal | Base::Roman => "",
+  };
+  let digits = match max_digits {
+    Some(max) if digits.len() > max => summarise_digits(&digits),
+    _ => digits,
+  };
+
+  if prefix.is_empty() || !show_prefix {
+    format!("{sign}{digits}")
+  } else {
+    format!("{sign}{prefix}{digits}")
+  }
+}
+
+/// Format a negative integer as its two's-complement bit pattern with the given
+/// width, padded with leading digits to the full width, as in `0xff01` for
+/// -255 with a width of 16.
+///
+/// This returns `None` for nonnegative integers, for integers in base 10 or in
+/// Roman numerals, and for integers that do not fit in the given width, which
+/// should be formatted as usual instead.
+fn format_twos_complement(
+  n: i64,
+  width: u32,
+  base: Base,
+  show_prefix: bool,
+) -> Option<String> {
+  if n >= 0
+    || matches!(base, Base::Decimal | Base::Roman)
+    || !(1..=64).contains(&width)
+  {
+    return None;
+  }
+  if width < 64 && n < -(1 << (width - 1)) {
+    return None;
+  }
+
+  let pattern = (n as u64) & (u64::MAX >> (64 - width));
+  let (prefix, digits) = match base {
+    Base::Binary => ("0b", format!("{pattern:0len$b}", len = width as usize)),
+    Base::Octal => {
+      ("0o", format!("{pattern:0len$o}", len = width.div_ceil(3) as usize))
+    },
+    Base::Hexadecimal => {
+      ("0x", format!("{pattern:0len$x}", len = width.div_ceil(4) as usize))
+    },
+    Base::Decimal | Base::Roman => {
+      unreachable!("two's complement only applies to binary, octal, and hex")
+    },
+  };
+
+  Some(if show_prefix { format!("{prefix}{digits}") } else { digits })
+}
+
+/// The number of digits shown at each end of a summarised result.
+const SUMMARY_DIGITS: usize = 4;
+
+/// Abbreviate a long run of digits to its first and last few digits and the
+/// total number of digits, as in `1234...5678 (1000003 digits)`.
+fn summarise_digits(digits: &str) -> String {
+  let len = digits.len();
+  if len <= 2 * SUMMARY_DIGITS {
+    return format!("{digits} ({len} digits)");
+  }
+
+  let (first, last) =
+    (&digits[..SUMMARY_DIGITS], &digits[len - SUMMARY_DIGITS..]);
+  format!("{first}...{last} ({len} digits)")
+}
+
+/// The SI prefixes from 10^-30 to 10^30 in steps of 10^3.
+const SI_PREFIXES: [&str; 21] = [
+  "q", "r", "y", "z", "a", "f", "p", "n", "µ", "m", "", "k", "M", "G", "T",
+  "P", "E", "Z", "Y", "R", "Q",
+];
+
+/// Format a float using the SI prefix that leaves between 1 and 1000 (in
+/// magnitude) before the prefix, as in `1.5k` for 1500.
+///
+/// Zero, infinities, NaN, and values beyond the range of the SI prefixes are
+/// formatted as usual.
+fn format_si(x: f64) -> String {
+  if x == 0.0 || !x.is_finite() {
+    return x.to_string();
+  }
+
+  // The index of the empty prefix, which corresponds to 10^0.
+  const UNIT_INDEX: i32 = 10;
+
+  // Scientific notation gives us the shortest digits that round trip, so we
+  // can pick a prefix by shifting the decimal point in the digits rather than
+  // dividing by a power of ten, which would introduce rounding errors.
+  let scientific = format!("{:e}", x.abs());
+  let (mantissa, exponent) = scientific
+    .split_once('e')
+    .expect("floats formatted with {:e} should have an exponent");
+  let exponent: i32 =
+    exponent.parse().expect("float exponents should be valid integers");
+
+  let index = exponent.div_euclid(3) + UNIT_INDEX;
+  let Some(prefix) =
+    usize::try_from(index).ok().and_then(|index| SI_PREFIXES.get(index))
+  else {
+    return x.to_string();
+  };
+
+  let mut digits = mantissa.replace('.', "");
+  let int_len = exponent.rem_euclid(3) as usize + 1;
+  while digits.len() < int_len {
+    digits.push('0');
+  }
+
+  let sign = if x < 0.0 { "-" } else { "" };
+  match digits.split_at(int_len) {
+    (int, "") => format!("{sign}{int}{prefix}"),
+    (int, frac) => format!("{sign}{int}.{frac}{prefix}"),
+  }
+}
+
+/// The line editor used by the REPL.
+type ReplEditor = Editor<ReplHelper, DefaultHistory>;
+
+/// The helper for the REPL's line editor, which hints at the value of a
+/// constant or the parameters of a function as its name is typed.
+#[derive(Debug, Default, Completer, Helper, Validator)]
+struct ReplHelper {
+  /// The name of every constant and function along with the hint to show after
+  /// it, sorted by name.
+  names: Vec<(String, String)>,
+}
+
+impl ReplHelper {
+  /// Update the names to hint at with the functions currently defined in the
+  /// environment.
+  fn refresh(&mut self, env: &Environment) {
+    self.names = hint_names(env);
+  }
+}
+
+impl Hinter for ReplHelper {
+  type Hint = ReplHint;
+
+  fn hint(
+    &self,
+    line: &str,
+    pos: usize,
+    _: &rustyline::Context<'_>,
+  ) -> Option<ReplHint> {
+    hint(line, pos, &self.names)
+  }
+}
+
+/// A hint shown after the cursor in the REPL, as in `i = 3.14…` after `p`.
+///
+/// Accepting the hint, as with the right arrow key, only inserts the rest of
+/// the name, so that accepting the hint for `pi` does not also insert its
+/// value.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct ReplHint {
+  /// The rest of the name followed by its value or parameters.
+  display: String,
+  /// The length of the rest of the name at the start of `display`.
+  completion_len: usize,
+}
+
+impl ReplHint {
+  /// Create a hint that completes a name with `completion` and then shows
+  /// `detail` after it.
+  fn new(completion: &str, detail: &str) -> Self {
+    Self {
+      display: format!("{completion}{detail}"),
+      completion_len: completion.len(),
+    }
+  }
+}
+
+impl Hint for ReplHint {
+  fn display(&self) -> &str {
+    &self.display
+  }
+
+  fn completion(&self) -> Option<&str> {
+    let completion = &self.display[..self.completion_len];
+    (!completion.is_empty()).then_some(completion)
+  }
+}
+
+impl Highlighter for ReplHelper {
+  fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+    Cow::Owned(format!("\x1b[2m{hint}\x1b[0m"))
+  }
+}
+
+/// The name of every constant and function that can be hinted at in `env`,
+/// each along with its hint, sorted by name.
+///
+/// A constant is hinted with its value, as in `pi = 3.14…`, and a function with
+/// its parameters, as in `f(x, y)`. The parameters of built-in functions are
+/// unnamed, so each is shown as an underscore, or as an ellipsis for functions
+/// that take any number of arguments. User-defined functions come
+/// before built-in functions of the same name, since they shadow them.
+fn hint_names(env: &Environment) -> Vec<(String, String)> {
+  let constants = CONSTANTS
+    .iter()
+    .map(|(name, value)| (name.to_string(), format!(" = {value}")));
+  let functions = env.functions().map(|(name, params)| {
+    (name.to_string(), format!("({})", params.join(", ")))
+  });
+  let builtins = BUILTINS.iter().map(|&(name, arity)| match arity {
+    Some(arity) => {
+      (name.to_string(), format!("({})", vec!["_"; arity].join(", ")))
+    },
+    None => (name.to_string(), "(…)".to_string()),
+  });
+
+  let mut names: Vec<_> = functions.chain(builtins).chain(constants).collect();
+  // The sort is stable, so shadowing functions stay first.
+  names.sort_by(|(a, _), (b, _)| a.cmp(b));
+  names
 }
 
-fn run(expr: &str) -> Result<()> {
-  let (output, errs) = parse(expr).into_output_errors();
-  if !errs.is_empty() {
-    println!("{errs:?}");
+/// The hint to show after the cursor at `pos` in a line of REPL input, if the
+/// cursor is at the end of a partially typed name from `names`.
+///
+/// The hint completes the word before the cursor to the first name that starts
+/// with it, and then shows the hint for that name from `names`.
+fn hint(
+  line: &str,
+  pos: usize,
+  names: &[(String, String)],
+) -> Option<ReplHint> {
+  if pos < line.len() || line.trim_start().starts_with(':') {
+    return None;
   }
 
-  if let Some(expr) = output {
-    println!("Parse tree: {expr:?}");
-    println!("Result: {}", eval(expr));
+  let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+  let start = line
+    .char_indices()
+    .rev()
+    .take_while(|&(_, c)| is_ident(c))
+    .last()
+    .map_or(line.len(), |(i, _)| i);
+  let word = &line[start..];
+  if word.is_empty() || word.starts_with(|c: char| c.is_ascii_digit()) {
+    return None;
   }
 
-  Ok(())
+  names
+    .iter()
+    .find(|(name, _)| name.starts_with(word))
+    .map(|(name, hint)| ReplHint::new(&name[word.len()..], hint))
 }
 
-fn repl() -> Result<()> {
+fn repl(session: &mut Session, vi: bool, output: &mut dyn Write) -> Result<()> {
   // The second parameter is the 'organisation' name and is left blank because
   // it doesn't really make sense in this context: I'm just one guy writing
   // this!
@@ -58,8 +1400,29 @@ fn repl() -> Result<()> {
   let state_dir = proj_dirs.state_dir().unwrap_or_else(|| proj_dirs.data_dir());
   let history_path = state_dir.join("history.txt");
 
-  let mut rl = DefaultEditor::new()?;
+  let edit_mode_path = proj_dirs.config_dir().join("edit-mode");
+
+  let mut rl = ReplEditor::new()?;
   rl.set_auto_add_history(true);
+  let mut helper = ReplHelper::default();
+  helper.refresh(&session.env);
+  rl.set_helper(Some(helper));
+
+  let saved_edit_mode = match std::fs::read_to_string(&edit_mode_path) {
+    Ok(mode) => Some(mode),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+    Err(err) => {
+      eprintln!(
+        "Warning: {:#}",
+        Error::new(err).context(format!(
+          "Failed to load the editing mode from '{}'",
+          edit_mode_path.display()
+        ))
+      );
+      None
+    },
+  };
+  rl.set_edit_mode(resolve_edit_mode(vi, saved_edit_mode.as_deref()));
 
   match rl.load_history(&history_path) {
     Ok(_) => {},
@@ -80,9 +1443,36 @@ fn repl() -> Result<()> {
     },
   }
 
+  // The lines entered so far of an input that has been continued with a
+  // trailing backslash.
+  let mut pending = String::new();
+
   loop {
-    match rl.readline("> ") {
-      Ok(line) => run(&line)?,
+    let prompt = if pending.is_empty() { "> " } else { ". " };
+    match rl.readline(prompt) {
+      Ok(line) => {
+        let Some(input) = continue_line(&mut pending, &line) else {
+          continue;
+        };
+
+        match input.trim().strip_prefix(':') {
+          Some(command) => {
+            run_command(command, &mut rl, &edit_mode_path, session, output)?
+          },
+          None => {
+            if confirm_evaluation(&input, session, &mut rl)? {
+              run(&input, session, output)?;
+            }
+          },
+        }
+
+        // The input may have defined a function to hint at.
+        if let Some(helper) = rl.helper_mut() {
+          helper.refresh(&session.env);
+        }
+      },
+      // Interrupting a continued input abandons it rather than the REPL.
+      Err(ReadlineError::Interrupted) if !pending.is_empty() => pending.clear(),
       Err(ReadlineError::Eof | ReadlineError::Interrupted) => break,
       Err(err) => {
         eprintln!("REPL Error: {err:?}");
@@ -107,3 +1497,1119 @@ fn repl() -> Result<()> {
 
   Ok(())
 }
+
+/// Ask the user whether to evaluate an input if it looks expensive to evaluate,
+/// returning whether to go ahead.
+fn confirm_evaluation(
+  input: &str,
+  session: &Session,
+  rl: &mut ReplEditor,
+) -> Result<bool> {
+  let Some(threshold) = session.settings.confirm_complexity else {
+    return Ok(true);
+  };
+  // Inputs that fail to parse are left for `run` to report.
+  let Some(expr) = parse_with(input, session.parse_options).into_output()
+  else {
+    return Ok(true);
+  };
+  if !is_risky(&expr, threshold) {
+    return Ok(true);
+  }
+
+  // The answer is not worth keeping in the history.
+  rl.set_auto_add_history(false);
+  let answer = rl.readline(
+    "This expression may take a long time to evaluate. Continue? [y/N] ",
+  );
+  rl.set_auto_add_history(true);
+
+  match answer {
+    Ok(answer) => Ok(matches!(answer.trim(), "y" | "Y" | "yes")),
+    Err(ReadlineError::Eof | ReadlineError::Interrupted) => Ok(false),
+    Err(err) => Err(err.into()),
+  }
+}
+
+/// Whether an expression looks expensive enough to evaluate that the user
+/// should confirm it first, which is when its [`complexity`] exceeds the
+/// threshold and it contains a power or function call.
+///
+/// Other operations take time in proportion to the size of the expression, so
+/// only powers, whose results grow with their exponents, and function calls,
+/// which may recurse, can be much slower than the expression looks.
+fn is_risky(expr: &Expr, threshold: usize) -> bool {
+  let expensive = expr.fold(&mut |expr, children: Vec<bool>| {
+    matches!(expr, Expr::Pow(..) | Expr::Call(..))
+      || children.into_iter().any(|expensive| expensive)
+  });
+
+  expensive && complexity(expr) > threshold
+}
+
+/// A rough measure of the cost of evaluating an expression, which is the number
+/// of nodes in the expression plus the exponent of every power with a literal
+/// exponent.
+fn complexity(expr: &Expr) -> usize {
+  expr.fold(&mut |expr, children: Vec<usize>| {
+    let exponent = match expr {
+      Expr::Pow(_, exponent) => match &**exponent {
+        Expr::Literal(n) => usize::try_from(n).unwrap_or(usize::MAX),
+        _ => 0,
+      },
+      _ => 0,
+    };
+    children.into_iter().fold(exponent.saturating_add(1), usize::saturating_add)
+  })
+}
+
+/// Add a line of REPL input to the pending lines of a continued input.
+///
+/// A line ending in a backslash is continued onto the next line, in which case
+/// the line is added to `pending` without the backslash and `None` is returned.
+/// Otherwise, the complete input is returned and `pending` is cleared. As in a
+/// shell, the backslash is removed without leaving any space in its place.
+fn continue_line(pending: &mut String, line: &str) -> Option<String> {
+  match line.trim_end().strip_suffix('\\') {
+    Some(line) => {
+      pending.push_str(line);
+      None
+    },
+    None => {
+      pending.push_str(line);
+      Some(std::mem::take(pending))
+    },
+  }
+}
+
+/// Start recording inputs to a new file at `path`, as with `:record`.
+///
+/// The recording begins with a `:set` command for every setting, so that
+/// replaying it evaluates each input under the same settings even if they were
+/// changed before the recording began.
+fn start_recording(path: &Path, session: &mut Session) -> Result<()> {
+  let mut file = File::create(path).with_context(|| {
+    format!("Failed to create recording file '{}'", path.display())
+  })?;
+  for (key, value) in session.settings.list() {
+    writeln!(file, ":set {key} {value}")
+      .context("Failed to write to the recording")?;
+  }
+
+  session.recording = Some(file);
+  Ok(())
+}
+
+/// Change a setting as with `:set`, where `setting` is the name of the setting
+/// followed by its new value, as in `base hex`.
+///
+/// The change is recorded if inputs are being recorded, so that the inputs
+/// after it are evaluated under the same settings when they are replayed.
+fn run_set(setting: &str, session: &mut Session) -> Result<()> {
+  let Some((key, value)) = setting.split_once(char::is_whitespace) else {
+    bail!("Expected a value for '{setting}'");
+  };
+  let value = value.trim();
+  session.settings.set(key, value)?;
+
+  if let Some(recording) = &mut session.recording
+    && let Err(err) = writeln!(recording, ":set {key} {value}")
+      .context("Failed to write to the recording")
+  {
+    eprintln!("Warning: {err:#}");
+  }
+
+  Ok(())
+}
+
+/// Run a REPL command, which is a line starting with a colon such as
+/// `:history`.
+fn run_command(
+  command: &str,
+  rl: &mut ReplEditor,
+  edit_mode_path: &Path,
+  session: &mut Session,
+  output: &mut dyn Write,
+) -> Result<()> {
+  let history: Vec<&str> = rl.history().iter().map(String::as_str).collect();
+
+  let (name, arg) = match command.split_once(char::is_whitespace) {
+    Some((name, arg)) => (name, Some(arg.trim())),
+    None => (command, None),
+  };
+
+  match (name, arg) {
+    ("history", None) => write!(output, "{}", format_history(&history, None))?,
+    ("history", Some(limit)) => match limit.parse() {
+      Ok(limit) => write!(output, "{}", format_history(&history, Some(limit)))?,
+      Err(_) => eprintln!("Error: Invalid number of history entries '{limit}'"),
+    },
+    (recall, None) if let Some(index) = recall.strip_prefix('!') => {
+      let entry = index
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| history.get(index.checked_sub(1)?));
+      match entry {
+        Some(entry) => {
+          let entry = entry.to_string();
+          writeln!(output, "{entry}")?;
+          run(&entry, session, output)?;
+        },
+        None => eprintln!("Error: No history entry '{index}'"),
+      }
+    },
+    ("constants", None) => write!(output, "{}", format_constants())?,
+    ("record", Some(path)) => match start_recording(Path::new(path), session) {
+      Ok(()) => writeln!(output, "Recording inputs to '{path}'")?,
+      Err(err) => eprintln!("Error: {err:#}"),
+    },
+    ("stop-record", None) => match session.recording.take() {
+      Some(_) => writeln!(output, "Stopped recording")?,
+      None => eprintln!("Error: Not recording"),
+    },
+    ("dump", Some(input)) => match dump(input, session) {
+      Ok(dump) => write!(output, "{dump}")?,
+      Err(err) => eprintln!("Error: {err}"),
+    },
+    ("set", None) => {
+      for (key, value) in session.settings.list() {
+        writeln!(output, "{key} = {value}")?;
+      }
+    },
+    ("set", Some(setting)) => {
+      if let Err(err) = run_set(setting, session) {
+        eprintln!("Error: {err}");
+      }
+    },
+    (mode @ ("vi" | "emacs"), None) => {
+      let (edit_mode, saved) = match mode {
+        "vi" => (EditMode::Vi, "vi\n"),
+        _ => (EditMode::Emacs, "emacs\n"),
+      };
+      rl.set_edit_mode(edit_mode);
+
+      if let Err(err) = edit_mode_path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(edit_mode_path, saved))
+      {
+        eprintln!(
+          "Warning: {:#}",
+          Error::new(err).context(format!(
+            "Failed to save the editing mode to '{}'",
+            edit_mode_path.display()
+          ))
+        );
+      }
+    },
+    _ => eprintln!("Error: Unknown command ':{command}'"),
+  }
+
+  Ok(())
+}
+
+/// Evaluate an expression and render the internal limbs of the result as a hex
+/// dump, for the `:dump` command.
+fn dump(input: &str, session: &Session) -> Result<String> {
+  let expr = match parse_with(input, session.parse_options).into_result() {
+    Ok(Expr::FnDef(..)) => bail!("Cannot dump a function definition"),
+    Ok(expr) => expr,
+    Err(errs) => {
+      let errs: Vec<_> = errs.iter().map(ToString::to_string).collect();
+      bail!(errs.join("\n"));
+    },
+  };
+
+  match eval_with(&expr, &session.env)? {
+    Value::Integer(n) if n >= 0 => Ok(Natural::from(n as u64).hex_dump()),
+    Value::Natural(n) => Ok(n.hex_dump()),
+    _ => bail!("Can only dump nonnegative integers"),
+  }
+}
+
+/// Decide which key bindings the REPL should use.
+///
+/// The `--vi` flag takes precedence over the mode last chosen with `:vi` or
+/// `:emacs`, which in turn takes precedence over the default of Emacs key
+/// bindings.
+fn resolve_edit_mode(vi: bool, saved: Option<&str>) -> EditMode {
+  match saved.map(str::trim) {
+    _ if vi => EditMode::Vi,
+    Some("vi") => EditMode::Vi,
+    _ => EditMode::Emacs,
+  }
+}
+
+/// Format the named constants as a list of their names and values.
+fn format_constants() -> String {
+  let width = CONSTANTS.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+  let mut listing = String::new();
+  for (name, value) in CONSTANTS {
+    writeln!(listing, "{name:<width$} = {value}")
+      .expect("writing to a String cannot fail");
+  }
+  listing
+}
+
+/// Format the last `limit` history entries (or all of them if there is no
+/// limit) as a list, where each entry is preceded by its index.
+///
+/// The indices start from 1 and can be passed to the `:!` command to rerun the
+/// entry.
+fn format_history(entries: &[&str], limit: Option<usize>) -> String {
+  let skip = limit.map_or(0, |limit| entries.len().saturating_sub(limit));
+  let width = entries.len().to_string().len();
+
+  let mut listing = String::new();
+  for (index, entry) in entries.iter().enumerate().skip(skip) {
+    writeln!(listing, "{:>width$}  {entry}", index + 1)
+      .expect("writing to a String cannot fail");
+  }
+  listing
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use rkn::syntax::parse;
+
+  use super::*;
+
+  #[test]
+  fn test_output_file() -> Result<()> {
+    let path = std::env::temp_dir()
+      .join(format!("rkn-test-output-{}.txt", std::process::id()));
+
+    let mut session = Session::default();
+    let mut file = File::create(&path)?;
+    for expr in ["1 + 2", "2 3", "2 * 3"] {
+      run(expr, &mut session, &mut file)?;
+    }
+    drop(file);
+
+    let contents = fs::read_to_string(&path)?;
+    fs::remove_file(&path)?;
+
+    assert_eq!(
+      contents,
+      "Parse tree: 1 + 2\n\
+       Result: 3\n\
+       Parse tree: 2 * 3\n\
+       Result: 6\n"
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_parse_tree_formats() -> Result<()> {
+    let mut session = Session::default();
+    let mut output = Vec::new();
+    run("1 + 2 * 3", &mut session, &mut output)?;
+    assert!(
+      String::from_utf8(output)?.starts_with("Parse tree: 1 + (2 * 3)\n")
+    );
+
+    session.settings.verbose = true;
+    let mut output = Vec::new();
+    run("1 + 2 * 3", &mut session, &mut output)?;
+    assert!(String::from_utf8(output)?.starts_with(
+      "Parse tree: Add(Literal(1), Mul(Literal(2), Literal(3)))\n"
+    ));
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_function_definition() -> Result<()> {
+    let mut session = Session::default();
+    let mut output = Vec::new();
+    run("f(x) = x * x", &mut session, &mut output)?;
+    output.clear();
+
+    run("f(5)", &mut session, &mut output)?;
+    assert!(String::from_utf8(output)?.ends_with("Result: 25\n"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_trailing_garbage() -> Result<()> {
+    let mut session = Session::default();
+    let mut output = Vec::new();
+    run("2+2 foo", &mut session, &mut output)?;
+    assert!(!String::from_utf8(output)?.contains("Result:"));
+
+    session.allow_trailing_garbage = true;
+    let mut output = Vec::new();
+    run("2+2 foo", &mut session, &mut output)?;
+    assert!(String::from_utf8(output)?.ends_with("Result: 4\n"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_parse_args_subcommands() {
+    let parse = |args: &[&str]| {
+      Args::try_parse_from(std::iter::once("rkn").chain(args.iter().copied()))
+    };
+
+    let args = parse(&["1", "+", "2"]).unwrap();
+    assert_eq!(
+      (args.command, args.expr),
+      (None, vec!["1".into(), "+".into(), "2".into()])
+    );
+
+    let args = parse(&["--base", "hex", "calc", "1", "+", "2"]).unwrap();
+    assert_eq!(
+      args.command,
+      Some(Command::Calc { expr: vec!["1".into(), "+".into(), "2".into()] })
+    );
+    assert_eq!(args.base, Base::Hexadecimal);
+
+    let args = parse(&["factor", "360"]).unwrap();
+    assert_eq!(args.command, Some(Command::Factor { n: 360 }));
+    assert!(parse(&["factor", "-1"]).is_err());
+    assert!(parse(&["factor"]).is_err());
+
+    let args = parse(&["convert", "2 + 3", "km", "m"]).unwrap();
+    assert_eq!(
+      args.command,
+      Some(Command::Convert {
+        expr: "2 + 3".into(),
+        from: "km".into(),
+        to: "m".into()
+      })
+    );
+    assert!(parse(&["convert", "1", "km"]).is_err());
+  }
+
+  #[test]
+  fn test_profile() -> Result<()> {
+    let session = Session::default();
+    let (value, stages) = profile("(1 + 2) * 3 ^ 4 - 5 * (6 + 7)", &session)?;
+    assert_eq!(value, Value::Integer(178));
+    assert!(stages.build > Duration::ZERO);
+    assert!(stages.parse > Duration::ZERO);
+    assert!(stages.eval > Duration::ZERO);
+
+    assert!(profile("1 +", &session).is_err());
+    assert!(profile("f(x) = x", &session).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_dump_dot() -> Result<()> {
+    let session = Session::default();
+    let mut output = Vec::new();
+    run_dump_dot("-x", &session, &mut output)?;
+    assert_eq!(
+      String::from_utf8(output)?,
+      "digraph {\n  n0 [label=\"x\"];\n  n1 [label=\"-\"];\n  n1 -> n0;\n}\n"
+    );
+
+    let mut output = Vec::new();
+    run_dump_dot("1 +", &session, &mut output)?;
+    assert!(output.is_empty());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_dump() {
+    let session = Session::default();
+    assert_eq!(
+      dump("2 ^ 8", &session).unwrap(),
+      "small\n[0] 0x0000000000000100\n"
+    );
+    assert_eq!(
+      dump("-1", &session).unwrap_err().to_string(),
+      "Can only dump nonnegative integers"
+    );
+    assert_eq!(
+      dump("1.5", &session).unwrap_err().to_string(),
+      "Can only dump nonnegative integers"
+    );
+    assert_eq!(
+      dump("f(x) = x", &session).unwrap_err().to_string(),
+      "Cannot dump a function definition"
+    );
+  }
+
+  #[test]
+  fn test_complexity() {
+    let complexity = |input| complexity(&parse(input).unwrap());
+    assert_eq!(complexity("1"), 1);
+    assert_eq!(complexity("1 + 2 * 3"), 5);
+    assert_eq!(complexity("2 ^ 10"), 13);
+    assert_eq!(complexity("2 ^ (5 + 5)"), 5);
+    assert_eq!(complexity("(2 ^ 1000) ^ 1000"), 2005);
+  }
+
+  #[test]
+  fn test_is_risky() {
+    let is_risky = |input: &str| is_risky(&parse(input).unwrap(), 1000);
+
+    assert!(is_risky("2 ^ 100000"));
+    assert!(is_risky("(2 ^ 30) ^ 999"));
+    assert!(!is_risky("2 ^ 10"));
+    assert!(!is_risky("1.5 ^ 100"));
+
+    // Long expressions are only risky if they contain something expensive.
+    let sum = vec!["1"; 600].join(" + ");
+    assert!(!is_risky(&sum));
+    assert!(is_risky(&format!("{sum} + 2 ^ 3")));
+    assert!(is_risky(&format!("{sum} + f(1)")));
+  }
+
+  #[test]
+  fn test_run_factor() -> Result<()> {
+    let mut output = Vec::new();
+    run_factor(360, &mut output)?;
+    assert_eq!(String::from_utf8(output)?, "360 = 2^3 * 3^2 * 5\n");
+    assert!(run_factor(0, &mut Vec::new()).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_describe_factors() {
+    assert_eq!(describe_factors(360).as_deref(), Some("= 2^3 * 3^2 * 5"));
+    assert_eq!(describe_factors(97).as_deref(), Some("= 97"));
+    assert_eq!(describe_factors(-12).as_deref(), Some("= -1 * 2^2 * 3"));
+    assert_eq!(describe_factors(1), None);
+    assert_eq!(describe_factors(0), None);
+    assert_eq!(describe_factors(-1), None);
+    assert_eq!(
+      describe_factors(i64::MAX).as_deref(),
+      Some("= 7^2 * 73 * 127 * 337 * 92737 * 649657")
+    );
+    assert_eq!(describe_factors(i64::MIN).as_deref(), Some("= -1 * 2^63"));
+  }
+
+  #[test]
+  fn test_run_with_factors() -> Result<()> {
+    let mut session = Session::default();
+    session.settings.with_factors = true;
+    let mut output = Vec::new();
+    run("6 * 60", &mut session, &mut output)?;
+    run("1.5", &mut session, &mut output)?;
+    assert_eq!(
+      String::from_utf8(output)?,
+      "Parse tree: 6 * 60\nResult: 360 = 2^3 * 3^2 * 5\n\
+       Parse tree: 1.5\nResult: 1.5\n"
+    );
+
+    // The factorisation is always written in decimal.
+    session.settings.base = Base::Hexadecimal;
+    assert_eq!(
+      format_value(Value::Integer(255), &session.settings).unwrap(),
+      "ff = 3 * 5 * 17"
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_div_mode() -> Result<()> {
+    let mut session = Session::default();
+    run("1 + 2", &mut session, &mut Vec::new())?;
+    assert!(session.cache.get("1 + 2").is_some());
+
+    // Changing the division mode applies it to the environment and discards
+    // the results evaluated under the old mode.
+    session.settings.set("div-mode", "rational")?;
+    run("3", &mut session, &mut Vec::new())?;
+    assert_eq!(session.env.div_mode(), DivMode::Rational);
+    assert!(session.cache.get("1 + 2").is_none());
+
+    let mut output = Vec::new();
+    run("7 / 2", &mut session, &mut output)?;
+    assert!(String::from_utf8(output)?.ends_with("Result: 7/2\n"));
+
+    let parse_args = |args: &[&str]| {
+      Args::try_parse_from(std::iter::once("rkn").chain(args.iter().copied()))
+    };
+    let args = parse_args(&["--div-mode", "float", "1"]).unwrap();
+    assert_eq!(args.div_mode, DivMode::Float);
+    assert_eq!(parse_args(&["1"]).unwrap().div_mode, DivMode::Truncate);
+    assert_eq!(
+      parse_args(&["--div-mode", "floor", "1"]).unwrap().div_mode,
+      DivMode::Floor
+    );
+    assert!(parse_args(&["--div-mode", "round", "1"]).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_cache() -> Result<()> {
+    let mut session = Session::default();
+    let mut output = Vec::new();
+    run("1 + 2", &mut session, &mut output)?;
+    run("f(x) = x", &mut session, &mut output)?;
+    run("f(1)", &mut session, &mut output)?;
+    assert!(session.cache.get("1 + 2").is_some());
+    assert!(session.cache.get("f(1)").is_none());
+
+    // Replace the cached result so that we can tell whether the input is
+    // evaluated again.
+    let expr = parse("1 + 2").into_result().unwrap();
+    session
+      .cache
+      .insert("1 + 2".to_string(), (Rc::new(expr), Value::Integer(42)));
+
+    let mut output = Vec::new();
+    run("  1 + 2 ", &mut session, &mut output)?;
+    assert_eq!(String::from_utf8(output)?, "Parse tree: 1 + 2\nResult: 42\n");
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_lru_cache() {
+    let mut evaluations = 0;
+    let mut cache = LruCache::new(2);
+    let mut evaluate = |cache: &mut LruCache<i32>, key: &str| {
+      if let Some(&value) = cache.get(key) {
+        return value;
+      }
+      evaluations += 1;
+      cache.insert(key.to_string(), key.len() as i32);
+      key.len() as i32
+    };
+
+    assert_eq!(evaluate(&mut cache, "a"), 1);
+    assert_eq!(evaluate(&mut cache, "a"), 1);
+    assert_eq!(evaluate(&mut cache, "bb"), 2);
+    assert_eq!(evaluate(&mut cache, "a"), 1);
+    assert_eq!(evaluate(&mut cache, "ccc"), 3);
+    assert_eq!(evaluations, 3);
+
+    // Using "a" more recently than "bb" means "bb" is the one to be evicted.
+    assert!(cache.get("bb").is_none());
+    assert_eq!(cache.get("a"), Some(&1));
+    assert_eq!(cache.get("ccc"), Some(&3));
+  }
+
+  #[test]
+  fn test_record_and_replay() -> Result<()> {
+    let path = std::env::temp_dir()
+      .join(format!("rkn-test-recording-{}.rkn", std::process::id()));
+
+    let mut session = Session::default();
+    session.settings.set("base", "hex")?;
+    start_recording(&path, &mut session)?;
+    let mut recorded = Vec::new();
+    for input in ["f(x) = x ^ 2", "  f(3) + 1  ", "1 +", "f(f(2))", "5 / 2"] {
+      run(input, &mut session, &mut recorded)?;
+    }
+    run_set("div-mode rational", &mut session)?;
+    run("5 / 2", &mut session, &mut recorded)?;
+    assert!(run_set("div-mode round", &mut session).is_err());
+    session.recording = None;
+
+    let contents = fs::read_to_string(&path)?;
+    let mut replayed = Vec::new();
+    let result = run_file(&path, &mut Session::default(), &mut replayed);
+    fs::remove_file(&path)?;
+    result?;
+
+    // The recording begins with every setting, and the settings changed while
+    // recording are recorded too, but the failed change and the input with an
+    // error are left out.
+    let settings: String =
+      Settings { base: Base::Hexadecimal, ..Default::default() }
+        .list()
+        .into_iter()
+        .map(|(key, value)| format!(":set {key} {value}\n"))
+        .collect();
+    assert_eq!(
+      contents,
+      format!(
+        "{settings}f(x) = x ^ 2\nf(3) + 1\nf(f(2))\n5 / 2\n\
+         :set div-mode rational\n5 / 2\n"
+      )
+    );
+    let replayed = String::from_utf8(replayed)?;
+    assert_eq!(replayed, String::from_utf8(recorded)?);
+    assert!(replayed.ends_with("Result: 2\nParse tree: 5 / 2\nResult: 5/2\n"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_files() -> Result<()> {
+    let dir = std::env::temp_dir();
+    let prelude =
+      dir.join(format!("rkn-test-prelude-{}.rkn", std::process::id()));
+    let script =
+      dir.join(format!("rkn-test-script-{}.rkn", std::process::id()));
+    fs::write(
+      &prelude,
+      "double(x) = 2 * x\n\nquadruple(x) = double(double(x))\n",
+    )?;
+    fs::write(&script, "quadruple(5)\nquadruple(10) + 2\n")?;
+
+    let mut session = Session::default();
+    let mut output = Vec::new();
+    run_file(&prelude, &mut session, &mut output)?;
+    output.clear();
+    run_file(&script, &mut session, &mut output)?;
+
+    let output = String::from_utf8(output)?;
+    let results: Vec<_> =
+      output.lines().filter(|line| line.starts_with("Result:")).collect();
+    assert_eq!(results, ["Result: 20", "Result: 42"]);
+    assert!(session.location.is_none());
+
+    fs::remove_file(&prelude)?;
+    fs::remove_file(&script)?;
+    assert!(run_file(&script, &mut session, &mut Vec::new()).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_lines_after_expression() -> Result<()> {
+    let mut session = Session::default();
+    let mut output = Vec::new();
+    run("f(x) = x + 1", &mut session, &mut output)?;
+    output.clear();
+
+    let stdin = io::Cursor::new("f(10)\n\nf(f(1))\n");
+    run_lines(stdin, "<stdin>", &mut session, &mut output)?;
+
+    let output = String::from_utf8(output)?;
+    let results: Vec<_> =
+      output.lines().filter(|line| line.starts_with("Result:")).collect();
+    assert_eq!(results, ["Result: 11", "Result: 3"]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_log_file() -> Result<()> {
+    let path = std::env::temp_dir()
+      .join(format!("rkn-test-log-{}.jsonl", std::process::id()));
+
+    let mut session =
+      Session { log: Some(open_log(&path)?), ..Default::default() };
+    let mut output = Vec::new();
+    for input in ["1 + 2", "1 km in kg", "f(x) = x"] {
+      run(input, &mut session, &mut output)?;
+    }
+    drop(session);
+
+    let contents = fs::read_to_string(&path)?;
+    fs::remove_file(&path)?;
+
+    let records: Vec<_> = contents.lines().collect();
+    assert_eq!(records.len(), 3);
+
+    // Strip the timestamps, which we cannot predict, from each record.
+    let records: Vec<_> = records
+      .iter()
+      .map(|record| {
+        let (timestamp, rest) = record
+          .strip_prefix("{\"timestamp\":")
+          .and_then(|r| r.split_once(','))
+          .expect("record should begin with a timestamp");
+        assert!(timestamp.parse::<u64>().is_ok(), "bad timestamp in {record}");
+        rest
+      })
+      .collect();
+
+    assert_eq!(records[0], r#""input":"1 + 2","result":"3"}"#);
+    assert_eq!(
+      records[1],
+      r#""input":"1 km in kg","error":"Cannot convert from km (length) to kg (mass)"}"#
+    );
+    assert_eq!(records[2], r#""input":"f(x) = x","result":null}"#);
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_json_string() {
+    assert_eq!(json_string("1 + 2"), r#""1 + 2""#);
+    assert_eq!(json_string("say \"hi\"\n"), r#""say \"hi\"\n""#);
+    assert_eq!(json_string("a\\b\u{7}"), r#""a\\b\u0007""#);
+  }
+
+  #[test]
+  fn test_benchmark() -> Result<()> {
+    let expr = parse("2 ^ 10 + 3 * 4").into_result().unwrap();
+    let iterations = NonZeroU32::new(25).unwrap();
+    let (value, stats) = benchmark(&expr, &Environment::default(), iterations)?;
+
+    assert_eq!(value, Value::Integer(1036));
+    assert_eq!(stats.iterations, 25);
+    assert!(stats.min <= stats.mean && stats.mean <= stats.max);
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_benchmark_error() {
+    let expr = parse("1 km in kg").into_result().unwrap();
+    let iterations = NonZeroU32::new(3).unwrap();
+    assert!(benchmark(&expr, &Environment::default(), iterations).is_err());
+  }
+
+  #[test]
+  fn test_should_show_progress() {
+    assert!(!should_show_progress(Duration::ZERO));
+    assert!(!should_show_progress(Duration::from_millis(199)));
+    assert!(should_show_progress(Duration::from_millis(200)));
+    assert!(should_show_progress(Duration::from_secs(5)));
+  }
+
+  #[test]
+  fn test_with_progress() {
+    assert_eq!(with_progress(true, || 1 + 2), 3);
+    assert_eq!(with_progress(false, || 1 + 2), 3);
+  }
+
+  #[test]
+  fn test_format_integer() {
+    assert_eq!(format_integer(255, Base::Hexadecimal, false, None), "ff");
+    assert_eq!(format_integer(255, Base::Hexadecimal, true, None), "0xff");
+    assert_eq!(format_integer(5, Base::Binary, false, None), "101");
+    assert_eq!(format_integer(5, Base::Binary, true, None), "0b101");
+    assert_eq!(format_integer(8, Base::Octal, true, None), "0o10");
+    assert_eq!(format_integer(42, Base::Decimal, true, None), "42");
+    assert_eq!(format_integer(-255, Base::Hexadecimal, true, None), "-0xff");
+    assert_eq!(
+      format_integer(i64::MIN, Base::Hexadecimal, false, None),
+      "-8000000000000000"
+    );
+  }
+
+  #[test]
+  fn test_format_natural() {
+    let n = Natural::from(1_u128 << 64);
+    assert_eq!(
+      format_natural(&n, Base::Hexadecimal, true, None),
+      "0x10000000000000000"
+    );
+    assert_eq!(
+      format_natural(&n, Base::Octal, false, None),
+      "2000000000000000000000"
+    );
+    assert_eq!(
+      format_natural(&n, Base::Decimal, true, None),
+      "18446744073709551616"
+    );
+    assert_eq!(
+      format_natural(&n, Base::Decimal, false, Some(10)),
+      "1844...1616 (20 digits)"
+    );
+  }
+
+  #[test]
+  fn test_run_eval_error() -> Result<()> {
+    // The error is reported on standard error instead of a result.
+    let mut session = Session::default();
+    let mut output = Vec::new();
+    run("2 ^ -3", &mut session, &mut output)?;
+    assert_eq!(String::from_utf8(output)?, "Parse tree: 2 ^ (-3)\n");
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_large_literal() -> Result<()> {
+    let mut session = Session::default();
+    let mut output = Vec::new();
+    run("123456789012345678901234567890 + 1", &mut session, &mut output)?;
+    assert_eq!(
+      String::from_utf8(output)?,
+      "Parse tree: 123456789012345678901234567890 + 1\n\
+       Result: 123456789012345678901234567891\n"
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_format_twos_complement() {
+    let settings =
+      Settings { base: Base::Hexadecimal, prefix: true, ..Default::default() };
+    assert_eq!(format_value(Value::Integer(-255), &settings).unwrap(), "-0xff");
+
+    let settings = Settings { twos_complement: Some(16), ..settings };
+    assert_eq!(
+      format_value(Value::Integer(-255), &settings).unwrap(),
+      "0xff01"
+    );
+    assert_eq!(format_value(Value::Integer(255), &settings).unwrap(), "0xff");
+
+    assert_eq!(
+      format_twos_complement(-1, 8, Base::Binary, false),
+      Some("11111111".to_string())
+    );
+    assert_eq!(
+      format_twos_complement(-128, 8, Base::Binary, true),
+      Some("0b10000000".to_string())
+    );
+    assert_eq!(
+      format_twos_complement(-255, 12, Base::Octal, false),
+      Some("7401".to_string())
+    );
+    assert_eq!(
+      format_twos_complement(i64::MIN, 64, Base::Hexadecimal, false),
+      Some("8000000000000000".to_string())
+    );
+    assert_eq!(
+      format_twos_complement(-1, 64, Base::Hexadecimal, false),
+      Some("ffffffffffffffff".to_string())
+    );
+  }
+
+  #[test]
+  fn test_format_twos_complement_fallback() {
+    // Integers that are out of range for the width keep their sign.
+    assert_eq!(format_twos_complement(-129, 8, Base::Binary, false), None);
+    assert_eq!(format_twos_complement(-1, 8, Base::Decimal, false), None);
+    assert_eq!(format_twos_complement(1, 8, Base::Binary, false), None);
+
+    let settings = Settings {
+      base: Base::Hexadecimal,
+      twos_complement: Some(8),
+      ..Default::default()
+    };
+    assert_eq!(format_value(Value::Integer(-255), &settings).unwrap(), "-ff");
+    assert_eq!(format_value(Value::Integer(-1), &settings).unwrap(), "ff");
+  }
+
+  #[test]
+  fn test_format_value_roman() {
+    let settings = Settings { base: Base::Roman, ..Default::default() };
+    assert_eq!(
+      format_value(Value::Integer(1994), &settings).unwrap(),
+      "MCMXCIV"
+    );
+    assert_eq!(format_value(Value::Float(1.5), &settings).unwrap(), "1.5");
+    assert_eq!(
+      format_value(Value::Integer(4000), &settings).unwrap_err().to_string(),
+      "Cannot write 4000 as a Roman numeral, which must be between 1 and 3999"
+    );
+  }
+
+  #[test]
+  fn test_continue_line() {
+    let mut pending = String::new();
+    assert_eq!(continue_line(&mut pending, "1 + 2 *\\"), None);
+    assert_eq!(continue_line(&mut pending, " 3 \\  "), None);
+    let input = continue_line(&mut pending, "+ 4").unwrap();
+    assert_eq!(input, "1 + 2 * 3 + 4");
+    assert!(pending.is_empty());
+    assert!(parse(&input).into_result().is_ok());
+
+    assert_eq!(continue_line(&mut pending, "5"), Some("5".to_string()));
+  }
+
+  #[test]
+  fn test_settings_set() -> Result<()> {
+    let mut settings = Settings::default();
+    settings.set("base", "hex")?;
+    settings.set("prefix", "on")?;
+    settings.set("max-digits", "10")?;
+    assert_eq!(settings.base, Base::Hexadecimal);
+    assert!(settings.prefix);
+    assert_eq!(settings.max_digits, Some(10));
+    assert_eq!(format_value(Value::Integer(255), &settings).unwrap(), "0xff");
+
+    settings.set("base", "2")?;
+    settings.set("max-digits", "off")?;
+    assert_eq!(settings.base, Base::Binary);
+    assert_eq!(settings.max_digits, None);
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_settings_set_invalid() {
+    let mut settings = Settings::default();
+    let mut err =
+      |key, value| settings.set(key, value).unwrap_err().to_string();
+
+    assert_eq!(err("colour", "on"), "Unknown setting 'colour'");
+    assert_eq!(err("base", "12"), "Unknown base '12'");
+    assert_eq!(
+      err("si", "maybe"),
+      "Expected 'on' or 'off' for 'si' but found 'maybe'"
+    );
+    assert!(err("max-digits", "-1").starts_with("Expected a number of digits"));
+    assert!(err("twos-complement", "65").starts_with("Expected a width"));
+    assert_eq!(
+      err("div-mode", "round"),
+      "Expected 'truncate', 'floor', 'ceil', 'round-half-even', \
+       'round-half-up', 'float', or 'rational' for 'div-mode' but found \
+       'round'"
+    );
+  }
+
+  #[test]
+  fn test_settings_list() {
+    let settings =
+      Settings { si: true, base: Base::Octal, ..Default::default() };
+    let list = settings.list();
+
+    assert!(list.contains(&("si", "on".to_string())));
+    assert!(list.contains(&("sexpr", "off".to_string())));
+    assert!(list.contains(&("verbose", "off".to_string())));
+    assert!(list.contains(&("base", "oct".to_string())));
+    assert!(list.contains(&("max-digits", "off".to_string())));
+
+    // Every listed setting should be able to be set to its listed value.
+    let mut copy = Settings::default();
+    for (key, value) in &list {
+      copy.set(key, value).unwrap();
+    }
+    assert_eq!(copy.list(), list);
+  }
+
+  #[test]
+  fn test_resolve_edit_mode() {
+    assert_eq!(resolve_edit_mode(false, None), EditMode::Emacs);
+    assert_eq!(resolve_edit_mode(true, None), EditMode::Vi);
+    assert_eq!(resolve_edit_mode(false, Some("vi\n")), EditMode::Vi);
+    assert_eq!(resolve_edit_mode(false, Some("emacs\n")), EditMode::Emacs);
+    assert_eq!(resolve_edit_mode(true, Some("emacs\n")), EditMode::Vi);
+    assert_eq!(resolve_edit_mode(false, Some("nonsense")), EditMode::Emacs);
+  }
+
+  #[test]
+  fn test_hint() {
+    let mut env = Environment::default();
+    env.define_function(
+      "rand_pair".to_string(),
+      vec!["low".to_string(), "high".to_string()],
+      parse("low + high").into_result().unwrap(),
+    );
+    let names = hint_names(&env);
+    let hint = |line: &str| hint(line, line.len(), &names);
+
+    assert_eq!(
+      hint("2 * p"),
+      Some(ReplHint::new("hi", " = 1.618033988749895"))
+    );
+    assert_eq!(hint("2 * pi"), Some(ReplHint::new("", " = 3.141592653589793")));
+    assert_eq!(hint("randi"), Some(ReplHint::new("nt", "(_, _)")));
+    assert_eq!(hint("pro"), Some(ReplHint::new("d", "(…)")));
+    assert_eq!(hint("1 + rand_"), Some(ReplHint::new("pair", "(low, high)")));
+
+    // Accepting a hint only completes the name.
+    let tau = hint("ta").unwrap();
+    assert_eq!(tau.display(), "u = 6.283185307179586");
+    assert_eq!(tau.completion(), Some("u"));
+    assert_eq!(hint("2 * pi").unwrap().completion(), None);
+    assert_eq!(hint("randi").unwrap().completion(), Some("nt"));
+
+    // There is nothing to hint at without a partial name that matches.
+    assert_eq!(hint("1 + "), None);
+    assert_eq!(hint("12"), None);
+    assert_eq!(hint("zeta"), None);
+    assert_eq!(hint(":set ba"), None);
+    assert_eq!(super::hint("pi + 1", 2, &names), None);
+  }
+
+  #[test]
+  fn test_hint_shadowing() {
+    let mut env = Environment::default();
+    env.define_function(
+      "randint".to_string(),
+      vec!["n".to_string()],
+      parse("n").into_result().unwrap(),
+    );
+    let names = hint_names(&env);
+    assert_eq!(hint("randint", 7, &names), Some(ReplHint::new("", "(n)")));
+  }
+
+  #[test]
+  fn test_format_constants() {
+    let list = format_constants();
+    assert_eq!(list.lines().count(), CONSTANTS.len());
+    assert!(list.starts_with("pi  = 3.14159"));
+
+    for &(name, value) in CONSTANTS {
+      assert!(list.contains(&format!("{name:<3} = {value}\n")), "{name}");
+
+      let expr = parse(name).into_result().unwrap();
+      assert_eq!(
+        eval_with(&expr, &Environment::default()),
+        Ok(Value::Float(value))
+      );
+    }
+  }
+
+  #[test]
+  fn test_format_history() {
+    let entries: Vec<_> = (1..=12).map(|n| format!("{n} + {n}")).collect();
+    let entries: Vec<_> = entries.iter().map(String::as_str).collect();
+
+    assert_eq!(
+      format_history(&entries, Some(3)),
+      "10  10 + 10\n11  11 + 11\n12  12 + 12\n"
+    );
+    assert_eq!(format_history(&entries, Some(0)), "");
+    assert_eq!(format_history(&entries, None).lines().count(), 12);
+    assert!(format_history(&entries, None).starts_with(" 1  1 + 1\n"));
+    assert_eq!(format_history(&entries[..2], Some(5)), "1  1 + 1\n2  2 + 2\n");
+  }
+
+  #[test]
+  fn test_format_si() {
+    assert_eq!(format_si(1500.0), "1.5k");
+    assert_eq!(format_si(0.001), "1m");
+    assert_eq!(format_si(-2.5e6), "-2.5M");
+    assert_eq!(format_si(4.7e-9), "4.7n");
+    assert_eq!(format_si(12.0), "12");
+    assert_eq!(format_si(0.0), "0");
+    assert_eq!(format_si(1e40), "10000000000000000000000000000000000000000");
+  }
+
+  #[test]
+  fn test_format_si_boundaries() {
+    assert_eq!(format_si(999.0), "999");
+    assert_eq!(format_si(1000.0), "1k");
+    assert_eq!(format_si(1.0), "1");
+    assert_eq!(format_si(0.5), "500m");
+  }
+
+  #[test]
+  fn test_format_integer_max_digits() {
+    assert_eq!(
+      format_integer(1_234_567_890_123, Base::Decimal, false, Some(10)),
+      "1234...0123 (13 digits)"
+    );
+    assert_eq!(
+      format_integer(-1_234_567_890_123, Base::Decimal, false, Some(10)),
+      "-1234...0123 (13 digits)"
+    );
+    assert_eq!(
+      format_integer(0xff_ffff_ffff, Base::Hexadecimal, true, Some(8)),
+      "0xffff...ffff (10 digits)"
+    );
+    assert_eq!(
+      format_integer(1_234_567_890, Base::Decimal, false, Some(10)),
+      "1234567890"
+    );
+    assert_eq!(
+      format_integer(123_456, Base::Decimal, false, Some(2)),
+      "123456 (6 digits)"
+    );
+  }
+
+  #[test]
+  fn test_format_value_si() {
+    let settings = Settings { si: true, ..Default::default() };
+    assert_eq!(format_value(Value::Float(1500.0), &settings).unwrap(), "1.5k");
+    assert_eq!(format_value(Value::Integer(1500), &settings).unwrap(), "1500");
+  }
+}
@@ -1,7 +1,19 @@
 // SPDX-FileCopyrightText: 2025 Severen Redwood <sev@severen.dev>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::ops::{Add, AddAssign, Mul, MulAssign};
+use std::{
+  cmp::Ordering,
+  fmt::{self, Display, Formatter, Write as _},
+  hash::{Hash, Hasher},
+  ops::{
+    Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor,
+    BitXorAssign, Div, DivAssign, Mul, MulAssign, Rem, RemAssign, Shl,
+    ShlAssign, Shr, ShrAssign,
+  },
+  str::FromStr,
+};
+
+use thiserror::Error;
 
 /// A single digit of an arbitrary-precision integer.
 ///
@@ -13,6 +25,29 @@ type Limb = u64;
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Natural(Repr);
 
+/// An error which can be returned when constructing a [`Natural`] from its
+/// digits or parsing one from a string.
+#[derive(Debug, Clone, Error, Eq, PartialEq)]
+pub enum ParseNaturalError {
+  #[error("Radix {0} is not between 2 and 256")]
+  InvalidRadix(u32),
+  #[error("Digit {digit} is out of range for radix {radix}")]
+  InvalidDigit { digit: u8, radix: u32 },
+  #[error("Cannot parse a natural number from an empty string")]
+  Empty,
+  #[error("Invalid character '{0}' in natural number")]
+  InvalidCharacter(char),
+}
+
+/// The error returned when converting a [`Natural`] to a primitive integer type
+/// that is too small to hold it.
+#[derive(Debug, Clone, Copy, Error, Eq, PartialEq)]
+#[error("Natural number is too large to fit in {target}")]
+pub struct TryFromNaturalError {
+  /// The name of the type that the natural number was being converted to.
+  pub target: &'static str,
+}
+
 /// The internal representation of a [`Natural`].
 ///
 /// If the number fits within a single [`Limb`], it is stored inline as a single
@@ -42,7 +77,10 @@ enum Repr {
   /// architectures).
   ///
   /// Note that the backing vector can be assumed to contain two or more limbs
-  /// since the natural should be stored in the `Small` variant otherwise.
+  /// since the natural should be stored in the `Small` variant otherwise, and
+  /// that its most-significant limb is nonzero. Comparisons rely on this, since
+  /// it means a large natural always has more limbs than any smaller number.
+  /// [`Natural::normalize`] restores both invariants.
   Large(Vec<Limb>),
 }
 
@@ -52,257 +90,1785 @@ impl Natural {
   /// The natural number 1.
   pub const ONE: Self = Self(Repr::Small(1));
 
-  #[cfg(test)]
-  fn from_limbs(limbs: &[Limb]) -> Self {
-    if limbs.is_empty() {
-      Self(Repr::Small(0))
-    } else if limbs.len() == 1 {
-      Self(Repr::Small(limbs[0]))
-    } else {
-      Self(Repr::Large(limbs.to_vec()))
+  /// Construct a natural number from its digits in the given radix, ordered
+  /// from most-significant to least-significant.
+  ///
+  /// The radix must be between 2 and 256 (inclusive) and each digit must be
+  /// less than the radix. An empty slice of digits represents zero.
+  pub fn from_digits(
+    digits: &[u8],
+    radix: u32,
+  ) -> Result<Natural, ParseNaturalError> {
+    if !(2..=256).contains(&radix) {
+      return Err(ParseNaturalError::InvalidRadix(radix));
+    }
+
+    // Rather than shifting in one digit at a time, which would take a pass over
+    // the limbs per digit, we gather as many digits as fit within a limb and
+    // shift them all in at once.
+    let radix = Limb::from(radix);
+    let chunk_len = Limb::MAX.ilog(radix) as usize;
+
+    let mut limbs: Vec<Limb> = Vec::new();
+    for chunk in digits.chunks(chunk_len) {
+      let mut value = 0;
+      for &digit in chunk {
+        if Limb::from(digit) >= radix {
+          return Err(ParseNaturalError::InvalidDigit {
+            digit,
+            radix: radix as u32,
+          });
+        }
+        value = value * radix + Limb::from(digit);
+      }
+
+      mul_add_limb(&mut limbs, radix.pow(chunk.len() as u32), value);
     }
+
+    Ok(Natural::from_vec(limbs))
   }
-}
 
-impl From<Limb> for Natural {
-  fn from(value: Limb) -> Self {
-    Self(Repr::Small(value))
+  /// The digits of this natural number in the given radix, ordered from
+  /// most-significant to least-significant.
+  ///
+  /// Zero has the single digit 0 and any other number has no leading zeros.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the radix is not between 2 and 256 (inclusive).
+  pub fn to_digits(&self, radix: u32) -> Vec<u8> {
+    assert!((2..=256).contains(&radix), "radix {radix} is out of range");
+
+    let mut limbs = self.limbs().to_vec();
+    let mut digits = Vec::new();
+
+    loop {
+      // The remainder of dividing by the radix is the next digit.
+      digits.push(div_rem_limb(&mut limbs, Limb::from(radix)) as u8);
+      if limbs.is_empty() {
+        break;
+      }
+    }
+
+    digits.reverse();
+    digits
   }
-}
 
-impl Add for Natural {
-  type Output = Self;
+  /// Parse a natural number written in the given radix, with an optional
+  /// leading `+`, where the letters `a` to `z` denote the digits from 10 to 35
+  /// regardless of case.
+  ///
+  /// This is the counterpart of [`Natural::to_str_radix`], and parsing in
+  /// radix 10 is the same as [`str::parse`].
+  ///
+  /// # Panics
+  ///
+  /// Panics if the radix is not between 2 and 36 (inclusive).
+  pub fn from_str_radix(
+    s: &str,
+    radix: u32,
+  ) -> Result<Natural, ParseNaturalError> {
+    assert!((2..=36).contains(&radix), "radix {radix} is out of range");
+
+    let s = s.strip_prefix('+').unwrap_or(s);
+    if s.is_empty() {
+      return Err(ParseNaturalError::Empty);
+    }
+
+    // A character that is a digit in a larger radix, like `2` in binary, is as
+    // invalid as any other.
+    let digits = s
+      .chars()
+      .map(|c| match c.to_digit(radix) {
+        Some(digit) => Ok(digit as u8),
+        None => Err(ParseNaturalError::InvalidCharacter(c)),
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Natural::from_digits(&digits, radix)
+  }
+
+  /// Write this natural number in the given radix, using the letters `a` to `z`
+  /// for the digits from 10 to 35.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the radix is not between 2 and 36 (inclusive).
+  pub fn to_str_radix(&self, radix: u32) -> String {
+    assert!((2..=36).contains(&radix), "radix {radix} is out of range");
 
-  fn add(mut self, other: Self) -> Self::Output {
-    self += other;
     self
+      .to_digits(radix)
+      .into_iter()
+      .map(|digit| {
+        char::from_digit(u32::from(digit), radix)
+          .expect("digit should be less than the radix")
+      })
+      .collect()
   }
-}
 
-impl AddAssign for Natural {
-  #[inline]
-  fn add_assign(&mut self, mut other: Self) {
-    match (&mut self.0, &mut other.0) {
-      (Repr::Small(x), Repr::Small(y)) => {
-        let (sum, overflow) = x.overflowing_add(*y);
-        if overflow {
-          *self = Natural(Repr::Large(vec![sum, 1]));
-        } else {
-          *x = sum;
-        }
-      },
-      (Repr::Small(_), Repr::Large(_)) => {
-        // We have ownership of _both_ `self` and `rhs`, so this reduces to the
-        // case of adding a large natural to a small one after we swap the two.
-        std::mem::swap(self, &mut other);
-        *self += other;
-      },
-      (Repr::Large(x), Repr::Small(y)) => {
-        let (sum, mut carry) = x[0].overflowing_add(*y);
-        x[0] = sum;
+  /// The bytes of this natural number in big-endian order, that is, from
+  /// most-significant to least-significant, without any leading zero bytes.
+  ///
+  /// Zero has no bytes, so this returns an empty vector for it.
+  pub fn to_be_bytes(&self) -> Vec<u8> {
+    let bytes: Vec<u8> =
+      self.limbs().iter().rev().flat_map(|limb| limb.to_be_bytes()).collect();
+    let leading_zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    bytes[leading_zeros..].to_vec()
+  }
 
-        for limb in x.iter_mut().skip(1) {
-          if !carry {
-            break;
-          }
+  /// Construct a natural number from its bytes in big-endian order, that is,
+  /// from most-significant to least-significant.
+  ///
+  /// Leading zero bytes are allowed, and an empty slice represents zero.
+  pub fn from_be_bytes(bytes: &[u8]) -> Natural {
+    const LIMB_BYTES: usize = (Limb::BITS / 8) as usize;
 
-          let (sum, overflow) = limb.overflowing_add(1);
-          *limb = sum;
-          carry = overflow;
-        }
+    // Each limb holds the bytes of one chunk, taken from the least-significant
+    // end, where only the most-significant chunk may be short.
+    let limbs = bytes
+      .rchunks(LIMB_BYTES)
+      .map(|chunk| {
+        let mut limb = [0; LIMB_BYTES];
+        limb[LIMB_BYTES - chunk.len()..].copy_from_slice(chunk);
+        Limb::from_be_bytes(limb)
+      })
+      .collect();
 
-        if carry {
-          x.push(1);
-        }
-      },
-      (Repr::Large(x), Repr::Large(y)) => {
-        // We have ownership of _both_ `x` and `y`, so we avoid unnecessarily
-        // allocating extra space by ensuring `x` becomes the larger of the two.
-        if x.len() < y.len() {
-          std::mem::swap(x, y);
-        }
+    Natural::from_vec(limbs)
+  }
 
-        let mut carry = false;
+  /// The bytes of this natural number in little-endian order, that is, from
+  /// least-significant to most-significant, without any trailing zero bytes.
+  ///
+  /// Zero has no bytes, so this returns an empty vector for it.
+  pub fn to_bytes_le(&self) -> Vec<u8> {
+    let mut bytes: Vec<u8> =
+      self.limbs().iter().flat_map(|limb| limb.to_le_bytes()).collect();
+    while bytes.last() == Some(&0) {
+      bytes.pop();
+    }
+    bytes
+  }
 
-        for (x_limb, y_limb) in x.iter_mut().zip(&mut *y) {
-          let (sum, overflow) = x_limb.carrying_add(*y_limb, carry);
-          *x_limb = sum;
-          carry = overflow;
-        }
+  /// Construct a natural number from its bytes in little-endian order, that
+  /// is, from least-significant to most-significant.
+  ///
+  /// Trailing zero bytes are allowed, and an empty slice represents zero.
+  pub fn from_bytes_le(bytes: &[u8]) -> Natural {
+    const LIMB_BYTES: usize = (Limb::BITS / 8) as usize;
 
-        // Propagate the carry through the rest of the limbs in `x` if
-        // necessary.
-        for limb in x.iter_mut().skip(y.len()) {
-          if !carry {
-            break;
-          }
+    // Only the most-significant chunk may be short, in which case its missing
+    // high bytes are zero.
+    let limbs = bytes
+      .chunks(LIMB_BYTES)
+      .map(|chunk| {
+        let mut limb = [0; LIMB_BYTES];
+        limb[..chunk.len()].copy_from_slice(chunk);
+        Limb::from_le_bytes(limb)
+      })
+      .collect();
 
-          let (sum, overflow) = limb.overflowing_add(1);
-          *limb = sum;
-          carry = overflow;
-        }
+    Natural::from_vec(limbs)
+  }
 
-        if carry {
-          x.push(1);
-        }
-      },
+  /// The number of digits needed to write this natural number in the given
+  /// radix.
+  ///
+  /// This is the length of [`Natural::to_digits`], but is computed from the
+  /// bit length of the number rather than by converting it.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the radix is not between 2 and 256 (inclusive).
+  pub fn digit_count(&self, radix: u32) -> u64 {
+    assert!((2..=256).contains(&radix), "radix {radix} is out of range");
+
+    let bits = self.bit_length();
+    if bits == 0 {
+      return 1;
     }
+
+    if radix.is_power_of_two() {
+      return bits.div_ceil(u64::from(radix.trailing_zeros()));
+    }
+
+    // A number with b bits lies in [2^(b-1), 2^b), so it has between
+    // floor((b-1)/log₂(r)) + 1 and floor(b/log₂(r)) + 1 digits. These bounds
+    // are widened slightly to account for rounding, and then the estimate
+    // is corrected by comparing against the smallest number with that many
+    // digits.
+    let log2_radix = f64::from(radix).log2();
+    let lower = ((bits - 1) as f64 / log2_radix - 1e-9).max(0.0) as u64 + 1;
+    let upper = (bits as f64 / log2_radix + 1e-9) as u64 + 1;
+
+    let mut digits = upper;
+    while digits > lower && *self < Natural::radix_pow(radix, digits - 1) {
+      digits -= 1;
+    }
+
+    digits
   }
-}
 
-impl Mul<Natural> for Natural {
-  type Output = Self;
+  /// Compute `radix^exponent`.
+  fn radix_pow(radix: u32, exponent: u64) -> Natural {
+    // Multiply by the largest power of the radix that fits in a limb as many
+    // times as possible to save on passes over the limbs.
+    let radix = Limb::from(radix);
+    let chunk_len = Limb::MAX.ilog(radix);
+    let chunk = radix.pow(chunk_len);
 
-  fn mul(mut self, other: Natural) -> Self::Output {
-    self *= other;
-    self
+    // The result has about exponent * log₂(radix) bits, so reserve enough
+    // limbs up front to avoid reallocating as it grows.
+    let bits = exponent as f64 * (radix as f64).log2();
+    let mut limbs = Vec::with_capacity(bits as usize / Limb::BITS as usize + 2);
+    limbs.push(1);
+
+    for _ in 0..exponent / u64::from(chunk_len) {
+      mul_add_limb(&mut limbs, chunk, 0);
+    }
+    let remainder = (exponent % u64::from(chunk_len)) as u32;
+    if remainder != 0 {
+      mul_add_limb(&mut limbs, radix.pow(remainder), 0);
+    }
+
+    Natural::from_vec(limbs)
   }
-}
 
-impl MulAssign<Natural> for Natural {
-  fn mul_assign(&mut self, mut other: Natural) {
-    match (&mut self.0, &mut other.0) {
-      (Repr::Small(0), _) => {},
-      (_, Repr::Small(0)) => *self = Natural::ZERO,
-      (Repr::Small(1), _) => *self = other,
-      (_, Repr::Small(1)) => {},
-      (Repr::Small(x), Repr::Small(y)) => {
-        let (product, overflow) = x.widening_mul(*y);
-        if overflow != 0 {
-          *self = Natural(Repr::Large(vec![product, overflow]));
-        } else {
-          *x = product;
-        }
-      },
-      (Repr::Small(_), Repr::Large(_)) => {
-        // We have ownership of _both_ `self` and `rhs`, so this reduces to the
-        // case of multiplying a large natural by a small one after we swap the
-        // two.
-        std::mem::swap(self, &mut other);
-        *self *= other;
-      },
-      (Repr::Large(_), Repr::Small(_)) => {
-        todo!("Implement multiplication of large natural by small natural")
-      },
-      (Repr::Large(_), Repr::Large(_)) => {
-        todo!("Implement multiplication of large natural by large natural")
-      },
+  /// Raise this natural number to the power `exp`.
+  ///
+  /// Following [`u64::pow`], `0^0 = 1`.
+  pub fn pow(self, mut exp: u64) -> Natural {
+    // Exponentiation by squaring: for each bit of the exponent, from the
+    // least-significant bit up, the base holds self^(2^i), which contributes to
+    // the result if the bit is set.
+    let mut base = self;
+    let mut result = Natural::ONE;
+    while exp > 0 {
+      if exp & 1 == 1 {
+        result *= base.clone();
+      }
+      exp >>= 1;
+      if exp > 0 {
+        base *= base.clone();
+      }
     }
+
+    result
   }
-}
 
-#[cfg(test)]
-mod tests {
-  use super::*;
+  /// Compute `self^exp mod modulus`.
+  ///
+  /// This reduces by the modulus after every step, so it is much faster than
+  /// computing the power in full when the exponent is large.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the modulus is zero.
+  pub fn modpow(&self, exp: &Natural, modulus: &Natural) -> Natural {
+    assert!(
+      !modulus.is_zero(),
+      "attempt to calculate the remainder with a divisor of zero"
+    );
 
-  const SMALL_MAX: Natural = Natural(Repr::Small(Limb::MAX));
+    // This is the same as exponentiation by squaring in `pow`, except that
+    // each product is reduced so that no intermediate value exceeds the square
+    // of the modulus.
+    let bits = exp.bit_length();
+    let mut base = self.clone() % modulus.clone();
+    let mut result = Natural::ONE % modulus.clone();
+    for i in 0..bits {
+      if exp.bit(i) {
+        result = result * base.clone() % modulus.clone();
+      }
+      if i + 1 < bits {
+        base = base.clone() * base % modulus.clone();
+      }
+    }
 
-  /// Assert that a list of equalities on [`Natural`] numbers hold.
-  macro_rules! assert_exprs {
-    ($($left:literal $op:tt $right:literal = $result:literal),* $(,)?) => {
-      $(
-        assert_eq!(
-          Natural::from($left) $op Natural::from($right),
-          Natural::from($result),
-        );
-      )*
-    };
+    result
   }
 
-  #[test]
-  fn test_add_small_small() {
-    assert_exprs! {
-      0 + 0 = 0,
-      0 + 1 = 1,
-      1 + 0 = 1,
-      1 + 1 = 2,
-      123 + 456 = 579
-    };
+  /// Compute `10^exp`.
+  ///
+  /// This is faster than repeatedly multiplying by ten, since it multiplies by
+  /// 10^19 (the largest power of ten that fits in a limb) wherever possible.
+  pub fn pow10(exp: u32) -> Natural {
+    Natural::radix_pow(10, u64::from(exp))
   }
 
-  #[test]
-  fn test_add_small_small_overflow() {
-    assert_eq!(SMALL_MAX + Natural::ONE, Natural::from_limbs(&[0, 1]));
-    assert_eq!(SMALL_MAX + SMALL_MAX, Natural::from_limbs(&[Limb::MAX - 1, 1]));
+  /// The integer square root of this natural number, that is, the largest
+  /// natural number whose square is at most this one.
+  pub fn isqrt(&self) -> Natural {
+    if self.is_zero() {
+      return Natural::ZERO;
+    }
+
+    // Newton's method, starting from a power of two that is at least the
+    // square root, decreases monotonically until it reaches the floor of the
+    // square root, after which the next estimate would be no smaller.
+    let mut estimate = Natural::ONE << self.bit_length().div_ceil(2);
+    loop {
+      let next = (estimate.clone() + self.clone() / estimate.clone()) >> 1;
+      if next >= estimate {
+        return estimate;
+      }
+      estimate = next;
+    }
   }
 
-  #[test]
-  fn test_add_small_large() {
-    let small = Natural::from(123);
-    let large = Natural::from_limbs(&[456, 1]);
-    assert_eq!(small + large, Natural::from_limbs(&[579, 1]));
+  /// The integer square root of this natural number, along with the remainder
+  /// left over after subtracting its square.
+  ///
+  /// The root `r` and remainder `rem` satisfy `r * r + rem == self` and
+  /// `rem <= 2 * r`, since otherwise `r + 1` would also fit.
+  pub fn sqrt_rem(&self) -> (Natural, Natural) {
+    let root = self.isqrt();
+    let remainder = self.abs_diff(&(root.clone() * root.clone()));
+    (root, remainder)
+  }
 
-    let small = Natural::from(42);
-    let large = Natural::from_limbs(&[100, 200, 300]);
-    assert_eq!(small + large, Natural::from_limbs(&[142, 200, 300]));
+  /// The number of bits needed to write this natural number in binary, which is
+  /// 0 for zero.
+  pub fn bit_length(&self) -> u64 {
+    let limbs = self.limbs();
+    let top = limbs.last().expect("a natural should have at least one limb");
+    (limbs.len() as u64 - 1) * u64::from(Limb::BITS)
+      + u64::from(Limb::BITS - top.leading_zeros())
   }
 
-  #[test]
-  fn test_add_small_large_overflow() {
-    let small = Natural::from(1);
-    let large = Natural::from_limbs(&[Limb::MAX, 0]);
-    assert_eq!(small + large, Natural::from_limbs(&[0, 1]));
+  /// Whether this natural number is zero.
+  pub fn is_zero(&self) -> bool {
+    match &self.0 {
+      Repr::Small(x) => *x == 0,
+      Repr::Large(limbs) => limbs.iter().all(|&limb| limb == 0),
+    }
+  }
 
-    let small = Natural::from(5);
-    let large = Natural::from_limbs(&[Limb::MAX - 3, Limb::MAX]);
-    assert_eq!(small + large, Natural::from_limbs(&[1, 0, 1]));
+  /// Whether this natural number is one.
+  pub fn is_one(&self) -> bool {
+    match &self.0 {
+      Repr::Small(x) => *x == 1,
+      Repr::Large(limbs) => match limbs.split_first() {
+        Some((&first, rest)) => {
+          first == 1 && rest.iter().all(|&limb| limb == 0)
+        },
+        None => false,
+      },
+    }
   }
 
-  #[test]
-  fn test_add_large_small() {
-    let large = Natural::from_limbs(&[100, 200]);
-    let small = Natural::from(50);
-    assert_eq!(large + small, Natural::from_limbs(&[150, 200]));
+  /// Whether this natural number is even, which includes zero.
+  pub fn is_even(&self) -> bool {
+    self.limbs().first().is_none_or(|limb| limb.is_multiple_of(2))
+  }
 
-    let large = Natural::from_limbs(&[1000, 2000, 3000]);
-    let small = Natural::from(234);
-    assert_eq!(large + small, Natural::from_limbs(&[1234, 2000, 3000]));
+  /// Check whether this natural number is a multiple of `divisor`.
+  ///
+  /// Following [`u64::is_multiple_of`], only zero is a multiple of zero, so
+  /// this is true for a zero divisor exactly when this number is also zero.
+  pub fn is_divisible_by(&self, divisor: &Natural) -> bool {
+    match (&self.0, &divisor.0) {
+      (_, Repr::Small(0)) => self.is_zero(),
+      (_, Repr::Small(1)) => true,
+      (_, Repr::Small(2)) => self.is_even(),
+      (Repr::Small(x), Repr::Small(y)) => x.is_multiple_of(*y),
+      _ if divisor.is_power_of_two() => match self.trailing_zeros() {
+        Some(zeros) => Some(zeros) >= divisor.trailing_zeros(),
+        None => true,
+      },
+      (_, Repr::Small(y)) => self.rem_limb(*y) == 0,
+      _ if self < divisor => self.is_zero(),
+      _ => trim(&div_rem_knuth(self.limbs(), divisor.limbs()).1).is_empty(),
+    }
   }
 
-  #[test]
-  fn test_add_large_small_overflow() {
-    let large = Natural::from_limbs(&[Limb::MAX, 10]);
-    let small = Natural::from(1);
-    assert_eq!(large + small, Natural::from_limbs(&[0, 11]));
+  /// The largest power of two that is at most this natural number, which
+  /// isolates its most-significant set bit, or `None` if it is zero.
+  pub fn highest_bit(&self) -> Option<Natural> {
+    let bits = self.bit_length();
+    (bits > 0).then(|| Natural::ONE << (bits - 1))
+  }
 
-    let large = Natural::from_limbs(&[Limb::MAX, Limb::MAX]);
-    let small = Natural::from(1);
-    assert_eq!(large + small, Natural::from_limbs(&[0, 0, 1]));
+  /// The number of ones in the binary representation of this natural number.
+  pub fn count_ones(&self) -> u64 {
+    self.limbs().iter().map(|limb| u64::from(limb.count_ones())).sum()
   }
 
-  #[test]
-  fn test_add_large_large() {
-    let a = Natural::from_limbs(&[123, 456]);
-    let b = Natural::from_limbs(&[789, 123]);
-    assert_eq!(a + b, Natural::from_limbs(&[912, 579]));
+  /// Whether this natural number is a power of two.
+  fn is_power_of_two(&self) -> bool {
+    self.count_ones() == 1
+  }
 
-    let a = Natural::from_limbs(&[100, 200, 300]);
-    let b = Natural::from_limbs(&[400, 500]);
-    assert_eq!(a + b, Natural::from_limbs(&[500, 700, 300]));
+  /// The number of trailing zero bits in this natural number, or `None` if
+  /// it is zero.
+  fn trailing_zeros(&self) -> Option<u64> {
+    let limbs = self.limbs();
+    let zero_limbs = limbs.iter().position(|&limb| limb != 0)?;
+    Some(
+      zero_limbs as u64 * u64::from(Limb::BITS)
+        + u64::from(limbs[zero_limbs].trailing_zeros()),
+    )
   }
 
-  #[test]
-  fn test_add_large_large_overflow() {
-    let a = Natural::from_limbs(&[Limb::MAX, 10]);
-    let b = Natural::from_limbs(&[1, 5]);
-    assert_eq!(a + b, Natural::from_limbs(&[0, 16]));
+  /// Whether the bit at the given index, counting from the least-significant
+  /// bit, is set.
+  pub fn bit(&self, index: u64) -> bool {
+    let (limb, bit) = bit_position(index);
+    self.limbs().get(limb).is_some_and(|limb| (limb >> bit) & 1 == 1)
+  }
 
-    let a = Natural::from_limbs(&[Limb::MAX, Limb::MAX]);
-    let b = Natural::from_limbs(&[1, 0]);
-    assert_eq!(a + b, Natural::from_limbs(&[0, 0, 1]));
+  /// Set or clear the bit at the given index, counting from the
+  /// least-significant bit.
+  ///
+  /// Setting a bit beyond the most-significant limb grows the number, and
+  /// clearing the top bit shrinks it back down to canonical form.
+  pub fn set_bit(&mut self, index: u64, value: bool) {
+    let (limb, bit) = bit_position(index);
+    let mask: Limb = 1 << bit;
 
-    let a = Natural::from_limbs(&[Limb::MAX, Limb::MAX, Limb::MAX]);
-    let b = Natural::from_limbs(&[1, 0, 0]);
-    assert_eq!(a + b, Natural::from_limbs(&[0, 0, 0, 1]));
+    if let Repr::Small(x) = &mut self.0
+      && limb == 0
+    {
+      if value {
+        *x |= mask;
+      } else {
+        *x &= !mask;
+      }
+      return;
+    }
+
+    // Bits beyond the top limb are already clear.
+    if !value && limb >= self.limbs().len() {
+      return;
+    }
+
+    if let Repr::Small(x) = self.0 {
+      self.0 = Repr::Large(vec![x]);
+    }
+    let Repr::Large(limbs) = &mut self.0 else {
+      unreachable!("a small natural should have been promoted");
+    };
+    if limbs.len() <= limb {
+      limbs.resize(limb + 1, 0);
+    }
+
+    if value {
+      limbs[limb] |= mask;
+    } else {
+      limbs[limb] &= !mask;
+    }
+    self.normalize();
   }
 
-  #[test]
-  fn test_mul_small_small() {
-    assert_exprs! {
-      0 * 0 = 0,
-      1 * 0 = 0,
-      0 * 1 = 0,
-      1 * 1 = 1,
+  /// Compute the remainder of dividing this natural number by a nonzero limb.
+  fn rem_limb(&self, divisor: Limb) -> Limb {
+    let divisor = u128::from(divisor);
+    let remainder = self.limbs().iter().rev().fold(0, |remainder, &limb| {
+      ((remainder << Limb::BITS) | u128::from(limb)) % divisor
+    });
+    remainder as Limb
+  }
+
+  /// Divide this natural number by another, returning both the quotient and
+  /// the remainder.
+  ///
+  /// This is cheaper than using both `/` and `%`, which would each perform the
+  /// same division.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the divisor is zero.
+  pub fn div_rem(self, divisor: Natural) -> (Natural, Natural) {
+    match (self.0, divisor.0) {
+      (_, Repr::Small(0)) => panic!("attempt to divide by zero"),
+      (Repr::Small(x), Repr::Small(y)) => {
+        (Natural::from(x / y), Natural::from(x % y))
+      },
+      // A large natural is always greater than a small one.
+      (Repr::Small(x), Repr::Large(_)) => (Natural::ZERO, Natural::from(x)),
+      (Repr::Large(mut x), Repr::Small(y)) => {
+        let remainder = div_rem_limb(&mut x, y);
+        (Natural::from_vec(x), Natural::from(remainder))
+      },
+      (Repr::Large(x), Repr::Large(y)) if x.len() < y.len() => {
+        (Natural::ZERO, Natural(Repr::Large(x)))
+      },
+      (Repr::Large(x), Repr::Large(y)) => {
+        let (quotient, remainder) = div_rem_knuth(&x, &y);
+        (Natural::from_vec(quotient), Natural::from_vec(remainder))
+      },
+    }
+  }
+
+  /// Restrict this natural number to the interval `[min, max]`.
+  ///
+  /// Returns `min` if `self` is less than `min`, `max` if `self` is greater
+  /// than `max`, and `self` otherwise.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `min > max`, as with [`Ord::clamp`].
+  pub fn clamp(self, min: Natural, max: Natural) -> Natural {
+    assert!(min <= max, "min must not exceed max");
+
+    if self < min {
+      min
+    } else if self > max {
+      max
+    } else {
+      self
+    }
+  }
+
+  /// The greatest common divisor of two natural numbers.
+  ///
+  /// Following convention, `gcd(0, n) = n`, so in particular `gcd(0, 0) = 0`.
+  pub fn gcd(self, other: Natural) -> Natural {
+    // Stein's algorithm, which only needs shifts and subtraction: the common
+    // factors of two contribute to the result directly, and the odd part of
+    // the result is unchanged by replacing the larger number with the
+    // difference of the two, which is even and can be halved.
+    let (Some(self_zeros), Some(other_zeros)) =
+      (self.trailing_zeros(), other.trailing_zeros())
+    else {
+      return if self.is_zero() { other } else { self };
+    };
+
+    let mut x = self >> self_zeros;
+    let mut y = other;
+    loop {
+      let zeros = y.trailing_zeros().expect("y should be nonzero");
+      y >>= zeros;
+      if x > y {
+        std::mem::swap(&mut x, &mut y);
+      }
+
+      y = y.abs_diff(&x);
+      if y.is_zero() {
+        return x << self_zeros.min(other_zeros);
+      }
+    }
+  }
+
+  /// The least common multiple of two natural numbers, which is zero if
+  /// either of them is zero.
+  pub fn lcm(self, other: Natural) -> Natural {
+    if self.is_zero() || other.is_zero() {
+      return Natural::ZERO;
+    }
+
+    // Dividing before multiplying keeps the intermediate value small.
+    let gcd = self.clone().gcd(other.clone());
+    self / gcd * other
+  }
+
+  /// Compute the absolute difference `|self - other|`.
+  ///
+  /// Unlike subtraction, this cannot underflow, since the smaller of the two
+  /// numbers is always subtracted from the larger.
+  pub fn abs_diff(&self, other: &Self) -> Natural {
+    if let (Repr::Small(x), Repr::Small(y)) = (&self.0, &other.0) {
+      return Natural(Repr::Small(x.abs_diff(*y)));
+    }
+
+    let (larger, smaller) = match self.cmp(other) {
+      Ordering::Less => (other.limbs(), self.limbs()),
+      _ => (self.limbs(), other.limbs()),
+    };
+
+    let mut diff = larger.to_vec();
+    let mut borrow = false;
+
+    for (x_limb, y_limb) in diff.iter_mut().zip(smaller) {
+      let (limb, overflow) = x_limb.borrowing_sub(*y_limb, borrow);
+      *x_limb = limb;
+      borrow = overflow;
+    }
+
+    // Propagate the borrow through the rest of the limbs if necessary.
+    for limb in diff.iter_mut().skip(smaller.len()) {
+      if !borrow {
+        break;
+      }
+
+      let (difference, overflow) = limb.overflowing_sub(1);
+      *limb = difference;
+      borrow = overflow;
+    }
+
+    debug_assert!(!borrow, "the larger number should be the minuend");
+    Natural::from_vec(diff)
+  }
+
+  /// Compute `self - other` for a single-limb `other`, returning `None` if the
+  /// result would be negative.
+  ///
+  /// This avoids constructing a natural number for `other`, and the borrow only
+  /// propagates through as many limbs as it needs to, but the limbs of `self`
+  /// are still copied into the result.
+  pub fn checked_sub_small(&self, other: u64) -> Option<Natural> {
+    let limbs = match &self.0 {
+      Repr::Small(x) => return x.checked_sub(other).map(Natural::from),
+      Repr::Large(limbs) => limbs,
+    };
+
+    // A large natural is at least 2^64, so this can never underflow.
+    let mut diff = limbs.clone();
+    let mut borrow;
+    (diff[0], borrow) = diff[0].overflowing_sub(other);
+    for limb in diff.iter_mut().skip(1) {
+      if !borrow {
+        break;
+      }
+      (*limb, borrow) = limb.overflowing_sub(1);
+    }
+
+    debug_assert!(!borrow, "a large natural should exceed any single limb");
+    Some(Natural::from_vec(diff))
+  }
+
+  /// Iterate over the natural numbers from `start` up to but excluding `end`.
+  ///
+  /// This stands in for the range `start..end`, which cannot be iterated over
+  /// since the [`std::iter::Step`] trait is unstable.
+  pub fn range(start: Natural, end: Natural) -> Range {
+    Range { next: start, end }
+  }
+
+  /// The limbs of this natural number in little-endian order.
+  fn limbs(&self) -> &[Limb] {
+    match &self.0 {
+      Repr::Small(limb) => std::slice::from_ref(limb),
+      Repr::Large(limbs) => limbs,
+    }
+  }
+
+  /// Construct a natural number in canonical form from its limbs in
+  /// little-endian order, which may include trailing zero limbs.
+  fn from_vec(limbs: Vec<Limb>) -> Self {
+    let mut n = Natural(Repr::Large(limbs));
+    n.normalize();
+    n
+  }
+
+  /// Restore canonical form after an operation that may have shrunk a large
+  /// natural, by removing its trailing zero limbs and demoting it to a small
+  /// natural if at most one limb remains.
+  ///
+  /// Every operation that can shrink a large natural, such as division or
+  /// clearing a bit, must finish by calling this, directly or through
+  /// [`Natural::from_vec`].
+  fn normalize(&mut self) {
+    let Repr::Large(limbs) = &mut self.0 else {
+      return;
+    };
+
+    while limbs.last() == Some(&0) {
+      limbs.pop();
+    }
+
+    match limbs[..] {
+      [] => self.0 = Repr::Small(0),
+      [limb] => self.0 = Repr::Small(limb),
+      _ => {},
+    }
+  }
+
+  /// Check whether this natural number is in canonical form.
+  ///
+  /// A natural number is in canonical form if it is stored as a small natural
+  /// whenever it fits within a single limb and, if it is stored as a large
+  /// natural, its most-significant limb is nonzero. Every arithmetic operation
+  /// should produce a result in canonical form when given operands in canonical
+  /// form.
+  pub(crate) fn is_canonical(&self) -> bool {
+    match &self.0 {
+      Repr::Small(_) => true,
+      Repr::Large(limbs) => limbs.len() >= 2 && limbs.last() != Some(&0),
+    }
+  }
+
+  /// Render the internal representation of this natural number as a hex dump.
+  ///
+  /// The first line states whether the number is stored as a small or a large
+  /// natural, and each following line holds one limb, from least to most
+  /// significant, prefixed by its index. This is intended for debugging, such
+  /// as when inspecting why two numbers with the same value compare unequal.
+  pub fn hex_dump(&self) -> String {
+    let limbs = self.limbs();
+    let mut dump = match self.0 {
+      Repr::Small(_) => String::from("small\n"),
+      Repr::Large(_) => format!("large ({} limbs)\n", limbs.len()),
+    };
+
+    let width = limbs.len().saturating_sub(1).to_string().len();
+    for (i, limb) in limbs.iter().enumerate() {
+      dump.push_str(&format!("[{i:>width$}] {limb:#018x}\n"));
+    }
+
+    dump
+  }
+
+  /// Combine the limbs of `other` into those of `self` with a bitwise
+  /// operation, where limbs missing from the shorter operand are treated as
+  /// zero.
+  ///
+  /// Since the operation is applied in place to whichever operand has more
+  /// limbs, it must be commutative and leave a limb unchanged when combined
+  /// with zero.
+  fn bitwise_assign(&mut self, mut other: Natural, op: fn(Limb, Limb) -> Limb) {
+    if self.limbs().len() < other.limbs().len() {
+      std::mem::swap(self, &mut other);
+    }
+
+    match &mut self.0 {
+      Repr::Small(x) => *x = op(*x, other.limbs()[0]),
+      Repr::Large(x) => {
+        for (x, &y) in x.iter_mut().zip(other.limbs()) {
+          *x = op(*x, y);
+        }
+      },
+    }
+
+    self.normalize();
+  }
+
+  #[cfg(test)]
+  fn from_limbs(limbs: &[Limb]) -> Self {
+    if limbs.is_empty() {
+      Self(Repr::Small(0))
+    } else if limbs.len() == 1 {
+      Self(Repr::Small(limbs[0]))
+    } else {
+      Self(Repr::Large(limbs.to_vec()))
+    }
+  }
+}
+
+/// Reduce the fraction `num / den` to lowest terms by dividing both parts by
+/// their greatest common divisor.
+///
+/// A zero denominator does not describe a fraction, so in that case the parts
+/// are returned unchanged rather than reduced to `1 / 0`. Otherwise, a zero
+/// numerator reduces to `0 / 1`.
+pub fn simplify_fraction(num: &Natural, den: &Natural) -> (Natural, Natural) {
+  if den.is_zero() {
+    return (num.clone(), den.clone());
+  }
+
+  let gcd = num.clone().gcd(den.clone());
+  (num.clone() / gcd.clone(), den.clone() / gcd)
+}
+
+/// Multiply a sequence of little-endian limbs by `factor` and add `addend` in
+/// place, growing the sequence if the result carries out of the top limb.
+fn mul_add_limb(limbs: &mut Vec<Limb>, factor: Limb, addend: Limb) {
+  let mut carry = addend;
+  for limb in limbs.iter_mut() {
+    (*limb, carry) = limb.carrying_mul(factor, carry);
+  }
+  if carry != 0 {
+    limbs.push(carry);
+  }
+}
+
+/// The index of the limb containing the bit at the given index, and the index
+/// of the bit within that limb.
+fn bit_position(index: u64) -> (usize, u32) {
+  let bits = u64::from(Limb::BITS);
+  ((index / bits) as usize, (index % bits) as u32)
+}
+
+/// Divide a sequence of little-endian limbs by a nonzero `divisor` in place,
+/// removing any trailing zero limbs from the quotient, and return the
+/// remainder.
+fn div_rem_limb(limbs: &mut Vec<Limb>, divisor: Limb) -> Limb {
+  let divisor = u128::from(divisor);
+  let mut remainder = 0;
+  for limb in limbs.iter_mut().rev() {
+    let wide = (remainder << Limb::BITS) | u128::from(*limb);
+    *limb = (wide / divisor) as Limb;
+    remainder = wide % divisor;
+  }
+
+  while limbs.last() == Some(&0) {
+    limbs.pop();
+  }
+
+  remainder as Limb
+}
+
+/// Divide one sequence of little-endian limbs by another using Knuth's
+/// Algorithm D (from _The Art of Computer Programming_, volume 2, section
+/// 4.3.1), returning the quotient and remainder.
+///
+/// The divisor must have at least two limbs with a nonzero top limb, and the
+/// dividend must have at least as many limbs as the divisor. The quotient has
+/// `u.len() - v.len() + 1` limbs and the remainder has `v.len()` limbs, either
+/// of which may have trailing zero limbs.
+fn div_rem_knuth(u: &[Limb], v: &[Limb]) -> (Vec<Limb>, Vec<Limb>) {
+  let n = v.len();
+  let m = u.len() - n;
+  debug_assert!(n >= 2 && v[n - 1] != 0, "divisor should be large");
+
+  // Normalise the operands by shifting them left until the top bit of the
+  // divisor is set, which guarantees that each estimate of a quotient limb
+  // below is at most two more than the true limb.
+  let shift = v[n - 1].leading_zeros();
+  let v = shl_limbs(v, shift);
+  let mut u = shl_limbs(u, shift);
+  u.resize(m + n + 1, 0);
+
+  let base = 1u128 << Limb::BITS;
+  let (v_top, v_next) = (u128::from(v[n - 1]), u128::from(v[n - 2]));
+  let mut quotient = vec![0; m + 1];
+
+  for j in (0..=m).rev() {
+    // Estimate the quotient limb from the top two limbs of the current
+    // remainder and the top limb of the divisor, then refine the estimate with
+    // the next limb of the divisor.
+    let top = (u128::from(u[j + n]) << Limb::BITS) | u128::from(u[j + n - 1]);
+    let (mut q_hat, mut r_hat) = (top / v_top, top % v_top);
+    while q_hat >= base
+      || q_hat * v_next > ((r_hat << Limb::BITS) | u128::from(u[j + n - 2]))
+    {
+      q_hat -= 1;
+      r_hat += v_top;
+      if r_hat >= base {
+        break;
+      }
+    }
+    let mut q_hat = q_hat as Limb;
+
+    // Subtract q_hat * v from the current remainder.
+    let (mut carry, mut borrow) = (0, false);
+    for i in 0..n {
+      let (product, high) = q_hat.carrying_mul(v[i], carry);
+      carry = high;
+      (u[i + j], borrow) = u[i + j].borrowing_sub(product, borrow);
+    }
+    (u[j + n], borrow) = u[j + n].borrowing_sub(carry, borrow);
+
+    // In rare cases the estimate is still one too large, so the subtraction
+    // goes negative and we must add the divisor back.
+    if borrow {
+      q_hat -= 1;
+      let mut carry = false;
+      for i in 0..n {
+        (u[i + j], carry) = u[i + j].carrying_add(v[i], carry);
+      }
+      u[j + n] = u[j + n].wrapping_add(Limb::from(carry));
+    }
+
+    quotient[j] = q_hat;
+  }
+
+  // Undo the normalisation to recover the remainder.
+  u.truncate(n);
+  (quotient, shr_limbs(&u, shift))
+}
+
+/// Shift a sequence of little-endian limbs left by fewer than `Limb::BITS`
+/// bits, growing it by a limb if bits are shifted out of the top limb.
+fn shl_limbs(limbs: &[Limb], shift: u32) -> Vec<Limb> {
+  if shift == 0 {
+    return limbs.to_vec();
+  }
+
+  let mut shifted = Vec::with_capacity(limbs.len() + 1);
+  let mut carry = 0;
+  for &limb in limbs {
+    shifted.push((limb << shift) | carry);
+    carry = limb >> (Limb::BITS - shift);
+  }
+  if carry != 0 {
+    shifted.push(carry);
+  }
+  shifted
+}
+
+/// Shift a sequence of little-endian limbs right by fewer than `Limb::BITS`
+/// bits, discarding the bits shifted out of the bottom limb.
+fn shr_limbs(limbs: &[Limb], shift: u32) -> Vec<Limb> {
+  if shift == 0 {
+    return limbs.to_vec();
+  }
+
+  let mut shifted = vec![0; limbs.len()];
+  for (i, &limb) in limbs.iter().enumerate() {
+    shifted[i] = limb >> shift;
+    if let Some(next) = limbs.get(i + 1) {
+      shifted[i] |= next << (Limb::BITS - shift);
+    }
+  }
+  shifted
+}
+
+/// Multiply two sequences of little-endian limbs using long multiplication.
+///
+/// The product has `x.len() + y.len()` limbs, the top one of which may be zero.
+fn mul_schoolbook(x: &[Limb], y: &[Limb]) -> Vec<Limb> {
+  let mut product = vec![0; x.len() + y.len()];
+
+  for (i, &x_limb) in x.iter().enumerate() {
+    // Add x_limb * y to the product, shifted along by i limbs.
+    let mut carry = 0;
+    for (j, &y_limb) in y.iter().enumerate() {
+      (product[i + j], carry) =
+        x_limb.carrying_mul_add(y_limb, carry, product[i + j]);
+    }
+    product[i + y.len()] = carry;
+  }
+
+  product
+}
+
+/// The number of limbs below which [`mul_karatsuba`] falls back to long
+/// multiplication, since the bookkeeping of splitting the operands costs more
+/// than it saves for short operands.
+const KARATSUBA_THRESHOLD: usize = 32;
+
+/// Multiply two sequences of little-endian limbs using Karatsuba's algorithm.
+///
+/// Each operand is split into a low half and a high half at half the length of
+/// the longer operand, so that `x = x1 * B^m + x0` and `y = y1 * B^m + y0`,
+/// where `B` is the limb base. The product is then
+///
+/// ```text
+/// x * y = z2 * B^2m + z1 * B^m + z0
+/// ```
+///
+/// where `z0 = x0 * y0`, `z2 = x1 * y1`, and `z1 = (x0 + x1)(y0 + y1) - z0 -
+/// z2`, which takes three recursive multiplications instead of four.
+///
+/// As with [`mul_schoolbook`], the product has `x.len() + y.len()` limbs, the
+/// top one of which may be zero.
+fn mul_karatsuba(x: &[Limb], y: &[Limb]) -> Vec<Limb> {
+  if x.len().min(y.len()) < KARATSUBA_THRESHOLD {
+    return mul_schoolbook(x, y);
+  }
+
+  let m = x.len().max(y.len()) / 2;
+  // The shorter operand may not reach the split point at all, in which case its
+  // high half is empty.
+  let (x0, x1) = x.split_at(m.min(x.len()));
+  let (y0, y1) = y.split_at(m.min(y.len()));
+
+  let z0 = mul_karatsuba(x0, y0);
+  let z2 = mul_karatsuba(x1, y1);
+
+  // Since (x0 + x1)(y0 + y1) = z0 + z2 + x0 * y1 + x1 * y0, neither
+  // subtraction can underflow.
+  let mut z1 = mul_karatsuba(&add_slices(x0, x1), &add_slices(y0, y1));
+  sub_assign_slice(&mut z1, &z0);
+  sub_assign_slice(&mut z1, &z2);
+
+  let mut product = vec![0; x.len() + y.len()];
+  product[..z0.len()].copy_from_slice(&z0);
+  add_assign_slice(&mut product[2 * m..], &z2);
+  add_assign_slice(&mut product[m..], trim(&z1));
+
+  product
+}
+
+/// Add two sequences of little-endian limbs, producing a sum with one more limb
+/// than the longer of the two.
+fn add_slices(x: &[Limb], y: &[Limb]) -> Vec<Limb> {
+  let (longer, shorter) = if x.len() >= y.len() { (x, y) } else { (y, x) };
+  let mut sum = Vec::with_capacity(longer.len() + 1);
+  sum.extend_from_slice(longer);
+  sum.push(0);
+  add_assign_slice(&mut sum, shorter);
+  sum
+}
+
+/// Add `y` to `x` in place, where `x` must have enough limbs to hold the sum.
+fn add_assign_slice(x: &mut [Limb], y: &[Limb]) {
+  let mut carry = false;
+  for (x_limb, &y_limb) in x.iter_mut().zip(y) {
+    (*x_limb, carry) = x_limb.carrying_add(y_limb, carry);
+  }
+
+  for limb in x.iter_mut().skip(y.len()) {
+    if !carry {
+      break;
+    }
+    (*limb, carry) = limb.overflowing_add(1);
+  }
+
+  debug_assert!(!carry, "sum should fit within the limbs of x");
+}
+
+/// Subtract `y` from `x` in place, where `x` must be at least `y`.
+fn sub_assign_slice(x: &mut [Limb], y: &[Limb]) {
+  let mut borrow = false;
+  for (x_limb, &y_limb) in x.iter_mut().zip(trim(y)) {
+    (*x_limb, borrow) = x_limb.borrowing_sub(y_limb, borrow);
+  }
+
+  for limb in x.iter_mut().skip(trim(y).len()) {
+    if !borrow {
+      break;
+    }
+    (*limb, borrow) = limb.overflowing_sub(1);
+  }
+
+  debug_assert!(!borrow, "difference should not be negative");
+}
+
+/// Strip the trailing zero limbs from a sequence of little-endian limbs.
+fn trim(limbs: &[Limb]) -> &[Limb] {
+  let len = limbs.iter().rposition(|&limb| limb != 0).map_or(0, |i| i + 1);
+  &limbs[..len]
+}
+
+/// The raw multiplication algorithms, exposed only so that the benchmarks can
+/// compare them directly.
+#[doc(hidden)]
+pub mod algorithms {
+  /// Multiply two sequences of little-endian limbs using long multiplication.
+  pub fn mul_schoolbook(x: &[u64], y: &[u64]) -> Vec<u64> {
+    super::mul_schoolbook(x, y)
+  }
+
+  /// Multiply two sequences of little-endian limbs using Karatsuba's
+  /// algorithm.
+  pub fn mul_karatsuba(x: &[u64], y: &[u64]) -> Vec<u64> {
+    super::mul_karatsuba(x, y)
+  }
+}
+
+/// An iterator over the natural numbers in a half-open range.
+///
+/// This is created by [`Natural::range`].
+#[derive(Debug, Clone)]
+pub struct Range {
+  next: Natural,
+  end: Natural,
+}
+
+impl Iterator for Range {
+  type Item = Natural;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.next >= self.end {
+      return None;
+    }
+
+    let next = self.next.clone();
+    self.next += 1;
+    Some(next)
+  }
+}
+
+impl Default for Natural {
+  fn default() -> Self {
+    Natural::ZERO
+  }
+}
+
+impl From<Limb> for Natural {
+  fn from(value: Limb) -> Self {
+    Self(Repr::Small(value))
+  }
+}
+
+impl From<u128> for Natural {
+  fn from(value: u128) -> Self {
+    let (low, high) = (value as Limb, (value >> Limb::BITS) as Limb);
+    if high == 0 {
+      Self(Repr::Small(low))
+    } else {
+      Self(Repr::Large(vec![low, high]))
+    }
+  }
+}
+
+impl From<u32> for Natural {
+  fn from(value: u32) -> Self {
+    Self::from(Limb::from(value))
+  }
+}
+
+impl From<u16> for Natural {
+  fn from(value: u16) -> Self {
+    Self::from(Limb::from(value))
+  }
+}
+
+impl From<u8> for Natural {
+  fn from(value: u8) -> Self {
+    Self::from(Limb::from(value))
+  }
+}
+
+impl From<usize> for Natural {
+  fn from(value: usize) -> Self {
+    // A usize is at most 64 bits wide on every supported target.
+    Self::from(value as Limb)
+  }
+}
+
+impl TryFrom<&Natural> for u128 {
+  type Error = TryFromNaturalError;
+
+  fn try_from(value: &Natural) -> Result<Self, Self::Error> {
+    match *value.limbs() {
+      [low] => Ok(u128::from(low)),
+      [low, high] => Ok((u128::from(high) << Limb::BITS) | u128::from(low)),
+      _ => Err(TryFromNaturalError { target: "u128" }),
+    }
+  }
+}
+
+impl TryFrom<Natural> for u128 {
+  type Error = TryFromNaturalError;
+
+  fn try_from(value: Natural) -> Result<Self, Self::Error> {
+    u128::try_from(&value)
+  }
+}
+
+/// Implement `TryFrom<Natural>` and `TryFrom<&Natural>` for primitive integer
+/// types narrower than `u128` by way of the conversion to `u128`.
+macro_rules! impl_try_from_natural {
+  ($($target:ty),*) => {
+    $(
+      impl TryFrom<&Natural> for $target {
+        type Error = TryFromNaturalError;
+
+        fn try_from(value: &Natural) -> Result<Self, Self::Error> {
+          u128::try_from(value)
+            .ok()
+            .and_then(|value| <$target>::try_from(value).ok())
+            .ok_or(TryFromNaturalError { target: stringify!($target) })
+        }
+      }
+
+      impl TryFrom<Natural> for $target {
+        type Error = TryFromNaturalError;
+
+        fn try_from(value: Natural) -> Result<Self, Self::Error> {
+          <$target>::try_from(&value)
+        }
+      }
+    )*
+  };
+}
+
+impl_try_from_natural!(u64, i64, usize);
+
+/// Parsing accepts a string of ASCII decimal digits, optionally preceded by a
+/// `+` sign, as in `+007`. Leading zeros are allowed.
+impl FromStr for Natural {
+  type Err = ParseNaturalError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Natural::from_str_radix(s, 10)
+  }
+}
+
+/// The largest power of ten that fits in a limb, and its exponent.
+const DECIMAL_CHUNK: (Limb, usize) = (10_000_000_000_000_000_000, 19);
+
+impl Display for Natural {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let mut limbs = match &self.0 {
+      Repr::Small(limb) => return Display::fmt(limb, f),
+      Repr::Large(limbs) => limbs.clone(),
+    };
+
+    // Split the number into chunks of 19 decimal digits, from least to most
+    // significant, by repeatedly dividing by 10^19.
+    let (chunk, chunk_len) = DECIMAL_CHUNK;
+    let mut chunks = Vec::with_capacity(limbs.len() * 20 / 19 + 1);
+    while !limbs.is_empty() {
+      chunks.push(div_rem_limb(&mut limbs, chunk));
+    }
+
+    // Every chunk but the most-significant one must be padded with zeros to
+    // the full chunk length.
+    let mut digits = String::with_capacity(chunks.len() * chunk_len);
+    let (first, rest) =
+      chunks.split_last().expect("a large natural is nonzero");
+    write!(digits, "{first}")?;
+    for chunk in rest.iter().rev() {
+      write!(digits, "{chunk:0chunk_len$}")?;
+    }
+
+    f.pad_integral(true, "", &digits)
+  }
+}
+
+/// Hashing uses only the significant limbs, so that the hash depends on the
+/// value alone and not on how it happens to be represented.
+impl Hash for Natural {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    trim(self.limbs()).hash(state);
+  }
+}
+
+impl PartialOrd for Natural {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Natural {
+  fn cmp(&self, other: &Self) -> Ordering {
+    debug_assert!(self.is_canonical() && other.is_canonical());
+
+    match (&self.0, &other.0) {
+      (Repr::Small(x), Repr::Small(y)) => x.cmp(y),
+      (Repr::Small(_), Repr::Large(_)) => Ordering::Less,
+      (Repr::Large(_), Repr::Small(_)) => Ordering::Greater,
+      (Repr::Large(x), Repr::Large(y)) => {
+        x.len().cmp(&y.len()).then_with(|| x.iter().rev().cmp(y.iter().rev()))
+      },
+    }
+  }
+}
+
+impl Add for Natural {
+  type Output = Self;
+
+  fn add(mut self, other: Self) -> Self::Output {
+    self += other;
+    self
+  }
+}
+
+/// Adding in place reuses the allocation of whichever operand has more limbs,
+/// so it never allocates unless the sum carries out into a new limb and the
+/// larger operand has no spare capacity for it.
+impl AddAssign for Natural {
+  #[inline]
+  fn add_assign(&mut self, mut other: Self) {
+    match (&mut self.0, &mut other.0) {
+      (Repr::Small(x), Repr::Small(y)) => {
+        let (sum, overflow) = x.overflowing_add(*y);
+        if overflow {
+          *self = Natural(Repr::Large(vec![sum, 1]));
+        } else {
+          *x = sum;
+        }
+      },
+      (Repr::Small(_), Repr::Large(_)) => {
+        // We have ownership of _both_ `self` and `rhs`, so this reduces to the
+        // case of adding a large natural to a small one after we swap the two.
+        // Swapping only moves the limbs of `other` into `self`, so the result
+        // takes over its allocation.
+        std::mem::swap(self, &mut other);
+        *self += other;
+      },
+      (Repr::Large(x), Repr::Small(y)) => {
+        let (sum, mut carry) = x[0].overflowing_add(*y);
+        x[0] = sum;
+
+        for limb in x.iter_mut().skip(1) {
+          if !carry {
+            break;
+          }
+
+          let (sum, overflow) = limb.overflowing_add(1);
+          *limb = sum;
+          carry = overflow;
+        }
+
+        if carry {
+          x.push(1);
+        }
+      },
+      (Repr::Large(x), Repr::Large(y)) => {
+        // We have ownership of _both_ `x` and `y`, so we avoid unnecessarily
+        // allocating extra space by ensuring `x` becomes the larger of the two.
+        if x.len() < y.len() {
+          std::mem::swap(x, y);
+        }
+
+        let mut carry = false;
+
+        for (x_limb, y_limb) in x.iter_mut().zip(&mut *y) {
+          let (sum, overflow) = x_limb.carrying_add(*y_limb, carry);
+          *x_limb = sum;
+          carry = overflow;
+        }
+
+        // Propagate the carry through the rest of the limbs in `x` if
+        // necessary.
+        for limb in x.iter_mut().skip(y.len()) {
+          if !carry {
+            break;
+          }
+
+          let (sum, overflow) = limb.overflowing_add(1);
+          *limb = sum;
+          carry = overflow;
+        }
+
+        if carry {
+          x.push(1);
+        }
+      },
+    }
+  }
+}
+
+/// Adding a limb takes the same path as adding a small natural, so it never
+/// allocates unless the sum carries out into a new limb.
+impl AddAssign<Limb> for Natural {
+  #[inline]
+  fn add_assign(&mut self, other: Limb) {
+    *self += Natural::from(other);
+  }
+}
+
+impl Mul<Natural> for Natural {
+  type Output = Self;
+
+  fn mul(mut self, other: Natural) -> Self::Output {
+    self *= other;
+    self
+  }
+}
+
+impl MulAssign<Natural> for Natural {
+  fn mul_assign(&mut self, mut other: Natural) {
+    match (&mut self.0, &mut other.0) {
+      (Repr::Small(0), _) => {},
+      (_, Repr::Small(0)) => *self = Natural::ZERO,
+      (Repr::Small(1), _) => *self = other,
+      (_, Repr::Small(1)) => {},
+      (Repr::Small(x), Repr::Small(y)) => {
+        let wide = x.widening_mul(*y);
+        let (product, overflow) = (wide as Limb, (wide >> Limb::BITS) as Limb);
+        if overflow != 0 {
+          *self = Natural(Repr::Large(vec![product, overflow]));
+        } else {
+          *x = product;
+        }
+      },
+      (Repr::Small(_), Repr::Large(_)) => {
+        // We have ownership of _both_ `self` and `rhs`, so this reduces to the
+        // case of multiplying a large natural by a small one after we swap the
+        // two.
+        std::mem::swap(self, &mut other);
+        *self *= other;
+      },
+      (Repr::Large(x), Repr::Small(y)) => {
+        // Since neither operand is zero, the top limb of the product is nonzero
+        // and hence the product is already in canonical form.
+        mul_add_limb(x, *y, 0);
+      },
+      (Repr::Large(x), Repr::Large(y)) => {
+        *self = Natural::from_vec(mul_karatsuba(x, y));
+      },
+    }
+  }
+}
+
+impl MulAssign<Limb> for Natural {
+  fn mul_assign(&mut self, other: Limb) {
+    *self *= Natural::from(other);
+  }
+}
+
+impl Div<Natural> for Natural {
+  type Output = Self;
+
+  fn div(self, other: Natural) -> Self::Output {
+    self.div_rem(other).0
+  }
+}
+
+impl DivAssign<Natural> for Natural {
+  fn div_assign(&mut self, other: Natural) {
+    *self = std::mem::take(self) / other;
+  }
+}
+
+impl Rem<Natural> for Natural {
+  type Output = Self;
+
+  fn rem(self, other: Natural) -> Self::Output {
+    assert!(
+      !other.is_zero(),
+      "attempt to calculate the remainder with a divisor of zero"
+    );
+    self.div_rem(other).1
+  }
+}
+
+impl RemAssign<Natural> for Natural {
+  fn rem_assign(&mut self, other: Natural) {
+    *self = std::mem::take(self) % other;
+  }
+}
+
+impl BitAnd for Natural {
+  type Output = Self;
+
+  fn bitand(mut self, other: Self) -> Self::Output {
+    self &= other;
+    self
+  }
+}
+
+impl BitAndAssign for Natural {
+  fn bitand_assign(&mut self, other: Self) {
+    match &mut self.0 {
+      Repr::Small(x) => *x &= other.limbs()[0],
+      Repr::Large(x) => {
+        // The limbs above the length of the shorter operand are all zero.
+        x.truncate(other.limbs().len());
+        for (x, &y) in x.iter_mut().zip(other.limbs()) {
+          *x &= y;
+        }
+        self.normalize();
+      },
+    }
+  }
+}
+
+impl BitOr for Natural {
+  type Output = Self;
+
+  fn bitor(mut self, other: Self) -> Self::Output {
+    self |= other;
+    self
+  }
+}
+
+impl BitOrAssign for Natural {
+  fn bitor_assign(&mut self, other: Self) {
+    self.bitwise_assign(other, |x, y| x | y);
+  }
+}
+
+impl BitXor for Natural {
+  type Output = Self;
+
+  fn bitxor(mut self, other: Self) -> Self::Output {
+    self ^= other;
+    self
+  }
+}
+
+impl BitXorAssign for Natural {
+  fn bitxor_assign(&mut self, other: Self) {
+    self.bitwise_assign(other, |x, y| x ^ y);
+  }
+}
+
+impl Shl<u64> for Natural {
+  type Output = Self;
+
+  fn shl(self, shift: u64) -> Self::Output {
+    if self.is_zero() {
+      return self;
+    }
+
+    // Shift by whole limbs by prepending zero limbs, and then shift the rest of
+    // the way across adjacent limbs.
+    let (limb_shift, bit_shift) = bit_position(shift);
+    let mut limbs = vec![0; limb_shift];
+    limbs.extend(shl_limbs(self.limbs(), bit_shift));
+    Natural::from_vec(limbs)
+  }
+}
+
+impl ShlAssign<u64> for Natural {
+  fn shl_assign(&mut self, shift: u64) {
+    *self = std::mem::take(self) << shift;
+  }
+}
+
+impl Shr<u64> for Natural {
+  type Output = Self;
+
+  fn shr(self, shift: u64) -> Self::Output {
+    // Shift by whole limbs by dropping the lowest limbs, and then shift the
+    // rest of the way across adjacent limbs.
+    let (limb_shift, bit_shift) = bit_position(shift);
+    match self.limbs().get(limb_shift..) {
+      Some(limbs) => Natural::from_vec(shr_limbs(limbs, bit_shift)),
+      None => Natural::ZERO,
+    }
+  }
+}
+
+impl ShrAssign<u64> for Natural {
+  fn shr_assign(&mut self, shift: u64) {
+    *self = std::mem::take(self) >> shift;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    collections::HashMap,
+    ops::RangeInclusive,
+  };
+
+  use super::*;
+
+  thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+  }
+
+  /// An allocator that counts the allocations made on each thread, so that
+  /// tests running in parallel do not interfere with each other's counts.
+  struct CountingAllocator;
+
+  unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+      let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+      unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+      unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(
+      &self,
+      ptr: *mut u8,
+      layout: Layout,
+      new_size: usize,
+    ) -> *mut u8 {
+      let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+      unsafe { System.realloc(ptr, layout, new_size) }
+    }
+  }
+
+  #[global_allocator]
+  static GLOBAL: CountingAllocator = CountingAllocator;
+
+  /// Count the allocations made by the current thread while running `f`.
+  fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.get();
+    f();
+    ALLOCATIONS.get() - before
+  }
+
+  const SMALL_MAX: Natural = Natural(Repr::Small(Limb::MAX));
+
+  /// Assert that the result of an arithmetic operation is in canonical form and
+  /// is equal to the expected value.
+  #[track_caller]
+  fn assert_result(actual: Natural, expected: Natural) {
+    assert!(
+      actual.is_canonical(),
+      "{actual:?} is not in canonical form:\n{}",
+      actual.hex_dump()
+    );
+    assert_eq!(
+      actual,
+      expected,
+      "\nactual:\n{}expected:\n{}",
+      actual.hex_dump(),
+      expected.hex_dump()
+    );
+  }
+
+  /// Assert that a list of equalities on [`Natural`] numbers hold.
+  macro_rules! assert_exprs {
+    ($($left:literal $op:tt $right:literal = $result:literal),* $(,)?) => {
+      $(
+        assert_result(
+          <Natural as From<Limb>>::from($left)
+            $op <Natural as From<Limb>>::from($right),
+          <Natural as From<Limb>>::from($result),
+        );
+      )*
+    };
+  }
+
+  #[test]
+  fn test_add_small_small() {
+    assert_exprs! {
+      0 + 0 = 0,
+      0 + 1 = 1,
+      1 + 0 = 1,
+      1 + 1 = 2,
+      123 + 456 = 579
+    };
+  }
+
+  #[test]
+  fn test_add_small_small_overflow() {
+    assert_result(SMALL_MAX + Natural::ONE, Natural::from_limbs(&[0, 1]));
+    assert_result(
+      SMALL_MAX + SMALL_MAX,
+      Natural::from_limbs(&[Limb::MAX - 1, 1]),
+    );
+  }
+
+  #[test]
+  fn test_add_small_large() {
+    let small = Natural::from(123_u64);
+    let large = Natural::from_limbs(&[456, 1]);
+    assert_result(small + large, Natural::from_limbs(&[579, 1]));
+
+    let small = Natural::from(42_u64);
+    let large = Natural::from_limbs(&[100, 200, 300]);
+    assert_result(small + large, Natural::from_limbs(&[142, 200, 300]));
+  }
+
+  #[test]
+  fn test_add_small_large_overflow() {
+    let small = Natural::from(1_u64);
+    let large = Natural::from_limbs(&[Limb::MAX, 0]);
+    assert_result(small + large, Natural::from_limbs(&[0, 1]));
+
+    let small = Natural::from(5_u64);
+    let large = Natural::from_limbs(&[Limb::MAX - 3, Limb::MAX]);
+    assert_result(small + large, Natural::from_limbs(&[1, 0, 1]));
+  }
+
+  #[test]
+  fn test_add_large_small() {
+    let large = Natural::from_limbs(&[100, 200]);
+    let small = Natural::from(50_u64);
+    assert_result(large + small, Natural::from_limbs(&[150, 200]));
+
+    let large = Natural::from_limbs(&[1000, 2000, 3000]);
+    let small = Natural::from(234_u64);
+    assert_result(large + small, Natural::from_limbs(&[1234, 2000, 3000]));
+  }
+
+  #[test]
+  fn test_add_large_small_overflow() {
+    let large = Natural::from_limbs(&[Limb::MAX, 10]);
+    let small = Natural::from(1_u64);
+    assert_result(large + small, Natural::from_limbs(&[0, 11]));
+
+    let large = Natural::from_limbs(&[Limb::MAX, Limb::MAX]);
+    let small = Natural::from(1_u64);
+    assert_result(large + small, Natural::from_limbs(&[0, 0, 1]));
+  }
+
+  #[test]
+  fn test_add_large_large() {
+    let a = Natural::from_limbs(&[123, 456]);
+    let b = Natural::from_limbs(&[789, 123]);
+    assert_result(a + b, Natural::from_limbs(&[912, 579]));
+
+    let a = Natural::from_limbs(&[100, 200, 300]);
+    let b = Natural::from_limbs(&[400, 500]);
+    assert_result(a + b, Natural::from_limbs(&[500, 700, 300]));
+  }
+
+  #[test]
+  fn test_add_large_large_overflow() {
+    let a = Natural::from_limbs(&[Limb::MAX, 10]);
+    let b = Natural::from_limbs(&[1, 5]);
+    assert_result(a + b, Natural::from_limbs(&[0, 16]));
+
+    let a = Natural::from_limbs(&[Limb::MAX, Limb::MAX]);
+    let b = Natural::from_limbs(&[1, 0]);
+    assert_result(a + b, Natural::from_limbs(&[0, 0, 1]));
+
+    let a = Natural::from_limbs(&[Limb::MAX, Limb::MAX, Limb::MAX]);
+    let b = Natural::from_limbs(&[1, 0, 0]);
+    assert_result(a + b, Natural::from_limbs(&[0, 0, 0, 1]));
+  }
+
+  #[test]
+  fn test_add_assign_allocations() {
+    let mut x = Natural::from(5_u64);
+    let y = Natural::from_limbs(&[1, 2]);
+    assert_eq!(count_allocations(|| x += y), 0);
+    assert_result(x, Natural::from_limbs(&[6, 2]));
+
+    let mut x = Natural::from_limbs(&[Limb::MAX, 2]);
+    assert_eq!(count_allocations(|| x += Natural::ONE), 0);
+    assert_result(x, Natural::from_limbs(&[0, 3]));
+
+    let mut x = Natural::from_limbs(&[1, 2]);
+    let y = Natural::from_limbs(&[3, 4, 5]);
+    assert_eq!(count_allocations(|| x += y), 0);
+    assert_result(x, Natural::from_limbs(&[4, 6, 5]));
+
+    // Carrying out into a new limb is the only case that may allocate.
+    let mut x = Natural::from_limbs(&[Limb::MAX, Limb::MAX]);
+    assert!(count_allocations(|| x += Natural::ONE) <= 1);
+    assert_result(x, Natural::from_limbs(&[0, 0, 1]));
+  }
+
+  #[test]
+  fn test_add_assign_limb() {
+    let numbers = [
+      Natural::ZERO,
+      Natural::from(5_u64),
+      SMALL_MAX,
+      Natural::from_limbs(&[Limb::MAX, 2]),
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX]),
+    ];
+
+    for n in numbers {
+      for y in [0, 1, 42, Limb::MAX] {
+        let mut x = n.clone();
+        x += y;
+        assert_result(x, n.clone() + Natural::from(y));
+      }
+    }
+
+    let mut x = SMALL_MAX;
+    x += 1;
+    assert_result(x, Natural::from_limbs(&[0, 1]));
+
+    let mut x = Natural::from_limbs(&[Limb::MAX, Limb::MAX]);
+    x += Limb::MAX;
+    assert_result(x, Natural::from_limbs(&[Limb::MAX - 1, 0, 1]));
+
+    let mut x = Natural::from_limbs(&[Limb::MAX, 2]);
+    assert_eq!(count_allocations(|| x += 1), 0);
+    assert_result(x, Natural::from_limbs(&[0, 3]));
+  }
+
+  #[test]
+  fn test_mul_assign_limb() {
+    let numbers = [
+      Natural::ZERO,
+      Natural::ONE,
+      Natural::from(5_u64),
+      SMALL_MAX,
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX]),
+    ];
+
+    for n in numbers {
+      for y in [0, 1, 2, 42, Limb::MAX] {
+        let mut x = n.clone();
+        x *= y;
+        assert_result(x, n.clone() * Natural::from(y));
+      }
+    }
+
+    let mut x = SMALL_MAX;
+    x *= 2;
+    assert_result(x, Natural::from_limbs(&[Limb::MAX - 1, 1]));
+
+    let mut x = Natural::from_limbs(&[Limb::MAX, Limb::MAX]);
+    x *= Limb::MAX;
+    assert_result(x, Natural::from_limbs(&[1, Limb::MAX, Limb::MAX - 1]));
+
+    let mut x = Natural::from_limbs(&[1, 2]);
+    x *= 0;
+    assert_result(x, Natural::ZERO);
+  }
+
+  #[test]
+  fn test_mul_small_small() {
+    assert_exprs! {
+      0 * 0 = 0,
+      1 * 0 = 0,
+      0 * 1 = 0,
+      1 * 1 = 1,
       0 * 5 = 0,
       5 * 0 = 0,
       1 * 5 = 5,
@@ -314,21 +1880,1822 @@ mod tests {
   }
 
   #[test]
-  fn test_mul_small_small_overflow() {
+  fn test_mul_small_small_overflow() {
+    assert_result(
+      SMALL_MAX * Natural::from(2_u64),
+      Natural::from_limbs(&[0xFFFF_FFFF_FFFF_FFFE, 1]),
+    );
+
+    let half_max = Natural::from(Limb::MAX / 2 + 1);
+    assert_result(
+      half_max.clone() * half_max,
+      Natural::from_limbs(&[0, 0x4000_0000_0000_0000]),
+    );
+
+    assert_result(
+      SMALL_MAX * SMALL_MAX,
+      Natural::from_limbs(&[0x1, 0xfffffffffffffffe]),
+    );
+  }
+
+  #[test]
+  fn test_mul_large_small() {
+    assert_result(
+      Natural::from_limbs(&[1, 2]) * Natural::from(3_u64),
+      Natural::from_limbs(&[3, 6]),
+    );
+    assert_result(
+      Natural::from(3_u64) * Natural::from_limbs(&[1, 2, 3]),
+      Natural::from_limbs(&[3, 6, 9]),
+    );
+
+    // The carry out of each limb is added to the next.
+    assert_result(
+      Natural::from_limbs(&[Limb::MAX, 1]) * Natural::from(2_u64),
+      Natural::from_limbs(&[Limb::MAX - 1, 3]),
+    );
+
+    // The carry out of the top limb becomes a new limb.
+    assert_result(
+      Natural::from_limbs(&[0, 1 << 63]) * Natural::from(2_u64),
+      Natural::from_limbs(&[0, 0, 1]),
+    );
+    assert_result(
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX]) * SMALL_MAX,
+      Natural::from_limbs(&[1, Limb::MAX, Limb::MAX - 1]),
+    );
+
+    // Multiplying by 0 or 1 is handled before the general case.
+    assert_result(Natural::from_limbs(&[1, 2]) * Natural::ZERO, Natural::ZERO);
+    assert_result(
+      Natural::from_limbs(&[1, 2]) * Natural::ONE,
+      Natural::from_limbs(&[1, 2]),
+    );
+  }
+
+  #[test]
+  fn test_mul_large_large() {
+    // (2^64 + 1)^2 = 2^128 + 2^65 + 1.
+    assert_result(
+      Natural::from_limbs(&[1, 1]) * Natural::from_limbs(&[1, 1]),
+      Natural::from_limbs(&[1, 2, 1]),
+    );
+
+    // Operands of differing lengths.
+    assert_result(
+      Natural::from_limbs(&[1, 2, 3]) * Natural::from_limbs(&[4, 5]),
+      Natural::from_limbs(&[4, 13, 22, 15]),
+    );
+    assert_result(
+      Natural::from_limbs(&[4, 5]) * Natural::from_limbs(&[1, 2, 3]),
+      Natural::from_limbs(&[4, 13, 22, 15]),
+    );
+
+    // (2^128 - 1)^2 = 2^256 - 2^129 + 1.
+    assert_result(
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX])
+        * Natural::from_limbs(&[Limb::MAX, Limb::MAX]),
+      Natural::from_limbs(&[1, 0, Limb::MAX - 1, Limb::MAX]),
+    );
+  }
+
+  #[test]
+  fn test_mul_large_large_trims_top_limb() {
+    // The product of two-limb numbers has room for four limbs, but 2^64 * 2^64
+    // only needs three.
+    assert_result(
+      Natural::from_limbs(&[0, 1]) * Natural::from_limbs(&[0, 1]),
+      Natural::from_limbs(&[0, 0, 1]),
+    );
+    assert_result(
+      Natural::from_limbs(&[3, 1]) * Natural::from_limbs(&[5, 0, 1]),
+      Natural::from_limbs(&[15, 5, 3, 1]),
+    );
+  }
+
+  /// Generate a fixed sequence of pseudorandom limbs with a nonzero top limb.
+  fn pseudorandom_limbs(len: usize, seed: u64) -> Vec<Limb> {
+    let mut state = seed;
+    let mut limbs: Vec<Limb> = (0..len)
+      .map(|_| {
+        // Xorshift is plenty for generating test inputs.
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+      })
+      .collect();
+    if let Some(top) = limbs.last_mut() {
+      *top |= 1;
+    }
+    limbs
+  }
+
+  #[test]
+  fn test_mul_karatsuba_matches_schoolbook() {
+    let sizes = [
+      (KARATSUBA_THRESHOLD, KARATSUBA_THRESHOLD),
+      (KARATSUBA_THRESHOLD + 1, KARATSUBA_THRESHOLD + 1),
+      (50, 50),
+      (100, 100),
+      (257, 257),
+      // Unequal lengths, including ones where the shorter operand does not
+      // reach the split point.
+      (40, 100),
+      (100, 40),
+      (33, 200),
+      (200, 150),
+    ];
+
+    for (i, (x_len, y_len)) in sizes.into_iter().enumerate() {
+      let x = pseudorandom_limbs(x_len, 2 * i as u64 + 1);
+      let y = pseudorandom_limbs(y_len, 2 * i as u64 + 2);
+      assert_eq!(
+        mul_karatsuba(&x, &y),
+        mul_schoolbook(&x, &y),
+        "{x_len} limbs by {y_len} limbs",
+      );
+    }
+  }
+
+  #[test]
+  fn test_mul_karatsuba_all_ones() {
+    // Operands of all one bits maximise the carries in the middle term.
+    for len in [KARATSUBA_THRESHOLD, 64, 101] {
+      let x = vec![Limb::MAX; len];
+      assert_eq!(mul_karatsuba(&x, &x), mul_schoolbook(&x, &x), "{len} limbs");
+    }
+  }
+
+  #[test]
+  fn test_mul_repeated() {
+    // Multiplying past a single limb and then multiplying again should work.
+    let mut x = Natural::ONE;
+    for _ in 0..100 {
+      x *= Natural::from(10_u64);
+    }
+
+    let mut digits = vec![0; 101];
+    digits[0] = 1;
+    assert_result(x, Natural::from_digits(&digits, 10).unwrap());
+  }
+
+  #[test]
+  fn test_operations_preserve_canonical_form() {
+    let values = [
+      Natural::ZERO,
+      Natural::ONE,
+      Natural::from(123_u64),
+      Natural::from(Limb::MAX / 2 + 1),
+      SMALL_MAX,
+      Natural::from_limbs(&[0, 1]),
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX]),
+      Natural::from_limbs(&[1, 2, 3]),
+    ];
+
+    for x in &values {
+      assert!(x.is_canonical(), "{x:?} is not in canonical form");
+
+      for y in &values {
+        let sum = x.clone() + y.clone();
+        assert!(sum.is_canonical(), "{x:?} + {y:?} = {sum:?}");
+
+        let mut acc = x.clone();
+        acc += y.clone();
+        acc += y.clone();
+        assert!(acc.is_canonical(), "{x:?} + 2 * {y:?} = {acc:?}");
+
+        let product = x.clone() * y.clone();
+        assert!(product.is_canonical(), "{x:?} * {y:?} = {product:?}");
+      }
+    }
+  }
+
+  #[test]
+  fn test_from_u128() {
+    assert_result(Natural::from(0_u128), Natural::ZERO);
+    assert_result(Natural::from(u128::from(u64::MAX)), SMALL_MAX);
+    assert_result(
+      Natural::from(u128::from(u64::MAX) + 1),
+      Natural::from_limbs(&[0, 1]),
+    );
+    assert_result(
+      Natural::from(u128::MAX),
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX]),
+    );
+    assert_eq!(u128::try_from(Natural::from(u128::MAX - 5)), Ok(u128::MAX - 5));
+  }
+
+  #[test]
+  fn test_from_narrow_integers() {
+    assert_result(Natural::from(u8::MAX), Natural::from(255_u64));
+    assert_result(Natural::from(u16::MAX), Natural::from(65_535_u64));
+    assert_result(Natural::from(u32::MAX), Natural::from(4_294_967_295_u64));
+    assert_result(Natural::from(usize::MAX), SMALL_MAX);
+    assert_result(Natural::from(0_u8), Natural::ZERO);
+  }
+
+  #[test]
+  fn test_try_from_u64() {
+    assert_eq!(u64::try_from(Natural::ZERO), Ok(0));
+    assert_eq!(u64::try_from(&SMALL_MAX), Ok(u64::MAX));
+    assert_eq!(
+      u64::try_from(Natural::from_limbs(&[0, 1])),
+      Err(TryFromNaturalError { target: "u64" })
+    );
+  }
+
+  #[test]
+  fn test_try_from_u128() {
+    assert_eq!(u128::try_from(SMALL_MAX), Ok(u128::from(u64::MAX)));
+    assert_eq!(
+      u128::try_from(Natural::from_limbs(&[0, 1])),
+      Ok(u128::from(u64::MAX) + 1)
+    );
+    assert_eq!(
+      u128::try_from(&Natural::from_limbs(&[Limb::MAX, Limb::MAX])),
+      Ok(u128::MAX)
+    );
+    assert_eq!(
+      u128::try_from(Natural::from_limbs(&[0, 0, 1])),
+      Err(TryFromNaturalError { target: "u128" })
+    );
+  }
+
+  #[test]
+  fn test_try_from_i64_and_usize() {
+    assert_eq!(i64::try_from(Natural::from(i64::MAX as u64)), Ok(i64::MAX));
+    assert_eq!(
+      i64::try_from(Natural::from(i64::MAX as u64 + 1)),
+      Err(TryFromNaturalError { target: "i64" })
+    );
+    assert_eq!(usize::try_from(&Natural::from(42_u64)), Ok(42));
+    assert_eq!(
+      usize::try_from(Natural::from_limbs(&[0, 1])),
+      Err(TryFromNaturalError { target: "usize" })
+    );
+    assert_eq!(
+      TryFromNaturalError { target: "i64" }.to_string(),
+      "Natural number is too large to fit in i64"
+    );
+  }
+
+  #[test]
+  fn test_display() {
+    assert_eq!(Natural::ZERO.to_string(), "0");
+    assert_eq!(Natural::from(42_u64).to_string(), "42");
+    assert_eq!(SMALL_MAX.to_string(), "18446744073709551615");
+
+    // 2^64 and 2^128.
+    assert_eq!(
+      Natural::from_limbs(&[0, 1]).to_string(),
+      "18446744073709551616"
+    );
+    assert_eq!(
+      Natural::from_limbs(&[0, 0, 1]).to_string(),
+      "340282366920938463463374607431768211456"
+    );
+  }
+
+  #[test]
+  fn test_display_pads_inner_chunks() {
+    // 10^38 has chunks of zeros below its leading digit which must be padded.
+    assert_eq!(Natural::pow10(38).to_string(), format!("1{}", "0".repeat(38)));
+    assert_eq!(
+      (Natural::pow10(40) + Natural::from(7_u64)).to_string(),
+      format!("1{}7", "0".repeat(39))
+    );
+  }
+
+  #[test]
+  fn test_display_matches_to_digits() {
+    let n = Natural::from_limbs(&[0x1234_5678_9abc_def0, 42, 7, 1]);
+    let digits: String =
+      n.to_digits(10).iter().map(|&d| char::from(b'0' + d)).collect();
+    assert_eq!(n.to_string(), digits);
+    assert_eq!(format!("{n:>100}"), format!("{digits:>100}"));
+  }
+
+  /// Hash a value with the standard library's default hasher.
+  fn hash_of(value: &impl Hash) -> u64 {
+    let mut hasher = std::hash::DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+  }
+
+  #[test]
+  fn test_hash_ignores_representation() {
+    let cases = [
+      (Natural::from(5_u64), Natural(Repr::Large(vec![5]))),
+      (Natural::ZERO, Natural(Repr::Large(vec![0, 0]))),
+      (Natural::from_limbs(&[1, 2]), Natural(Repr::Large(vec![1, 2, 0]))),
+    ];
+
+    for (canonical, unnormalised) in cases {
+      assert_eq!(hash_of(&canonical), hash_of(&unnormalised), "{canonical:?}");
+    }
+  }
+
+  #[test]
+  fn test_hash_map_keys() {
+    let keys = [
+      Natural::ZERO,
+      Natural::ONE,
+      SMALL_MAX,
+      Natural::from(u128::from(Limb::MAX) + 1),
+      Natural::from(u128::from(Limb::MAX) + 2),
+      Natural::from_limbs(&[0, 0, 1]),
+    ];
+
+    let map: HashMap<_, _> =
+      keys.iter().cloned().enumerate().map(|(i, key)| (key, i)).collect();
+    assert_eq!(map.len(), keys.len());
+
+    for (i, key) in keys.iter().enumerate() {
+      assert_eq!(map.get(key), Some(&i), "{key:?}");
+    }
+    assert_eq!(map.get(&Natural::from(2_u64)), None);
+    assert_eq!(map.get(&Natural::from_limbs(&[2, 1])), None);
+  }
+
+  #[test]
+  fn test_cmp_equal() {
+    assert_eq!(Natural::ZERO.cmp(&Natural::ZERO), Ordering::Equal);
+    assert_eq!(
+      Natural::from(42_u64).cmp(&Natural::from(42_u64)),
+      Ordering::Equal
+    );
+    assert_eq!(
+      Natural::from_limbs(&[1, 2, 3]).cmp(&Natural::from_limbs(&[1, 2, 3])),
+      Ordering::Equal
+    );
+  }
+
+  #[test]
+  fn test_cmp_small() {
+    assert!(Natural::ZERO < Natural::ONE);
+    assert!(Natural::from(5_u64) > Natural::from(3_u64));
+    assert!(SMALL_MAX < Natural::from_limbs(&[0, 1]));
+    assert!(Natural::from_limbs(&[0, 1]) > SMALL_MAX);
+  }
+
+  #[test]
+  fn test_cmp_differing_lengths() {
+    // A number with more limbs is larger regardless of the values of its
+    // lower limbs.
+    assert!(
+      Natural::from_limbs(&[0, 0, 1])
+        > Natural::from_limbs(&[Limb::MAX, Limb::MAX])
+    );
+    assert!(Natural::from_limbs(&[5, 1]) < Natural::from_limbs(&[0, 0, 1]));
+  }
+
+  #[test]
+  fn test_cmp_top_limb() {
+    assert!(Natural::from_limbs(&[7, 7, 2]) < Natural::from_limbs(&[7, 7, 3]));
+    // The top limb decides the comparison even when the lower limbs disagree.
+    assert!(Natural::from_limbs(&[9, 1]) < Natural::from_limbs(&[1, 2]));
+
+    let mut numbers = vec![
+      Natural::from_limbs(&[0, 3]),
+      Natural::from(7_u64),
+      Natural::from_limbs(&[1, 0, 1]),
+      Natural::from_limbs(&[Limb::MAX, 2]),
+      Natural::ZERO,
+    ];
+    numbers.sort();
+    assert_eq!(
+      numbers,
+      [
+        Natural::ZERO,
+        Natural::from(7_u64),
+        Natural::from_limbs(&[Limb::MAX, 2]),
+        Natural::from_limbs(&[0, 3]),
+        Natural::from_limbs(&[1, 0, 1]),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_div_rem_small() {
+    assert_eq!(
+      Natural::from(17_u64).div_rem(Natural::from(5_u64)),
+      (Natural::from(3_u64), Natural::from(2_u64))
+    );
+    assert_eq!(
+      Natural::from(20_u64) / Natural::from(5_u64),
+      Natural::from(4_u64)
+    );
+    assert_eq!(Natural::from(20_u64) % Natural::from(5_u64), Natural::ZERO);
+    assert_eq!(
+      Natural::from(3_u64).div_rem(Natural::from(7_u64)),
+      (Natural::ZERO, Natural::from(3_u64))
+    );
+  }
+
+  #[test]
+  fn test_div_rem_large_small() {
+    // (2^64 + 5) / 2 = 2^63 + 2 remainder 1.
+    let (quotient, remainder) =
+      Natural::from_limbs(&[5, 1]).div_rem(Natural::from(2_u64));
+    assert_result(quotient, Natural::from((1_u64 << 63) + 2));
+    assert_result(remainder, Natural::ONE);
+
+    let (quotient, remainder) =
+      Natural::from_limbs(&[0, 0, 3]).div_rem(Natural::from(3_u64));
+    assert_result(quotient, Natural::from_limbs(&[0, 0, 1]));
+    assert_result(remainder, Natural::ZERO);
+  }
+
+  #[test]
+  fn test_div_rem_divisor_larger() {
+    let small = Natural::from(12345_u64);
+    let large = Natural::from_limbs(&[1, 2]);
+    let larger = Natural::from_limbs(&[1, 2, 3]);
+
+    assert_eq!(small.clone().div_rem(large.clone()), (Natural::ZERO, small));
+    assert_eq!(large.clone().div_rem(larger), (Natural::ZERO, large.clone()));
+    assert_eq!(
+      large.clone().div_rem(Natural::from_limbs(&[2, 2])),
+      (Natural::ZERO, large)
+    );
+  }
+
+  #[test]
+  fn test_div_rem_exact() {
+    let x = Natural::from_limbs(&[0x1234_5678_9abc_def0, 42, 7, 1]);
+    let y = Natural::from_limbs(&[Limb::MAX, 3, 99]);
+    let (quotient, remainder) = (x.clone() * y.clone()).div_rem(y.clone());
+    assert_result(quotient, x.clone());
+    assert_result(remainder, Natural::ZERO);
+
+    assert_result(x.clone() / x.clone(), Natural::ONE);
+    assert_result(x.clone() % x, Natural::ZERO);
+  }
+
+  #[test]
+  fn test_div_rem_with_remainder() {
+    let x = Natural::from_limbs(&[5, 6, 7, 8, 9]);
+    let y = Natural::from_limbs(&[1, 1 << 40]);
+    let r = Natural::from_limbs(&[12345, 1 << 39]);
+    let (quotient, remainder) = (x.clone() * y.clone() + r.clone()).div_rem(y);
+    assert_result(quotient, x);
+    assert_result(remainder, r);
+  }
+
+  #[test]
+  fn test_div_rem_add_back() {
+    // These require the rare step of adding the divisor back after an
+    // overestimated quotient limb.
+    let (quotient, remainder) =
+      Natural::from_limbs(&[0, 1 << 63, (1 << 63) + 1, (1 << 63) - 1])
+        .div_rem(Natural::from_limbs(&[Limb::MAX - 1, 1, 1 << 63]));
+    assert_result(quotient, Natural::from(Limb::MAX - 1));
+    assert_result(
+      remainder,
+      Natural::from_limbs(&[Limb::MAX - 3, (1 << 63) + 5, (1 << 63) - 1]),
+    );
+
+    let (quotient, remainder) = Natural::from_limbs(&[Limb::MAX - 1; 3])
+      .div_rem(Natural::from_limbs(&[Limb::MAX, Limb::MAX, 2]));
+    assert_result(quotient, Natural::from(6_148_914_691_236_517_204_u64));
+    assert_result(
+      remainder,
+      Natural::from_limbs(&[6_148_914_691_236_517_202, Limb::MAX, 2]),
+    );
+  }
+
+  #[test]
+  fn test_div_rem_pseudorandom() {
+    for (i, (x_len, y_len)) in
+      [(2, 2), (3, 2), (5, 3), (10, 4), (40, 7), (33, 32)]
+        .into_iter()
+        .enumerate()
+    {
+      let x = Natural::from_limbs(&pseudorandom_limbs(x_len, 2 * i as u64 + 1));
+      let y = Natural::from_limbs(&pseudorandom_limbs(y_len, 2 * i as u64 + 2));
+      let (quotient, remainder) = x.clone().div_rem(y.clone());
+
+      assert!(quotient.is_canonical() && remainder.is_canonical());
+      assert!(remainder < y, "{x_len} limbs by {y_len} limbs");
+      assert_eq!(quotient * y + remainder, x, "{x_len} limbs by {y_len} limbs");
+    }
+  }
+
+  #[test]
+  fn test_div_rem_assign() {
+    let mut n = Natural::from_limbs(&[0, 0, 6]);
+    n /= Natural::from_limbs(&[0, 2]);
+    assert_result(n.clone(), Natural::from_limbs(&[0, 3]));
+    n %= Natural::from(5_u64);
+    // 2^64 is 1 modulo 5.
+    assert_result(n, Natural::from(3_u64));
+  }
+
+  #[test]
+  #[should_panic(expected = "attempt to divide by zero")]
+  fn test_div_by_zero() {
+    let _ = Natural::ONE / Natural::ZERO;
+  }
+
+  #[test]
+  #[should_panic(
+    expected = "attempt to calculate the remainder with a divisor of zero"
+  )]
+  fn test_rem_by_zero() {
+    let _ = Natural::from_limbs(&[1, 1]) % Natural::ZERO;
+  }
+
+  #[test]
+  fn test_abs_diff_small() {
+    let (a, b) = (Natural::from(10_u64), Natural::from(3_u64));
+    assert_result(a.abs_diff(&b), Natural::from(7_u64));
+    assert_result(b.abs_diff(&a), Natural::from(7_u64));
+    assert_result(a.abs_diff(&a), Natural::ZERO);
+    assert_result(SMALL_MAX.abs_diff(&Natural::ZERO), SMALL_MAX);
+  }
+
+  #[test]
+  fn test_abs_diff_large() {
+    let a = Natural::from_limbs(&[5, 10]);
+    let b = Natural::from_limbs(&[2, 3]);
+    assert_result(a.abs_diff(&b), Natural::from_limbs(&[3, 7]));
+    assert_result(b.abs_diff(&a), Natural::from_limbs(&[3, 7]));
+    assert_result(a.abs_diff(&a.clone()), Natural::ZERO);
+
+    let a = Natural::from_limbs(&[1, 2, 3]);
+    let b = Natural::from_limbs(&[1, 2, 4]);
+    assert_result(a.abs_diff(&b), Natural::from_limbs(&[0, 0, 1]));
+  }
+
+  #[test]
+  fn test_abs_diff_borrow() {
+    let a = Natural::from_limbs(&[0, 0, 1]);
+    assert_result(
+      a.abs_diff(&Natural::ONE),
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX]),
+    );
+    assert_result(
+      Natural::ONE.abs_diff(&a),
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX]),
+    );
+
+    let a = Natural::from_limbs(&[0, 1]);
+    assert_result(a.abs_diff(&Natural::ONE), SMALL_MAX);
+
+    let a = Natural::from_limbs(&[3, 5]);
+    let b = Natural::from_limbs(&[4, 4]);
+    assert_result(a.abs_diff(&b), SMALL_MAX);
+  }
+
+  #[test]
+  fn test_checked_sub_small() {
+    assert_eq!(
+      Natural::from(10_u64).checked_sub_small(3),
+      Some(Natural::from(7_u64))
+    );
+    assert_eq!(Natural::from(3_u64).checked_sub_small(3), Some(Natural::ZERO));
+    assert_eq!(Natural::ZERO.checked_sub_small(0), Some(Natural::ZERO));
+    assert_result(
+      Natural::from_limbs(&[10, 5, 1]).checked_sub_small(3).unwrap(),
+      Natural::from_limbs(&[7, 5, 1]),
+    );
+  }
+
+  #[test]
+  fn test_checked_sub_small_borrow() {
+    // 2^64 - 1 drops back down to a small natural.
+    assert_result(
+      Natural::from_limbs(&[0, 1]).checked_sub_small(1).unwrap(),
+      SMALL_MAX,
+    );
+
+    // The borrow propagates through every limb but the last.
+    assert_result(
+      Natural::from_limbs(&[0, 0, 0, 1]).checked_sub_small(1).unwrap(),
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX, Limb::MAX]),
+    );
+    assert_result(
+      Natural::from_limbs(&[2, 0, 0, 7]).checked_sub_small(5).unwrap(),
+      Natural::from_limbs(&[Limb::MAX - 2, Limb::MAX, Limb::MAX, 6]),
+    );
+  }
+
+  #[test]
+  fn test_checked_sub_small_underflow() {
+    assert_eq!(Natural::ZERO.checked_sub_small(1), None);
+    assert_eq!(Natural::from(3_u64).checked_sub_small(4), None);
+    assert_eq!(Natural::from(5_u64).checked_sub_small(Limb::MAX), None);
+  }
+
+  #[test]
+  fn test_default() {
+    assert_result(Natural::default(), Natural::ZERO);
+
+    let mut totals: HashMap<&str, Natural> = HashMap::new();
+    *totals.entry("a").or_default() += Natural::from(2_u64);
+    *totals.entry("a").or_default() += Natural::from(3_u64);
+    totals.entry("b").or_default();
+
+    assert_eq!(totals["a"], Natural::from(5_u64));
+    assert_eq!(totals["b"], Natural::ZERO);
+  }
+
+  #[test]
+  fn test_from_digits() {
+    assert_result(
+      Natural::from_digits(&[3, 6, 0], 10).unwrap(),
+      360_u64.into(),
+    );
+    assert_result(Natural::from_digits(&[0, 0, 7], 10).unwrap(), 7_u64.into());
+    assert_result(Natural::from_digits(&[], 10).unwrap(), Natural::ZERO);
+    assert_result(Natural::from_digits(&[1, 0, 1], 2).unwrap(), 5_u64.into());
+    assert_result(
+      Natural::from_digits(&[1, 0, 0, 0, 0, 0, 0, 0, 0], 256).unwrap(),
+      Natural::from_limbs(&[0, 1]),
+    );
+  }
+
+  #[test]
+  fn test_from_digits_matches_naive() {
+    /// Construct a natural from its digits by shifting in one digit at a time.
+    fn from_digits_naive(digits: &[u8], radix: u32) -> Natural {
+      let mut limbs = Vec::new();
+      for &digit in digits {
+        mul_add_limb(&mut limbs, Limb::from(radix), Limb::from(digit));
+      }
+      Natural::from_vec(limbs)
+    }
+
+    // A simple linear congruential generator is enough to produce digits that
+    // exercise chunks of every length.
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    for radix in [2, 3, 10, 16, 36, 255, 256] {
+      for len in [0, 1, 18, 19, 20, 38, 39, 100, 500] {
+        let digits: Vec<u8> = (0..len)
+          .map(|_| {
+            state =
+              state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            ((state >> 33) % u64::from(radix)) as u8
+          })
+          .collect();
+
+        assert_result(
+          Natural::from_digits(&digits, radix).unwrap(),
+          from_digits_naive(&digits, radix),
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn test_from_digits_error() {
+    assert_eq!(
+      Natural::from_digits(&[1, 2, 10], 10),
+      Err(ParseNaturalError::InvalidDigit { digit: 10, radix: 10 })
+    );
+    assert_eq!(
+      Natural::from_digits(&[2], 2),
+      Err(ParseNaturalError::InvalidDigit { digit: 2, radix: 2 })
+    );
+    assert_eq!(
+      Natural::from_digits(&[0], 1),
+      Err(ParseNaturalError::InvalidRadix(1))
+    );
+    assert_eq!(
+      Natural::from_digits(&[0], 257),
+      Err(ParseNaturalError::InvalidRadix(257))
+    );
+  }
+
+  #[test]
+  fn test_from_str() {
+    assert_eq!("0".parse(), Ok(Natural::ZERO));
+    assert_eq!("42".parse(), Ok(Natural::from(42_u64)));
+    assert_eq!("+42".parse(), Ok(Natural::from(42_u64)));
+    assert_eq!("007".parse(), Ok(Natural::from(7_u64)));
+    assert_eq!("18446744073709551615".parse(), Ok(SMALL_MAX));
+  }
+
+  #[test]
+  fn test_from_str_large() {
+    assert_result(
+      "18446744073709551616".parse().unwrap(),
+      Natural::from_limbs(&[0, 1]),
+    );
+    assert_result(
+      "340282366920938463463374607431768211456".parse().unwrap(),
+      Natural::from_limbs(&[0, 0, 1]),
+    );
+    assert_result(
+      "000340282366920938463463374607431768211457".parse().unwrap(),
+      Natural::from_limbs(&[1, 0, 1]),
+    );
+
+    let n = Natural::from_limbs(&[0x1234_5678_9abc_def0, 42, 7, 1]);
+    assert_eq!(n.to_string().parse(), Ok(n));
+  }
+
+  #[test]
+  fn test_from_str_error() {
+    assert_eq!("".parse::<Natural>(), Err(ParseNaturalError::Empty));
+    assert_eq!("+".parse::<Natural>(), Err(ParseNaturalError::Empty));
+    assert_eq!(
+      "-1".parse::<Natural>(),
+      Err(ParseNaturalError::InvalidCharacter('-'))
+    );
+    assert_eq!(
+      "12a4".parse::<Natural>(),
+      Err(ParseNaturalError::InvalidCharacter('a'))
+    );
+    assert_eq!(
+      " 1".parse::<Natural>(),
+      Err(ParseNaturalError::InvalidCharacter(' '))
+    );
+    assert_eq!(
+      "1٣".parse::<Natural>(),
+      Err(ParseNaturalError::InvalidCharacter('٣'))
+    );
+    assert_eq!(
+      "++1".parse::<Natural>(),
+      Err(ParseNaturalError::InvalidCharacter('+'))
+    );
+  }
+
+  #[test]
+  fn test_digits_round_trip() {
+    let values = [
+      Natural::ZERO,
+      Natural::ONE,
+      Natural::from(360_u64),
+      SMALL_MAX,
+      Natural::from_limbs(&[0, 1]),
+      Natural::from_limbs(&[Limb::MAX, 12345, 1 << 63]),
+    ];
+
+    for value in values {
+      for radix in [2, 3, 10, 16, 36, 256] {
+        let digits = value.to_digits(radix);
+        assert!(digits.len() == 1 || digits[0] != 0, "{digits:?}");
+        assert_result(
+          Natural::from_digits(&digits, radix).unwrap(),
+          value.clone(),
+        );
+      }
+    }
+
+    assert_eq!(Natural::from(360_u64).to_digits(10), [3, 6, 0]);
+    assert_eq!(Natural::ZERO.to_digits(10), [0]);
+    assert_eq!(
+      Natural::from_limbs(&[0, 1]).to_digits(16),
+      [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+    );
+  }
+
+  #[test]
+  fn test_from_str_radix() {
+    let parse = Natural::from_str_radix;
+    assert_eq!(parse("ff", 16), Ok(Natural::from(255_u64)));
+    assert_eq!(parse("FF", 16), Ok(Natural::from(255_u64)));
+    assert_eq!(parse("+dEaDbEeF", 16), Ok(Natural::from(0xdead_beef_u64)));
+    assert_eq!(parse("101", 2), Ok(Natural::from(5_u64)));
+    assert_eq!(parse("777", 8), Ok(Natural::from(0o777_u64)));
+    assert_eq!(parse("z", 36), Ok(Natural::from(35_u64)));
+    assert_eq!(parse("Zz", 36), Ok(Natural::from(36_u64 * 36 - 1)));
+    assert_eq!(parse("0000", 7), Ok(Natural::ZERO));
+
+    assert_result(
+      parse("10000000000000000", 16).unwrap(),
+      Natural::from_limbs(&[0, 1]),
+    );
+    assert_result(
+      parse("FEDCBA980123456789ABCDEF", 16).unwrap(),
+      Natural::from_limbs(&[0x0123_4567_89ab_cdef, 0xfedc_ba98]),
+    );
+  }
+
+  #[test]
+  fn test_from_str_radix_error() {
+    let parse = Natural::from_str_radix;
+    assert_eq!(parse("", 16), Err(ParseNaturalError::Empty));
+    assert_eq!(parse("+", 2), Err(ParseNaturalError::Empty));
+    assert_eq!(parse("102", 2), Err(ParseNaturalError::InvalidCharacter('2')));
+    assert_eq!(parse("fg", 16), Err(ParseNaturalError::InvalidCharacter('g')));
+    assert_eq!(
+      parse("0x1f", 16),
+      Err(ParseNaturalError::InvalidCharacter('x'))
+    );
+    assert_eq!(parse("-1", 36), Err(ParseNaturalError::InvalidCharacter('-')));
+  }
+
+  #[test]
+  fn test_str_radix_round_trip() {
+    for seed in 1..=8 {
+      let n = Natural::from_vec(pseudorandom_limbs(seed as usize, seed));
+      for radix in 2..=36 {
+        let s = n.to_str_radix(radix);
+        assert_eq!(Natural::from_str_radix(&s, radix), Ok(n.clone()));
+        let upper = s.to_uppercase();
+        assert_eq!(Natural::from_str_radix(&upper, radix), Ok(n.clone()));
+      }
+    }
+  }
+
+  #[test]
+  fn test_to_str_radix() {
+    for x in [0, 1, 9, 10, 35, 36, 255, 0xdead_beef, Limb::MAX] {
+      let n = Natural::from(x);
+      assert_eq!(n.to_str_radix(16), format!("{x:x}"));
+      assert_eq!(n.to_str_radix(8), format!("{x:o}"));
+      assert_eq!(n.to_str_radix(2), format!("{x:b}"));
+      assert_eq!(n.to_str_radix(10), x.to_string());
+    }
+
+    assert_eq!(Natural::from(35_u64).to_str_radix(36), "z");
+    assert_eq!(Natural::from(36_u64 * 36 - 1).to_str_radix(36), "zz");
+    assert_eq!(
+      Natural::from_limbs(&[0, 1]).to_str_radix(16),
+      "10000000000000000"
+    );
+    assert_eq!(
+      Natural::from_limbs(&[0x0123_4567_89ab_cdef, 0xfedc_ba98])
+        .to_str_radix(16),
+      "fedcba980123456789abcdef"
+    );
+    assert_eq!(
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX]).to_str_radix(10),
+      u128::MAX.to_string()
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "radix 37 is out of range")]
+  fn test_to_str_radix_out_of_range() {
+    let _ = Natural::ONE.to_str_radix(37);
+  }
+
+  #[test]
+  fn test_be_bytes() {
+    assert!(Natural::ZERO.to_be_bytes().is_empty());
+    assert_eq!(Natural::from(1_u64).to_be_bytes(), [1]);
+    assert_eq!(Natural::from(0x1234_u64).to_be_bytes(), [0x12, 0x34]);
+    assert_eq!(
+      Natural::from_limbs(&[0x0102_0304_0506_0708, 0x09]).to_be_bytes(),
+      [9, 1, 2, 3, 4, 5, 6, 7, 8]
+    );
+
+    assert_result(Natural::from_be_bytes(&[]), Natural::ZERO);
+    assert_result(Natural::from_be_bytes(&[0, 0, 0]), Natural::ZERO);
+    assert_result(
+      Natural::from_be_bytes(&[0, 0x12, 0x34]),
+      Natural::from(0x1234_u64),
+    );
+    assert_result(
+      Natural::from_be_bytes(&[9, 1, 2, 3, 4, 5, 6, 7, 8]),
+      Natural::from_limbs(&[0x0102_0304_0506_0708, 0x09]),
+    );
+    assert_result(
+      Natural::from_be_bytes(&[1, 0, 0, 0, 0, 0, 0, 0, 0]),
+      Natural::from_limbs(&[0, 1]),
+    );
+  }
+
+  #[test]
+  fn test_be_bytes_round_trip() {
+    let numbers = [
+      Natural::ZERO,
+      SMALL_MAX,
+      Natural::from_limbs(&[0, 1]),
+      Natural::from_limbs(&[Limb::MAX, 0, 0xff]),
+      Natural::from_limbs(&[0x1234_5678_9abc_def0, 42, 7, 1]),
+    ];
+
+    for n in numbers {
+      let bytes = n.to_be_bytes();
+      assert_ne!(bytes.first(), Some(&0), "{n:?} has a leading zero byte");
+      assert_result(Natural::from_be_bytes(&bytes), n.clone());
+
+      // Bytes are just the digits in radix 256, except that zero has none.
+      if n != Natural::ZERO {
+        assert_eq!(bytes, n.to_digits(256));
+      }
+    }
+  }
+
+  #[test]
+  fn test_hex_dump() {
+    assert_eq!(Natural::ZERO.hex_dump(), "small\n[0] 0x0000000000000000\n");
+    assert_eq!(SMALL_MAX.hex_dump(), "small\n[0] 0xffffffffffffffff\n");
+
+    let limbs: Vec<Limb> = (1..=11).map(|i| i << 60 | i).collect();
+    assert_eq!(
+      Natural::from_limbs(&limbs).hex_dump(),
+      "large (11 limbs)\n\
+       [ 0] 0x1000000000000001\n\
+       [ 1] 0x2000000000000002\n\
+       [ 2] 0x3000000000000003\n\
+       [ 3] 0x4000000000000004\n\
+       [ 4] 0x5000000000000005\n\
+       [ 5] 0x6000000000000006\n\
+       [ 6] 0x7000000000000007\n\
+       [ 7] 0x8000000000000008\n\
+       [ 8] 0x9000000000000009\n\
+       [ 9] 0xa00000000000000a\n\
+       [10] 0xb00000000000000b\n"
+    );
+
+    // Unnormalised large naturals should be dumped as they are stored.
+    assert_eq!(
+      Natural(Repr::Large(vec![1, 0])).hex_dump(),
+      "large (2 limbs)\n[0] 0x0000000000000001\n[1] 0x0000000000000000\n"
+    );
+  }
+
+  #[test]
+  fn test_is_zero() {
+    assert!(Natural::ZERO.is_zero());
+    assert!(!Natural::ONE.is_zero());
+    assert!(!SMALL_MAX.is_zero());
+    assert!(!Natural::from_limbs(&[0, 1]).is_zero());
+
+    // Unnormalised large naturals should be handled by value.
+    assert!(Natural(Repr::Large(vec![])).is_zero());
+    assert!(Natural(Repr::Large(vec![0, 0])).is_zero());
+  }
+
+  #[test]
+  fn test_is_one() {
+    assert!(Natural::ONE.is_one());
+    assert!(!Natural::ZERO.is_one());
+    assert!(!Natural::from(2_u64).is_one());
+    assert!(!Natural::from_limbs(&[1, 1]).is_one());
+
+    assert!(Natural(Repr::Large(vec![1, 0, 0])).is_one());
+    assert!(!Natural(Repr::Large(vec![])).is_one());
+    assert!(!Natural(Repr::Large(vec![0, 1])).is_one());
+  }
+
+  #[test]
+  fn test_is_even() {
+    assert!(Natural::ZERO.is_even());
+    assert!(!Natural::ONE.is_even());
+    assert!(Natural::from(42_u64).is_even());
+    assert!(!SMALL_MAX.is_even());
+    assert!(Natural::from_limbs(&[0, 1]).is_even());
+    assert!(!Natural::from_limbs(&[3, 2]).is_even());
+    assert!(Natural(Repr::Large(vec![])).is_even());
+  }
+
+  #[test]
+  fn test_bitand() {
+    assert_result(
+      Natural::from(0b1100_u64) & Natural::from(0b1010_u64),
+      Natural::from(0b1000_u64),
+    );
+    assert_result(
+      Natural::from(0xff_u64) & Natural::from_limbs(&[0x0f0f, 1]),
+      Natural::from(0x0f_u64),
+    );
+    assert_result(
+      Natural::from_limbs(&[0x0f0f, 1, 7]) & Natural::from(0xf0_u64),
+      Natural::ZERO,
+    );
+    assert_result(
+      Natural::from_limbs(&[Limb::MAX, 0b0110, 1])
+        & Natural::from_limbs(&[0xffff, 0b1100]),
+      Natural::from_limbs(&[0xffff, 0b0100]),
+    );
+
+    // Clearing the top limbs should demote the result to a small natural.
+    assert_result(
+      Natural::from_limbs(&[42, 0b01, Limb::MAX])
+        & Natural::from_limbs(&[Limb::MAX, 0b10, 0]),
+      Natural::from(42_u64),
+    );
+  }
+
+  #[test]
+  fn test_bitor() {
+    assert_result(
+      Natural::from(0b1100_u64) | Natural::from(0b1010_u64),
+      Natural::from(0b1110_u64),
+    );
+    assert_result(
+      Natural::from(0xf0_u64) | Natural::from_limbs(&[0x0f, 1]),
+      Natural::from_limbs(&[0xff, 1]),
+    );
+    assert_result(
+      Natural::from_limbs(&[0x0f, 0, 3]) | Natural::from_limbs(&[0xf0, 4]),
+      Natural::from_limbs(&[0xff, 4, 3]),
+    );
+    assert_result(Natural::ZERO | SMALL_MAX, SMALL_MAX);
+  }
+
+  #[test]
+  fn test_bitxor() {
+    assert_result(
+      Natural::from(0b1100_u64) ^ Natural::from(0b1010_u64),
+      Natural::from(0b0110_u64),
+    );
+    assert_result(
+      Natural::from(0xff_u64) ^ Natural::from_limbs(&[0x0f, 1]),
+      Natural::from_limbs(&[0xf0, 1]),
+    );
+
+    // Cancelling out the top limbs should demote the result to a small natural.
+    let x = Natural::from_limbs(&[0x1234, 5, 6]);
+    assert_result(x.clone() ^ x.clone(), Natural::ZERO);
+    assert_result(
+      x ^ Natural::from_limbs(&[0x4321, 5, 6]),
+      Natural::from(0x1234_u64 ^ 0x4321),
+    );
+  }
+
+  #[test]
+  fn test_bitwise_assign() {
+    let mut n = Natural::from_limbs(&[0b1010, 1]);
+    n |= Natural::from(0b0101_u64);
+    assert_result(n.clone(), Natural::from_limbs(&[0b1111, 1]));
+    n ^= Natural::from_limbs(&[0b0011, 1]);
+    assert_result(n.clone(), Natural::from(0b1100_u64));
+    n &= Natural::from(0b0110_u64);
+    assert_result(n, Natural::from(0b0100_u64));
+  }
+
+  #[test]
+  fn test_shl() {
+    let one = Natural::from(1_u64);
+    assert_result(Natural::ZERO << 1000, Natural::ZERO);
+    assert_result(one.clone() << 0, one.clone());
+    assert_result(one.clone() << 63, Natural::from(1_u64 << 63));
+    assert_result(one.clone() << 64, Natural::from_limbs(&[0, 1]));
+    assert_result(one.clone() << 65, Natural::from_limbs(&[0, 2]));
+    assert_result(one.clone() << 128, Natural::from_limbs(&[0, 0, 1]));
+    assert_result(SMALL_MAX << 1, Natural::from_limbs(&[Limb::MAX - 1, 1]));
+    assert_result(
+      Natural::from_limbs(&[Limb::MAX, 0x8000_0000_0000_0001]) << 68,
+      Natural::from_limbs(&[0, 0xffff_ffff_ffff_fff0, 0x1f, 0x08]),
+    );
+
+    let mut n = one;
+    n <<= 100;
+    assert_result(n, Natural::from(1_u128 << 100));
+  }
+
+  #[test]
+  fn test_shr() {
+    assert_result(Natural::ZERO >> 5, Natural::ZERO);
+    assert_result(Natural::from(0b1011_u64) >> 2, Natural::from(0b10_u64));
+    assert_result(SMALL_MAX >> 64, Natural::ZERO);
+    assert_result(SMALL_MAX >> 1000, Natural::ZERO);
+    assert_result(Natural::from_limbs(&[0, 1]) >> 64, Natural::from(1_u64));
+    assert_result(Natural::from_limbs(&[0, 2]) >> 65, Natural::from(1_u64));
+    assert_result(
+      Natural::from_limbs(&[42, 0, 1]) >> 128,
+      Natural::from(1_u64),
+    );
+    assert_result(
+      Natural::from_limbs(&[7, 1]) >> 1,
+      Natural::from((1_u64 << 63) | 3),
+    );
+    assert_result(
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX, 3]) >> 66,
+      Natural::from(Limb::MAX),
+    );
+
+    let mut n = Natural::from(u128::MAX);
+    n >>= 100;
+    assert_result(n, Natural::from((1_u64 << 28) - 1));
+  }
+
+  #[test]
+  fn test_shift_round_trip() {
+    for seed in 1..=8 {
+      let n = Natural::from_vec(pseudorandom_limbs(seed as usize * 3, seed));
+      for shift in [0, 1, 63, 64, 65, 127, 128, 200] {
+        assert_result((n.clone() << shift) >> shift, n.clone());
+      }
+    }
+  }
+
+  #[test]
+  fn test_bytes_le() {
+    assert!(Natural::ZERO.to_bytes_le().is_empty());
+    assert_eq!(Natural::from(0x2a_u64).to_bytes_le(), [0x2a]);
+    assert_eq!(SMALL_MAX.to_bytes_le(), [0xff; 8]);
+    assert_eq!(
+      Natural::from(0x0102_0304_0506_0708_u64).to_bytes_le(),
+      [8, 7, 6, 5, 4, 3, 2, 1]
+    );
+
+    // Two and a half limbs.
+    let n = Natural::from_limbs(&[
+      0x0706_0504_0302_0100,
+      0x0f0e_0d0c_0b0a_0908,
+      0x1312_1110,
+    ]);
+    let bytes: Vec<u8> = (0..20).collect();
+    assert_eq!(n.to_bytes_le(), bytes);
+    assert_result(Natural::from_bytes_le(&bytes), n);
+
+    assert_result(Natural::from_bytes_le(&[]), Natural::ZERO);
+    assert_result(Natural::from_bytes_le(&[0, 0]), Natural::ZERO);
+    assert_result(
+      Natural::from_bytes_le(&[0x2a, 0, 0]),
+      Natural::from(0x2a_u64),
+    );
+    assert_result(Natural::from_bytes_le(&[0xff; 8]), SMALL_MAX);
+    assert_result(
+      Natural::from_bytes_le(&[0, 0, 0, 0, 0, 0, 0, 0, 1, 0]),
+      Natural::from_limbs(&[0, 1]),
+    );
+  }
+
+  #[test]
+  fn test_bytes_le_round_trip() {
+    for seed in 1..=8 {
+      let n = Natural::from_vec(pseudorandom_limbs(seed as usize, seed));
+      let bytes = n.to_bytes_le();
+      assert_ne!(bytes.last(), Some(&0), "{n:?} has a trailing zero byte");
+      assert_result(Natural::from_bytes_le(&bytes), n.clone());
+
+      // The big-endian bytes are the same bytes in the opposite order.
+      let mut reversed = n.to_be_bytes();
+      reversed.reverse();
+      assert_eq!(bytes, reversed);
+    }
+  }
+
+  #[test]
+  fn test_digit_count() {
+    let power_of_ten = |k: usize| {
+      let mut digits = vec![0; k + 1];
+      digits[0] = 1;
+      Natural::from_digits(&digits, 10).unwrap()
+    };
+    let values = [
+      Natural::ZERO,
+      Natural::ONE,
+      Natural::from(9_u64),
+      Natural::from(10_u64),
+      Natural::from(255_u64),
+      Natural::from(256_u64),
+      Natural::from(10_u64.pow(19) - 1),
+      Natural::from(10_u64.pow(19)),
+      SMALL_MAX,
+      Natural::from_limbs(&[0, 1]),
+      Natural::from_digits(&[9; 40], 10).unwrap(),
+      power_of_ten(40),
+      power_of_ten(100),
+      Natural::from_limbs(&[Limb::MAX, 12345, 1 << 63]),
+    ];
+
+    for value in &values {
+      for radix in [2, 3, 7, 8, 10, 16, 36, 100, 256] {
+        assert_eq!(
+          value.digit_count(radix),
+          value.to_digits(radix).len() as u64,
+          "{value:?} in radix {radix}"
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn test_pow() {
+    assert_eq!(Natural::ZERO.pow(0), Natural::ONE);
+    assert_eq!(Natural::from(7_u64).pow(0), Natural::ONE);
+    assert_eq!(Natural::ZERO.pow(5), Natural::ZERO);
+    assert_eq!(Natural::ONE.pow(u64::MAX), Natural::ONE);
+    assert_eq!(Natural::from(3_u64).pow(4), Natural::from(81_u64));
+    assert_eq!(Natural::from(2_u64).pow(63), Natural::from(1_u64 << 63));
+  }
+
+  #[test]
+  fn test_pow_large() {
+    assert_result(Natural::from(2_u64).pow(64), Natural::from_limbs(&[0, 1]));
+    assert_result(
+      Natural::from(2_u64).pow(128),
+      Natural::from_limbs(&[0, 0, 1]),
+    );
+    assert_result(
+      Natural::from(2_u64).pow(200),
+      Natural::from_limbs(&[0, 0, 0, 1 << 8]),
+    );
+
+    // 3^80 = (3^40)^2, where 3^40 fits in a limb.
+    let n = Natural::from(3_u64.pow(40));
+    assert_result(Natural::from(3_u64).pow(80), n.clone() * n.clone());
+    assert_result(n.clone().pow(3), n.clone() * n.clone() * n);
+
+    assert_result(Natural::from(10_u64).pow(1000), Natural::pow10(1000));
+  }
+
+  #[test]
+  fn test_isqrt() {
+    assert_result(Natural::ZERO.isqrt(), Natural::ZERO);
+    assert_result(Natural::ONE.isqrt(), Natural::ONE);
+    for root in [2_u64, 3, 10, 255, 1_000_000, u64::from(u32::MAX)] {
+      assert_result(Natural::from(root * root).isqrt(), Natural::from(root));
+    }
+
+    // Values that are not perfect squares round down.
+    assert_result(Natural::from(2_u64).isqrt(), Natural::ONE);
+    assert_result(Natural::from(99_u64).isqrt(), Natural::from(9_u64));
+    assert_result(SMALL_MAX.isqrt(), Natural::from(u64::from(u32::MAX)));
+  }
+
+  #[test]
+  fn test_isqrt_large() {
+    // (2^80)^2 = 2^160.
+    let root = Natural::ONE << 80;
+    assert_result((root.clone() * root.clone()).isqrt(), root);
+
+    let root = Natural::from_limbs(&[0x1234_5678_9abc_def0, 42, 7]);
+    let square = root.clone() * root.clone();
+    assert_result(square.isqrt(), root.clone());
+    assert_result((square.clone() + Natural::ONE).isqrt(), root.clone());
+
+    // One less than a perfect square has the next smaller root.
+    let below = square.checked_sub_small(1).unwrap().isqrt();
+    assert_result(below.clone() + Natural::ONE, root);
+  }
+
+  #[test]
+  fn test_sqrt_rem() {
+    let n = |x: u64| Natural::from(x);
+    assert_eq!(Natural::ZERO.sqrt_rem(), (n(0), n(0)));
+    assert_eq!(n(1).sqrt_rem(), (n(1), n(0)));
+    assert_eq!(n(2).sqrt_rem(), (n(1), n(1)));
+    assert_eq!(n(15).sqrt_rem(), (n(3), n(6)));
+    assert_eq!(n(16).sqrt_rem(), (n(4), n(0)));
+    assert_eq!(n(99).sqrt_rem(), (n(9), n(18)));
     assert_eq!(
-      SMALL_MAX * Natural::from(2),
-      Natural::from_limbs(&[0xFFFF_FFFF_FFFF_FFFE, 1])
+      SMALL_MAX.sqrt_rem(),
+      (n(u64::from(u32::MAX)), n(2 * u64::from(u32::MAX)))
     );
+  }
 
-    let half_max = Natural::from(Limb::MAX / 2 + 1);
+  #[test]
+  fn test_sqrt_rem_invariants() {
+    let root = Natural::from_limbs(&[0x1234_5678_9abc_def0, 42, 7]);
+    let square = root.clone() * root.clone();
+    assert_eq!(square.sqrt_rem(), (root.clone(), Natural::ZERO));
+
+    let values = [
+      SMALL_MAX + Natural::ONE,
+      Natural::from_limbs(&[Limb::MAX, Limb::MAX, 3]),
+      square.clone() + Natural::ONE,
+      square.checked_sub_small(1).unwrap(),
+      square + root.clone() + root,
+    ];
+    for n in values {
+      let (root, remainder) = n.sqrt_rem();
+      assert_result(root.clone() * root.clone() + remainder.clone(), n.clone());
+      assert!(
+        remainder <= root.clone() + root,
+        "{n} has an oversized remainder"
+      );
+    }
+  }
+
+  #[test]
+  fn test_modpow() {
+    let n = |x: u64| Natural::from(x);
+    assert_result(n(2).modpow(&n(10), &n(1000)), n(24));
+    assert_result(n(3).modpow(&n(200), &n(1)), Natural::ZERO);
+    assert_result(n(0).modpow(&n(0), &n(7)), Natural::ONE);
+    assert_result(n(5).modpow(&n(0), &n(7)), Natural::ONE);
+    assert_result(n(4).modpow(&n(13), &n(497)), n(445));
+
+    // Reducing along the way should agree with computing the power in full.
+    let base = Natural::from_limbs(&[0x1234_5678_9abc_def0, 42]);
+    let modulus = Natural::from_limbs(&[Limb::MAX, 7, 3]);
+    assert_result(
+      base.modpow(&n(5), &modulus),
+      base.clone().pow(5) % modulus.clone(),
+    );
+  }
+
+  #[test]
+  fn test_modpow_large() {
+    // By Fermat's little theorem, a^(p - 1) = 1 (mod p) for the Mersenne prime
+    // p = 2^127 - 1 and any a that is not a multiple of p.
+    let p = Natural::from_limbs(&[Limb::MAX, Limb::MAX >> 1]);
+    let p_minus_one = p.checked_sub_small(1).unwrap();
+    let a = Natural::from_limbs(&[123, 456]);
+    assert_result(a.modpow(&p_minus_one, &p), Natural::ONE);
+    assert_result(a.modpow(&p, &p), a.clone());
+
+    // Both the base and exponent can exceed the modulus.
+    let big = Natural::from_limbs(&[5, 6, 7]);
+    assert_result(
+      big.modpow(&big, &p),
+      (big.clone() % p.clone()).modpow(&big, &p),
+    );
+  }
+
+  #[test]
+  #[should_panic(
+    expected = "attempt to calculate the remainder with a divisor of zero"
+  )]
+  fn test_modpow_zero_modulus() {
+    let _ = Natural::from(2_u64).modpow(&Natural::ONE, &Natural::ZERO);
+  }
+
+  #[test]
+  fn test_gcd() {
+    let n = |x: u64| Natural::from(x);
+    assert_result(n(12).gcd(n(18)), n(6));
+    assert_result(n(17).gcd(n(31)), n(1));
+    assert_result(n(7).gcd(n(21)), n(7));
+    assert_result(n(21).gcd(n(7)), n(7));
+    assert_result(n(48).gcd(n(48)), n(48));
+    assert_result(n(1 << 40).gcd(n(3 << 20)), n(1 << 20));
+    assert_result(n(0).gcd(n(5)), n(5));
+    assert_result(n(5).gcd(n(0)), n(5));
+    assert_result(n(0).gcd(n(0)), n(0));
+  }
+
+  #[test]
+  fn test_gcd_large() {
+    let a = Natural::from_limbs(&[0x1234_5678_9abc_def0, 42, 7]);
+    let b = Natural::from_limbs(&[Limb::MAX, 3]);
+    let c = Natural::from_limbs(&[12345, 1]);
+
+    // Scaling both operands by the same factor scales their gcd by it too.
+    assert_result(
+      (a.clone() * c.clone()).gcd(b.clone() * c.clone()),
+      a.clone().gcd(b.clone()) * c,
+    );
+
+    // One operand dividing the other.
+    assert_result((a.clone() * b.clone()).gcd(a.clone()), a.clone());
+    assert_result(
+      (Natural::ONE << 200).gcd(Natural::ONE << 130),
+      Natural::ONE << 130,
+    );
+
+    // Consecutive naturals are coprime.
+    assert_result(b.clone().gcd(b.clone() + Natural::ONE), Natural::ONE);
+  }
+
+  #[test]
+  fn test_simplify_fraction() {
+    let n = |x: u64| Natural::from(x);
+    assert_eq!(simplify_fraction(&n(12), &n(18)), (n(2), n(3)));
+    assert_eq!(simplify_fraction(&n(5), &n(5)), (n(1), n(1)));
+    assert_eq!(simplify_fraction(&n(7), &n(3)), (n(7), n(3)));
+    assert_eq!(simplify_fraction(&n(0), &n(4)), (n(0), n(1)));
+    assert_eq!(simplify_fraction(&n(6), &n(0)), (n(6), n(0)));
+    assert_eq!(simplify_fraction(&n(0), &n(0)), (n(0), n(0)));
+
+    // The reduced parts of a scaled fraction do not depend on the scale.
+    let a = Natural::from_limbs(&[0x1234_5678_9abc_def0, 42]);
+    let b = Natural::from_limbs(&[Limb::MAX, 3]);
+    let scale = Natural::from_limbs(&[12345, 1]);
+    let (num, den) = simplify_fraction(&a, &b);
     assert_eq!(
-      half_max.clone() * half_max,
-      Natural::from_limbs(&[0, 0x4000_0000_0000_0000])
+      simplify_fraction(&(a * scale.clone()), &(b * scale)),
+      (num.clone(), den.clone())
+    );
+    assert!(num.gcd(den).is_one());
+  }
+
+  #[test]
+  fn test_lcm() {
+    let n = |x: u64| Natural::from(x);
+    assert_result(n(4).lcm(n(6)), n(12));
+    assert_result(n(7).lcm(n(13)), n(91));
+    assert_result(n(5).lcm(n(25)), n(25));
+    assert_result(n(0).lcm(n(5)), n(0));
+    assert_result(n(5).lcm(n(0)), n(0));
+
+    let a = Natural::from_limbs(&[3, 1]);
+    let b = Natural::from_limbs(&[Limb::MAX, 9]);
+    let lcm = a.clone().lcm(b.clone());
+    assert!(lcm.is_divisible_by(&a) && lcm.is_divisible_by(&b));
+    assert_result(lcm * a.clone().gcd(b.clone()), a * b);
+  }
+
+  #[test]
+  fn test_pow10() {
+    let mut expected = Natural::ONE;
+    for exp in 0..=100 {
+      assert_result(Natural::pow10(exp), expected.clone());
+      expected *= Natural::from(10_u64);
+    }
+
+    assert_result(Natural::pow10(19), Natural::from(10_u64.pow(19)));
+    assert_result(
+      Natural::pow10(20),
+      Natural::from_limbs(&[7766279631452241920, 5]),
+    );
+  }
+
+  #[test]
+  fn test_pow10_large() {
+    let mut expected = Natural::ONE;
+    for _ in 0..1000 {
+      expected *= Natural::from(10_u64);
+    }
+    assert_result(Natural::pow10(1000), expected);
+
+    let digits = Natural::pow10(1234).to_digits(10);
+    assert_eq!(digits.len(), 1235);
+    assert_eq!(digits[0], 1);
+    assert!(digits[1..].iter().all(|&digit| digit == 0));
+  }
+
+  #[test]
+  fn test_is_divisible_by_small() {
+    let divisible =
+      |n: Limb, d: Limb| Natural::from(n).is_divisible_by(&d.into());
+
+    assert!(divisible(12, 1));
+    assert!(divisible(12, 2));
+    assert!(!divisible(13, 2));
+    assert!(divisible(12, 4));
+    assert!(divisible(12, 3));
+    assert!(!divisible(12, 5));
+    assert!(!divisible(3, 12));
+  }
+
+  #[test]
+  fn test_is_divisible_by_zero() {
+    assert!(Natural::ZERO.is_divisible_by(&Natural::ZERO));
+    assert!(!Natural::ONE.is_divisible_by(&Natural::ZERO));
+    assert!(!Natural::from_limbs(&[0, 1]).is_divisible_by(&Natural::ZERO));
+    assert!(Natural::ZERO.is_divisible_by(&Natural::from_limbs(&[3, 4])));
+  }
+
+  #[test]
+  fn test_is_divisible_by_power_of_two() {
+    // 2^64 and 2^65 + 2^64.
+    let (a, b) = (Natural::from_limbs(&[0, 1]), Natural::from_limbs(&[0, 3]));
+
+    assert!(a.is_divisible_by(&Natural::from(1_u64 << 63)));
+    assert!(a.is_divisible_by(&a));
+    assert!(b.is_divisible_by(&a));
+    assert!(!b.is_divisible_by(&Natural::from_limbs(&[0, 2])));
+    assert!(!Natural::from(1_u64 << 63).is_divisible_by(&a));
+    assert!(Natural::from_limbs(&[0, 0, 1]).is_divisible_by(&a));
+  }
+
+  #[test]
+  fn test_is_divisible_by_general() {
+    // 2^64 + 2 is divisible by 3, since 2^64 leaves a remainder of 1.
+    let n = Natural::from_limbs(&[2, 1]);
+    assert!(n.is_divisible_by(&Natural::from(3_u64)));
+    assert!(n.is_divisible_by(&Natural::from(6_u64)));
+    assert!(!n.is_divisible_by(&Natural::from(7_u64)));
+    assert!(
+      !Natural::from_limbs(&[0, 1]).is_divisible_by(&Natural::from(3_u64))
     );
 
+    // (2^64 + 1)^2 = 2^128 + 2^65 + 1.
+    let root = Natural::from_limbs(&[1, 1]);
+    let square = Natural::from_limbs(&[1, 2, 1]);
+    assert!(square.is_divisible_by(&root));
+    assert!(!Natural::from_limbs(&[2, 2, 1]).is_divisible_by(&root));
+    assert!(!root.is_divisible_by(&square));
+    assert!(!Natural::from(5_u64).is_divisible_by(&root));
+  }
+
+  #[test]
+  fn test_normalize() {
+    let mut n = Natural::from_limbs(&[5, 0]);
+    assert!(!n.is_canonical());
+    n.normalize();
+    assert_result(n.clone(), Natural::from(5_u64));
+    assert_eq!(n.cmp(&Natural::from(5_u64)), Ordering::Equal);
+    assert_eq!(n.to_string(), "5");
+
+    let mut n = Natural::from_limbs(&[0, 0, 0]);
+    n.normalize();
+    assert_result(n, Natural::ZERO);
+
+    let mut n = Natural::from_limbs(&[1, 2, 0, 0]);
+    n.normalize();
+    assert_result(n.clone(), Natural::from_limbs(&[1, 2]));
+    assert_eq!(n.to_string(), "36893488147419103233");
+
+    // Numbers already in canonical form are left alone.
+    let mut n = Natural::from(7_u64);
+    n.normalize();
+    assert_result(n, Natural::from(7_u64));
+  }
+
+  #[test]
+  fn test_bit_length() {
+    assert_eq!(Natural::ZERO.bit_length(), 0);
+    assert_eq!(Natural::ONE.bit_length(), 1);
+    assert_eq!(Natural::from(255_u64).bit_length(), 8);
+    assert_eq!(Natural::from(256_u64).bit_length(), 9);
+
+    for bits in [1, 2, 63, 64, 65, 127, 128, 129, 1000] {
+      let power = Natural::ONE << bits;
+      assert_eq!(power.bit_length(), bits + 1, "2^{bits}");
+      let below = power.checked_sub_small(1).unwrap();
+      assert_eq!(below.bit_length(), bits, "2^{bits} - 1");
+    }
+  }
+
+  #[test]
+  fn test_highest_bit() {
+    assert_eq!(Natural::ZERO.highest_bit(), None);
+    assert_eq!(Natural::ONE.highest_bit(), Some(Natural::ONE));
     assert_eq!(
-      SMALL_MAX * SMALL_MAX,
-      Natural::from_limbs(&[0x1, 0xfffffffffffffffe])
+      Natural::from(0b1011_u64).highest_bit(),
+      Some(Natural::from(0b1000_u64))
+    );
+    assert_eq!(SMALL_MAX.highest_bit(), Some(Natural::from(1_u64 << 63)));
+
+    for bits in [63, 64, 65, 127, 128, 129] {
+      let power = Natural::ONE << bits;
+      let below = power.checked_sub_small(1).unwrap();
+      let above = power.clone() + Natural::ONE;
+      assert_eq!(below.highest_bit(), Some(Natural::ONE << (bits - 1)));
+      assert_eq!(power.clone().highest_bit(), Some(power.clone()));
+      assert_result(above.highest_bit().unwrap(), power);
+    }
+  }
+
+  #[test]
+  fn test_count_ones() {
+    assert_eq!(Natural::ZERO.count_ones(), 0);
+    assert_eq!(Natural::from(0b1011_u64).count_ones(), 3);
+    assert_eq!(SMALL_MAX.count_ones(), 64);
+    assert_eq!(Natural::from_limbs(&[Limb::MAX, 0, 0b11]).count_ones(), 66);
+
+    for bits in [0, 1, 63, 64, 65, 128, 1000] {
+      let power = Natural::ONE << bits;
+      assert_eq!(power.count_ones(), 1, "2^{bits}");
+      let below = power.checked_sub_small(1).unwrap();
+      assert_eq!(below.count_ones(), bits, "2^{bits} - 1");
+    }
+  }
+
+  #[test]
+  fn test_bit() {
+    let n = Natural::from_limbs(&[0b1010, 1 << 63, 1]);
+    assert!(!n.bit(0));
+    assert!(n.bit(1));
+    assert!(n.bit(3));
+    assert!(!n.bit(63));
+    assert!(n.bit(64 + 63));
+    assert!(n.bit(128));
+    assert!(!n.bit(129));
+    assert!(!n.bit(u64::MAX));
+    assert!(!Natural::ZERO.bit(0));
+  }
+
+  #[test]
+  fn test_set_bit() {
+    let mut n = Natural::ZERO;
+    n.set_bit(3, true);
+    assert_result(n.clone(), Natural::from(8_u64));
+
+    // Setting a bit in a higher limb promotes a small natural to a large one.
+    n.set_bit(64, true);
+    assert_result(n.clone(), Natural::from_limbs(&[8, 1]));
+    n.set_bit(200, true);
+    assert_result(n.clone(), Natural::from_limbs(&[8, 1, 0, 1 << 8]));
+
+    // Setting a bit that is already set, or clearing one that is already
+    // clear, changes nothing.
+    n.set_bit(3, true);
+    n.set_bit(100, false);
+    n.set_bit(1000, false);
+    assert_result(n.clone(), Natural::from_limbs(&[8, 1, 0, 1 << 8]));
+
+    n.set_bit(3, false);
+    assert_result(n, Natural::from_limbs(&[0, 1, 0, 1 << 8]));
+  }
+
+  #[test]
+  fn test_set_bit_normalises() {
+    // Clearing the top bit removes the limbs that become zero.
+    let mut n = Natural::from_limbs(&[1, 0, 0, 1]);
+    n.set_bit(192, false);
+    assert_result(n.clone(), Natural::ONE);
+
+    let mut n = Natural::from_limbs(&[5, 6, 1 << 10]);
+    n.set_bit(128 + 10, false);
+    assert_result(n, Natural::from_limbs(&[5, 6]));
+
+    let mut n = Natural::from(1_u64);
+    n.set_bit(0, false);
+    assert_result(n, Natural::ZERO);
+  }
+
+  #[test]
+  fn test_clamp() {
+    let (min, max) = (Natural::from(10_u64), Natural::from_limbs(&[0, 1]));
+
+    assert_result(
+      Natural::from(3_u64).clamp(min.clone(), max.clone()),
+      min.clone(),
+    );
+    assert_result(
+      Natural::from(10_u64).clamp(min.clone(), max.clone()),
+      min.clone(),
+    );
+    assert_result(SMALL_MAX.clamp(min.clone(), max.clone()), SMALL_MAX);
+    assert_result(
+      Natural::from_limbs(&[5, 1]).clamp(min.clone(), max.clone()),
+      max.clone(),
     );
+    assert_result(
+      Natural::from_limbs(&[0, 0, 1]).clamp(min, max.clone()),
+      max.clone(),
+    );
+    assert_result(Natural::ONE.clamp(max.clone(), max.clone()), max);
+  }
+
+  #[test]
+  #[should_panic(expected = "min must not exceed max")]
+  fn test_clamp_min_greater_than_max() {
+    Natural::from(5_u64).clamp(Natural::from(6_u64), Natural::from(5_u64));
+  }
+
+  #[test]
+  fn test_range() {
+    let range = Natural::range(Natural::ONE, Natural::from(11_u64));
+    assert_result(
+      range.fold(Natural::default(), Add::add),
+      Natural::from(55_u64),
+    );
+
+    assert_eq!(
+      Natural::range(Natural::from(5_u64), Natural::from(5_u64)).count(),
+      0
+    );
+    assert_eq!(
+      Natural::range(Natural::from(6_u64), Natural::from(5_u64)).count(),
+      0
+    );
+  }
+
+  #[test]
+  fn test_range_large() {
+    let start = Natural::from(Limb::MAX - 1);
+    let end = Natural::from_limbs(&[2, 1]);
+    let range: Vec<_> = Natural::range(start, end).collect();
+
+    assert_eq!(range.len(), 4);
+    assert_result(range[1].clone(), SMALL_MAX);
+    assert_result(range[2].clone(), Natural::from_limbs(&[0, 1]));
+    assert_result(range[3].clone(), Natural::from_limbs(&[1, 1]));
+  }
+
+  /// Property tests that check invariants against many pseudorandom natural
+  /// numbers.
+  ///
+  /// The cases are generated from a fixed seed so that failures are
+  /// reproducible, but a different seed can be chosen by setting the
+  /// `RKN_FUZZ_SEED` environment variable. A failing case is shrunk before it
+  /// is reported, so the reported case is usually small enough to debug by
+  /// hand.
+  mod fuzz {
+    use super::*;
+    use crate::random::{Rng, fuzz_seed};
+
+    /// The number of cases checked for each property.
+    const CASES: usize = 500;
+    /// The maximum number of limbs in a generated natural number.
+    const MAX_LIMBS: i64 = 12;
+
+    /// A generator of pseudorandom natural numbers.
+    ///
+    /// Each number is generated as a list of limbs, which is what gets shrunk
+    /// on failure, since removing limbs or making them smaller always produces
+    /// a simpler case.
+    struct Generator {
+      seed: u64,
+      rng: Rng,
+    }
+
+    impl Generator {
+      fn from_env() -> Self {
+        let seed = fuzz_seed();
+        Self { seed, rng: Rng::from_seed(seed) }
+      }
+
+      /// Generate the limbs of a natural number, favouring limbs at the edges
+      /// of the range of a limb, where carries and normalisation bugs lurk.
+      fn limbs(&self) -> Vec<Limb> {
+        let len = self.rng.int_between(0, MAX_LIMBS) as usize;
+        (0..len)
+          .map(|_| match self.rng.int_between(0, 5) {
+            0 => 0,
+            1 => 1,
+            2 => Limb::MAX,
+            _ => self.rng.next_u64(),
+          })
+          .collect()
+      }
+
+      fn radix(&self, radices: RangeInclusive<i64>) -> u32 {
+        self.rng.int_between(*radices.start(), *radices.end()) as u32
+      }
+    }
+
+    /// Find a simpler list of limbs for which `fails` still holds, by
+    /// repeatedly removing a limb or shrinking one towards zero until no
+    /// simpler failing case can be found.
+    fn shrink(
+      mut limbs: Vec<Limb>,
+      fails: impl Fn(&[Limb]) -> bool,
+    ) -> Vec<Limb> {
+      loop {
+        let mut candidates = Vec::new();
+        for i in 0..limbs.len() {
+          let mut candidate = limbs.clone();
+          candidate.remove(i);
+          candidates.push(candidate);
+        }
+        for i in 0..limbs.len() {
+          for limb in [0, limbs[i] / 2] {
+            if limb != limbs[i] {
+              let mut candidate = limbs.clone();
+              candidate[i] = limb;
+              candidates.push(candidate);
+            }
+          }
+        }
+
+        match candidates.into_iter().find(|candidate| fails(candidate)) {
+          Some(simpler) => limbs = simpler,
+          None => return limbs,
+        }
+      }
+    }
+
+    /// Check that `property` holds for many pseudorandom natural numbers and
+    /// radices, panicking with a shrunk counterexample if it does not.
+    #[track_caller]
+    fn check(
+      radices: RangeInclusive<i64>,
+      property: impl Fn(&Natural, u32) -> bool,
+    ) {
+      let generator = Generator::from_env();
+      for _ in 0..CASES {
+        let (limbs, radix) =
+          (generator.limbs(), generator.radix(radices.clone()));
+        let fails =
+          |limbs: &[Limb]| !property(&Natural::from_vec(limbs.to_vec()), radix);
+
+        if fails(&limbs) {
+          let limbs = shrink(limbs, fails);
+          panic!(
+            "property failed for {:?} in radix {radix} (RKN_FUZZ_SEED={})",
+            Natural::from_vec(limbs),
+            generator.seed,
+          );
+        }
+      }
+    }
+
+    #[test]
+    fn test_digits_round_trip() {
+      check(2..=256, |n, radix| {
+        Natural::from_digits(&n.to_digits(radix), radix).as_ref() == Ok(n)
+      });
+    }
+
+    #[test]
+    fn test_str_radix_round_trip() {
+      check(2..=36, |n, radix| {
+        Natural::from_str_radix(&n.to_str_radix(radix), radix).as_ref() == Ok(n)
+      });
+      // Digits beyond 9 are written in lowercase but parsed in either case.
+      check(11..=36, |n, radix| {
+        let upper = n.to_str_radix(radix).to_uppercase();
+        Natural::from_str_radix(&upper, radix).as_ref() == Ok(n)
+      });
+    }
+
+    #[test]
+    fn test_digit_count_matches_digits() {
+      check(2..=256, |n, radix| {
+        n.digit_count(radix) == n.to_digits(radix).len() as u64
+      });
+    }
+
+    #[test]
+    fn test_decimal_round_trip() {
+      check(10..=10, |n, _| n.to_string().parse().as_ref() == Ok(n));
+    }
+
+    #[test]
+    fn test_shrink() {
+      // Shrinking a case that fails whenever the number is at least 2^64
+      // should leave a single limb after the first, as small as possible.
+      let fails = |limbs: &[Limb]| {
+        Natural::from_vec(limbs.to_vec()) >= Natural::from_limbs(&[0, 1])
+      };
+      assert_eq!(shrink(vec![7, Limb::MAX, 3, 9], fails), [0, 1]);
+    }
   }
 }
@@ -0,0 +1,124 @@
+// SPDX-FileCopyrightText: 2025 Severen Redwood <sev@severen.dev>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+  cell::Cell,
+  collections::hash_map::RandomState,
+  hash::{BuildHasher, Hasher},
+};
+
+/// A small pseudorandom number generator for the random functions available
+/// in expressions, such as `randint`.
+///
+/// This is an implementation of SplitMix64, which is fast and statistically
+/// sound enough for a calculator, but is _not_ cryptographically secure. The
+/// state is kept in a [`Cell`] so that numbers can be generated while
+/// evaluating against a shared [`Environment`](crate::Environment).
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+  state: Cell<u64>,
+}
+
+impl Rng {
+  /// Create a generator that always produces the same sequence of numbers for
+  /// the same seed.
+  pub(crate) fn from_seed(seed: u64) -> Self {
+    Self { state: Cell::new(seed) }
+  }
+
+  /// Create a generator seeded from the entropy the standard library uses to
+  /// randomise hash maps.
+  pub(crate) fn from_entropy() -> Self {
+    Self::from_seed(RandomState::new().build_hasher().finish())
+  }
+
+  /// Generate the next number in the sequence.
+  pub(crate) fn next_u64(&self) -> u64 {
+    let state = self.state.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+    self.state.set(state);
+
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+
+  /// Generate an integer uniformly at random from the inclusive range
+  /// `[low, high]`, which must not be empty.
+  pub(crate) fn int_between(&self, low: i64, high: i64) -> i64 {
+    debug_assert!(low <= high, "the range should not be empty");
+
+    // The number of integers in the range, less one, which always fits in a
+    // u64 even when the range covers every i64.
+    let max_offset = high.abs_diff(low);
+    let offset = match max_offset.checked_add(1) {
+      None => self.next_u64(),
+      Some(span) => self.below(span),
+    };
+
+    low.wrapping_add_unsigned(offset)
+  }
+
+  /// Generate an integer uniformly at random from `[0, span)`.
+  ///
+  /// This uses Lemire's method of multiplying by the span and keeping the high
+  /// half, rejecting the few samples that would bias the result.
+  fn below(&self, span: u64) -> u64 {
+    let threshold = span.wrapping_neg() % span;
+    loop {
+      let product = u128::from(self.next_u64()) * u128::from(span);
+      if product as u64 >= threshold {
+        return (product >> u64::BITS) as u64;
+      }
+    }
+  }
+}
+
+/// The seed used by the fuzz tests when `RKN_FUZZ_SEED` is not set.
+#[cfg(test)]
+pub(crate) const DEFAULT_FUZZ_SEED: u64 = 0x5eed;
+
+/// The seed for the fuzz tests, which can be set with `RKN_FUZZ_SEED` to
+/// reproduce a failure or to explore different inputs.
+///
+/// Failure messages should report the seed as `RKN_FUZZ_SEED={seed}`, so that
+/// it can be copied straight onto the command line.
+#[cfg(test)]
+pub(crate) fn fuzz_seed() -> u64 {
+  std::env::var("RKN_FUZZ_SEED")
+    .ok()
+    .map(|seed| seed.parse().expect("RKN_FUZZ_SEED should be a u64"))
+    .unwrap_or(DEFAULT_FUZZ_SEED)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_same_seed_same_sequence() {
+    let (a, b) = (Rng::from_seed(42), Rng::from_seed(42));
+    for _ in 0..100 {
+      assert_eq!(a.next_u64(), b.next_u64());
+    }
+
+    let c = Rng::from_seed(43);
+    assert_ne!(Rng::from_seed(42).next_u64(), c.next_u64());
+  }
+
+  #[test]
+  fn test_int_between() {
+    let rng = Rng::from_seed(7);
+    let mut seen = [false; 6];
+    for _ in 0..1000 {
+      let n = rng.int_between(-2, 3);
+      assert!((-2..=3).contains(&n), "{n} is out of range");
+      seen[(n + 2) as usize] = true;
+    }
+    assert!(seen.iter().all(|&seen| seen), "every value should occur");
+
+    assert_eq!(rng.int_between(5, 5), 5);
+    let n = rng.int_between(i64::MIN, i64::MAX);
+    assert!((i64::MIN..=i64::MAX).contains(&n));
+  }
+}
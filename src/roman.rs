@@ -0,0 +1,61 @@
+// SPDX-FileCopyrightText: 2025 Severen Redwood <sev@severen.dev>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Roman numerals.
+
+/// The values of the Roman numerals, including the subtractive pairs like `CM`,
+/// from largest to smallest.
+const ROMAN_NUMERALS: [(i64, &str); 13] = [
+  (1000, "M"),
+  (900, "CM"),
+  (500, "D"),
+  (400, "CD"),
+  (100, "C"),
+  (90, "XC"),
+  (50, "L"),
+  (40, "XL"),
+  (10, "X"),
+  (9, "IX"),
+  (5, "V"),
+  (4, "IV"),
+  (1, "I"),
+];
+
+/// Format an integer as a Roman numeral, as in `MCMXCIV` for 1994.
+///
+/// Returns `None` if the integer is not between 1 and 3999, since there is no
+/// numeral for zero or negative numbers and the standard numerals cannot write
+/// 4000 or more.
+pub fn format_roman(mut n: i64) -> Option<String> {
+  if !(1..=3999).contains(&n) {
+    return None;
+  }
+
+  let mut numeral = String::new();
+  for (value, symbol) in ROMAN_NUMERALS {
+    while n >= value {
+      numeral.push_str(symbol);
+      n -= value;
+    }
+  }
+
+  Some(numeral)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_format_roman() {
+    assert_eq!(format_roman(1994).as_deref(), Some("MCMXCIV"));
+    assert_eq!(format_roman(4).as_deref(), Some("IV"));
+    assert_eq!(format_roman(9).as_deref(), Some("IX"));
+    assert_eq!(format_roman(1).as_deref(), Some("I"));
+    assert_eq!(format_roman(2024).as_deref(), Some("MMXXIV"));
+    assert_eq!(format_roman(3999).as_deref(), Some("MMMCMXCIX"));
+    assert_eq!(format_roman(0), None);
+    assert_eq!(format_roman(-5), None);
+    assert_eq!(format_roman(4000), None);
+  }
+}
@@ -1,42 +1,1207 @@
 // SPDX-FileCopyrightText: 2025 Severen Redwood <sev@severen.dev>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::fmt::{self, Debug, Formatter};
+
 use chumsky::{pratt::*, prelude::*};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{natural::Natural, unit::Unit};
 
-#[derive(Debug)]
+#[derive(PartialEq)]
 pub enum Expr {
-  // TODO: Use arbitrary precision integers based on `rkn::natural::Natural`.
-  Literal(i64),
+  Literal(Natural),
+  Float(f64),
+  Var(String),
   Neg(Box<Self>),
   Add(Box<Self>, Box<Self>),
   Sub(Box<Self>, Box<Self>),
   Mul(Box<Self>, Box<Self>),
+  Div(Box<Self>, Box<Self>),
+  /// A division that rounds down to an integer, as in `7 // 2`.
+  FloorDiv(Box<Self>, Box<Self>),
+  /// The remainder of a division, as in `10 % 3`.
+  Rem(Box<Self>, Box<Self>),
+  /// The remainder of a Euclidean division, as in `10 mod 3`, which is never
+  /// negative.
+  Mod(Box<Self>, Box<Self>),
   Pow(Box<Self>, Box<Self>),
+  /// A conversion of a quantity from one unit to another, as in `1 km in m`.
+  ///
+  /// The conversion binds more loosely than any operator, so the quantity is
+  /// the entire expression preceding the source unit.
+  Convert(Box<Self>, Unit, Unit),
+  /// A call to a user-defined function, as in `f(1, 2)`.
+  Call(String, Vec<Self>),
+  /// A definition of a function, as in `f(x) = x^2 + 1`.
+  ///
+  /// Definitions may only appear at the top level of the input.
+  FnDef(String, Vec<String>, Box<Self>),
+}
+
+/// The debug representation matches what would be derived, except that literals
+/// are written in decimal rather than as the limbs of a [`Natural`], so that
+/// parse trees stay readable.
+impl Debug for Expr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    use Expr::*;
+
+    let binary = |f: &mut Formatter<'_>, name, l: &Self, r: &Self| {
+      f.debug_tuple(name).field(l).field(r).finish()
+    };
+
+    match self {
+      Literal(n) => {
+        f.debug_tuple("Literal").field(&format_args!("{n}")).finish()
+      },
+      Float(x) => f.debug_tuple("Float").field(x).finish(),
+      Var(name) => f.debug_tuple("Var").field(name).finish(),
+      Neg(e) => f.debug_tuple("Neg").field(e).finish(),
+      Add(l, r) => binary(f, "Add", l, r),
+      Sub(l, r) => binary(f, "Sub", l, r),
+      Mul(l, r) => binary(f, "Mul", l, r),
+      Div(l, r) => binary(f, "Div", l, r),
+      FloorDiv(l, r) => binary(f, "FloorDiv", l, r),
+      Rem(l, r) => binary(f, "Rem", l, r),
+      Mod(l, r) => binary(f, "Mod", l, r),
+      Pow(l, r) => binary(f, "Pow", l, r),
+      Convert(e, from, to) => {
+        f.debug_tuple("Convert").field(e).field(from).field(to).finish()
+      },
+      Call(name, args) => {
+        f.debug_tuple("Call").field(name).field(args).finish()
+      },
+      FnDef(name, params, body) => {
+        f.debug_tuple("FnDef").field(name).field(params).field(body).finish()
+      },
+    }
+  }
+}
+
+impl Expr {
+  /// The height of the expression tree, where a lone literal has depth 1.
+  pub fn depth(&self) -> usize {
+    self.fold(&mut |_, depths| 1 + depths.into_iter().max().unwrap_or(0))
+  }
+
+  /// The total number of nodes in the expression tree.
+  pub fn node_count(&self) -> usize {
+    self.fold(&mut |_, counts| 1 + counts.into_iter().sum::<usize>())
+  }
+
+  /// Reduce the expression tree to a single value from the bottom up.
+  ///
+  /// The function is called once for each node, after it has been called for
+  /// each of the node's subexpressions, with the node itself and the results
+  /// for its subexpressions in order.
+  pub fn fold<T>(&self, f: &mut impl FnMut(&Self, Vec<T>) -> T) -> T {
+    let children = self.children().into_iter().map(|e| e.fold(f)).collect();
+    f(self, children)
+  }
+
+  /// Rewrite the expression tree from the bottom up.
+  ///
+  /// The function is called once for each node, after each of the node's
+  /// subexpressions have been rewritten, and its result replaces the node.
+  pub fn transform(self, f: &mut impl FnMut(Self) -> Self) -> Self {
+    use Expr::*;
+
+    let mut boxed = |e: Box<Self>| Box::new(e.transform(f));
+    let expr = match self {
+      Literal(_) | Float(_) | Var(_) => self,
+      Neg(e) => Neg(boxed(e)),
+      Add(l, r) => Add(boxed(l), boxed(r)),
+      Sub(l, r) => Sub(boxed(l), boxed(r)),
+      Mul(l, r) => Mul(boxed(l), boxed(r)),
+      Div(l, r) => Div(boxed(l), boxed(r)),
+      FloorDiv(l, r) => FloorDiv(boxed(l), boxed(r)),
+      Rem(l, r) => Rem(boxed(l), boxed(r)),
+      Mod(l, r) => Mod(boxed(l), boxed(r)),
+      Pow(l, r) => Pow(boxed(l), boxed(r)),
+      Convert(e, from, to) => Convert(boxed(e), from, to),
+      Call(name, args) => {
+        Call(name, args.into_iter().map(|arg| arg.transform(f)).collect())
+      },
+      FnDef(name, params, body) => FnDef(name, params, boxed(body)),
+    };
+
+    f(expr)
+  }
+
+  /// The immediate subexpressions of this expression.
+  fn children(&self) -> Vec<&Self> {
+    use Expr::*;
+
+    match self {
+      Literal(_) | Float(_) | Var(_) => vec![],
+      Neg(e) | Convert(e, ..) | FnDef(_, _, e) => vec![e],
+      Add(l, r)
+      | Sub(l, r)
+      | Mul(l, r)
+      | Div(l, r)
+      | FloorDiv(l, r)
+      | Rem(l, r)
+      | Mod(l, r)
+      | Pow(l, r) => {
+        vec![l, r]
+      },
+      Call(_, args) => args.iter().collect(),
+    }
+  }
+}
+
+/// The message reported when the input ends where an operand was expected, as
+/// in `2 +`.
+const MISSING_OPERAND: &str = "Expected an operand but found the end of \
+                               input; perhaps the expression is incomplete?";
+
+pub fn parse(input: &str) -> ParseResult<Expr, Rich<'_, char>> {
+  parse_with(input, ParseOptions::default())
+}
+
+/// Options that enable optional parts of the grammar.
+///
+/// Every option is off by default, which gives the grammar accepted by
+/// [`parse`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ParseOptions {
+  /// Whether single spaces between digits group them, as in `1 000 000`.
+  pub space_grouping: bool,
+  /// Whether to reject decimal literals with superfluous leading zeros, as in
+  /// `0123`.
+  ///
+  /// Such literals are read as decimal by default, but languages like C read
+  /// them as octal, so a user coming from one of those languages may expect
+  /// `0123` to mean 83 rather than 123. This crate writes octal with a `0o`
+  /// prefix instead, and rejecting such literals catches that mistake.
+  pub strict_leading_zeros: bool,
+}
+
+/// Parse an expression using the grammar enabled by `options`.
+pub fn parse_with(
+  input: &str,
+  options: ParseOptions,
+) -> ParseResult<Expr, Rich<'_, char>> {
+  parser(options).parse(input)
 }
 
-pub fn parse(input: &str) -> ParseResult<Expr, EmptyErr> {
-  parser().parse(input)
+/// Build the parser used by [`parse_with`] for the grammar enabled by
+/// `options`.
+///
+/// Building the parser takes time, so this is useful for parsing more than one
+/// input with the same parser.
+pub fn parser<'src>(
+  options: ParseOptions,
+) -> impl Parser<'src, &'src str, Expr, extra::Err<Rich<'src, char>>> {
+  statement(options).then_ignore(end())
 }
 
-fn parser<'src>() -> impl Parser<'src, &'src str, Expr> {
+/// Parse the longest prefix of the input that forms an expression, using the
+/// grammar enabled by `options`.
+///
+/// On success, the expression is returned along with the rest of the input
+/// that follows it.
+pub fn parse_prefix(
+  input: &str,
+  options: ParseOptions,
+) -> ParseResult<(Expr, &str), Rich<'_, char>> {
+  statement(options).then(any().repeated().to_slice()).parse(input)
+}
+
+/// Build a parser for a single expression or definition, which may be followed
+/// by further input.
+fn statement<'src>(
+  options: ParseOptions,
+) -> impl Parser<'src, &'src str, Expr, extra::Err<Rich<'src, char>>> {
   use Expr::*;
 
-  let number = text::digits(10)
-    .to_slice()
-    .map(|s: &str| Literal(s.parse::<i64>().unwrap()));
+  // Grouping with spaces is ambiguous when numbers are adjacent, as in a list
+  // of arguments, so it is only permitted when explicitly requested.
+  let digits = if options.space_grouping {
+    text::digits(10)
+      .then(just(' ').then(text::digits(10)).repeated())
+      .ignored()
+      .boxed()
+  } else {
+    text::digits(10).ignored().boxed()
+  };
+
+  let exponent =
+    one_of("eE").then(one_of("+-").or_not()).then(text::digits(10));
+  // A decimal point may come before, after, or between digits, as in `.5`,
+  // `5.`, or `0.5`, but cannot stand alone.
+  let mantissa = digits
+    .then(just('.').then(text::digits(10).or_not()).or_not())
+    .ignored()
+    .or(just('.').then(text::digits(10)).ignored());
+  let number = mantissa.then(exponent.or_not()).to_slice().try_map(
+    move |s: &str, span| {
+      let digits = s.replace(' ', "");
+      if options.strict_leading_zeros && has_leading_zero(&digits) {
+        return Err(Rich::custom(
+          span,
+          format!("Number '{s}' has a leading zero, which is not allowed"),
+        ));
+      }
+
+      number_literal(&digits)
+        .ok_or_else(|| Rich::custom(span, format!("Number '{s}' is too large")))
+    },
+  );
 
-  let op = |c| just(c);
+  // Infinity and NaN are float literals written as keywords, which take
+  // precedence over variables of the same name. Negative infinity is then just
+  // the negation of `inf`, as in `-inf`.
+  let special_float = text::ascii::keyword("inf")
+    .map(|_| Float(f64::INFINITY))
+    .or(text::ascii::keyword("nan").map(|_| Float(f64::NAN)));
 
-  recursive(|expr| {
-    let atom = number.or(expr.delimited_by(just('('), just(')'))).padded();
+  let unit = text::ascii::ident()
+    .try_map(|s: &str, span| {
+      Unit::from_symbol(s)
+        .ok_or_else(|| Rich::custom(span, format!("Unknown unit '{s}'")))
+    })
+    .padded();
 
-    atom.pratt((
+  // Reaching the end of the input when we expected an operand almost always
+  // means that the user has not finished typing the expression, so we report
+  // that case specifically rather than listing everything that could come
+  // next.
+  let missing_operand = end()
+    .try_map(|_, span| Err::<Expr, _>(Rich::custom(span, MISSING_OPERAND)));
+
+  let ident = text::ascii::ident().map(str::to_string).padded();
+
+  // Operators are padded so that a prefix operator can follow whitespace after
+  // another operator, as in `2 ^ -3`.
+  let op = |c| just(c).padded();
+
+  let expr = recursive(|expr| {
+    let args = expr
+      .clone()
+      .separated_by(just(','))
+      .collect()
+      .delimited_by(just('('), just(')'));
+    let call = ident.then(args).map(|(name, args)| Call(name, args));
+
+    let atom = number
+      .or(special_float)
+      .or(expr.delimited_by(just('('), just(')')))
+      .or(call)
+      .or(ident.map(Var))
+      .or(missing_operand)
+      .padded();
+
+    let arith = atom.pratt((
       infix(left(1), op('+'), |a, _, b, _| Add(Box::new(a), Box::new(b))),
       infix(left(1), op('-'), |a, _, b, _| Sub(Box::new(a), Box::new(b))),
-      infix(left(2), op('*'), |a, _, b, _| Mul(Box::new(a), Box::new(b))),
+      infix(left(2), op('*').or(op('×')), |a, _, b, _| {
+        Mul(Box::new(a), Box::new(b))
+      }),
+      // This must come before `/`, which would otherwise match the first half
+      // of the operator.
+      infix(left(2), just("//").padded(), |a, _, b, _| {
+        FloorDiv(Box::new(a), Box::new(b))
+      }),
+      infix(left(2), op('/').or(op('÷')), |a, _, b, _| {
+        Div(Box::new(a), Box::new(b))
+      }),
+      infix(left(2), op('%'), |a, _, b, _| Rem(Box::new(a), Box::new(b))),
+      infix(left(2), text::ascii::keyword("mod").padded(), |a, _, b, _| {
+        Mod(Box::new(a), Box::new(b))
+      }),
       infix(right(3), op('^'), |a, _, b, _| Pow(Box::new(a), Box::new(b))),
       prefix(2, op('-'), |_, x, _| Neg(Box::new(x))),
-    ))
+    ));
+
+    let conversion =
+      unit.then_ignore(text::ascii::keyword("in")).then(unit).or_not();
+
+    arith.then(conversion).map(|(e, conversion)| match conversion {
+      Some((from, to)) => Convert(Box::new(e), from, to),
+      None => e,
+    })
+  });
+
+  let params = ident
+    .separated_by(just(','))
+    .collect()
+    .delimited_by(just('('), just(')'))
+    .padded();
+  let definition = ident
+    .then(params)
+    .then_ignore(just('='))
+    .then(expr.clone())
+    .map(|((name, params), body)| FnDef(name, params, Box::new(body)));
+
+  definition.or(expr)
+}
+
+/// Format a parse error as its message followed by the input with the location
+/// of the error underlined, as in
+///
+/// ```text
+/// Unknown unit 'x'
+///   1 km in x
+///           ^
+/// ```
+///
+/// The underline is positioned by the width of the input in terminal columns,
+/// so it lines up correctly after wide characters such as CJK characters and
+/// ignores zero-width combining characters.
+pub fn format_error(input: &str, err: &Rich<'_, char>) -> String {
+  let span = err.span();
+  let indent = input[..span.start].width();
+  let len = input[span.start..span.end].width().max(1);
+  format!("{err}\n  {input}\n  {}{}", " ".repeat(indent), "^".repeat(len))
+}
+
+/// The maximum width of an s-expression before [`to_sexpr`] splits it over
+/// multiple lines.
+const SEXPR_WIDTH: usize = 60;
+
+/// Render an expression as a Lisp-style s-expression, as in `(+ 1 (* 2 3))`.
+///
+/// Lists that would be wider than a line are split so that each element after
+/// the head is on its own line, indented beneath the head.
+pub fn to_sexpr(expr: &Expr) -> String {
+  use Expr::*;
+
+  expr.fold(&mut |expr, children: Vec<String>| {
+    let (head, extra) = match expr {
+      Literal(n) => return n.to_string(),
+      Float(x) => return x.to_string(),
+      Var(name) => return name.clone(),
+      Neg(_) => ("-".to_string(), vec![]),
+      Add(..) => ("+".to_string(), vec![]),
+      Sub(..) => ("-".to_string(), vec![]),
+      Mul(..) => ("*".to_string(), vec![]),
+      Div(..) => ("/".to_string(), vec![]),
+      FloorDiv(..) => ("//".to_string(), vec![]),
+      Rem(..) => ("%".to_string(), vec![]),
+      Mod(..) => ("mod".to_string(), vec![]),
+      Pow(..) => ("^".to_string(), vec![]),
+      Convert(_, from, to) => {
+        ("convert".to_string(), vec![from.to_string(), to.to_string()])
+      },
+      Call(name, _) => (name.clone(), vec![]),
+      FnDef(name, params, _) => {
+        let signature = std::iter::once(name).chain(params);
+        let signature = signature.cloned().collect::<Vec<_>>().join(" ");
+        ("define".to_string(), vec![format!("({signature})")])
+      },
+    };
+
+    // Any extra components like units or a function signature come before the
+    // subexpressions so that the body of a definition comes last.
+    let items: Vec<_> = extra.into_iter().chain(children).collect();
+
+    let line = std::iter::once(head.as_str())
+      .chain(items.iter().map(String::as_str))
+      .collect::<Vec<_>>()
+      .join(" ");
+    if line.len() + 2 <= SEXPR_WIDTH && !line.contains('\n') {
+      return format!("({line})");
+    }
+
+    let mut sexpr = format!("({head}");
+    for item in items {
+      for line in item.lines() {
+        sexpr.push_str("\n  ");
+        sexpr.push_str(line);
+      }
+    }
+    sexpr.push(')');
+    sexpr
   })
-  .then_ignore(end())
+}
+
+/// Render an expression compactly in infix notation, as in `1 + (2 * 3)`.
+///
+/// Every operand that is itself an operation is parenthesised, so the structure
+/// of the tree is explicit without relying on precedence. Floats always have a
+/// decimal point or exponent, so that they can be told apart from integers.
+pub fn to_compact(expr: &Expr) -> String {
+  use Expr::*;
+
+  let (compact, _) = expr.fold(&mut |expr, children: Vec<(String, bool)>| {
+    let operands: Vec<_> = children
+      .iter()
+      .map(|(s, compound)| if *compound { format!("({s})") } else { s.clone() })
+      .collect();
+
+    let compact = match expr {
+      Literal(n) => return (n.to_string(), false),
+      Float(x) => return (format!("{x:?}"), false),
+      Var(name) => return (name.clone(), false),
+      // The arguments of a call are already delimited, so they never need
+      // parentheses of their own, and neither does the body of a definition.
+      Call(name, _) => {
+        let args: Vec<_> = children.into_iter().map(|(s, _)| s).collect();
+        return (format!("{name}({})", args.join(", ")), false);
+      },
+      FnDef(name, params, _) => {
+        format!("{name}({}) = {}", params.join(", "), children[0].0)
+      },
+      Neg(_) => format!("-{}", operands[0]),
+      Add(..) => format!("{} + {}", operands[0], operands[1]),
+      Sub(..) => format!("{} - {}", operands[0], operands[1]),
+      Mul(..) => format!("{} * {}", operands[0], operands[1]),
+      Div(..) => format!("{} / {}", operands[0], operands[1]),
+      FloorDiv(..) => format!("{} // {}", operands[0], operands[1]),
+      Rem(..) => format!("{} % {}", operands[0], operands[1]),
+      Mod(..) => format!("{} mod {}", operands[0], operands[1]),
+      Pow(..) => format!("{} ^ {}", operands[0], operands[1]),
+      Convert(_, from, to) => format!("{} {from} in {to}", operands[0]),
+    };
+    (compact, true)
+  });
+
+  compact
+}
+
+/// Render an expression as a Graphviz DOT graph, which can be drawn with a
+/// command such as `dot -Tpng`.
+///
+/// Each node of the expression tree is labelled by its operator or value, and
+/// has an edge to each of its subexpressions in order.
+pub fn to_dot(expr: &Expr) -> String {
+  use Expr::*;
+
+  let mut dot = String::from("digraph {\n");
+  let mut count = 0;
+
+  expr.fold(&mut |expr, children: Vec<usize>| {
+    let label = match expr {
+      Literal(n) => n.to_string(),
+      Float(x) => x.to_string(),
+      Var(name) => name.clone(),
+      Neg(_) | Sub(..) => "-".to_string(),
+      Add(..) => "+".to_string(),
+      Mul(..) => "*".to_string(),
+      Div(..) => "/".to_string(),
+      FloorDiv(..) => "//".to_string(),
+      Rem(..) => "%".to_string(),
+      Mod(..) => "mod".to_string(),
+      Pow(..) => "^".to_string(),
+      Convert(_, from, to) => format!("{from} in {to}"),
+      Call(name, _) => format!("{name}()"),
+      FnDef(name, params, _) => format!("{name}({}) =", params.join(", ")),
+    };
+
+    // Labels are made up of identifiers, numbers, and operators, none of which
+    // contain characters that need escaping.
+    let id = count;
+    count += 1;
+    dot.push_str(&format!("  n{id} [label=\"{label}\"];\n"));
+    for child in children {
+      dot.push_str(&format!("  n{id} -> n{child};\n"));
+    }
+    id
+  });
+
+  dot.push_str("}\n");
+  dot
+}
+
+/// Whether the integer part of a number literal has a superfluous leading zero,
+/// as in `0123` or `00.5`, but not `0` or `0.5`.
+fn has_leading_zero(s: &str) -> bool {
+  let integer_part = s.split(['.', 'e', 'E']).next().unwrap_or_default();
+  integer_part.len() > 1 && integer_part.starts_with('0')
+}
+
+/// The largest exponent allowed in an integer literal with an exponent.
+///
+/// Integer literals are otherwise arbitrarily large, but without this limit a
+/// literal as short as `1e999999999` would take gigabytes of memory.
+const MAX_LITERAL_EXPONENT: u32 = 100_000;
+
+/// Convert a number literal such as `123`, `1e6`, or `0.5` to an expression.
+///
+/// A literal with a decimal point always denotes a float. Otherwise, a literal
+/// with a nonnegative exponent, such as `1e6`, denotes an integer in the same
+/// way as the corresponding literal without an exponent, while a literal with a
+/// negative exponent, such as `1e-3`, denotes a float. Returns `None` if the
+/// exponent of an integer literal exceeds [`MAX_LITERAL_EXPONENT`].
+fn number_literal(s: &str) -> Option<Expr> {
+  if s.contains('.') {
+    return Some(Expr::Float(
+      s.parse().expect("literal should be a valid float"),
+    ));
+  }
+
+  let Some((mantissa, exponent)) = s.split_once(['e', 'E']) else {
+    return Some(Expr::Literal(natural_literal(s)));
+  };
+
+  if exponent.starts_with('-') {
+    return Some(Expr::Float(
+      s.parse().expect("literal should be a valid float"),
+    ));
+  }
+
+  let mantissa = natural_literal(mantissa);
+  if mantissa.is_zero() {
+    return Some(Expr::Literal(mantissa));
+  }
+
+  let exponent: u32 = exponent.trim_start_matches('+').parse().ok()?;
+  if exponent > MAX_LITERAL_EXPONENT {
+    return None;
+  }
+
+  Some(Expr::Literal(mantissa * Natural::pow10(exponent)))
+}
+
+/// Convert a string of decimal digits that the parser has already accepted to
+/// a natural number.
+fn natural_literal(digits: &str) -> Natural {
+  Natural::from_str_radix(digits, 10)
+    .expect("literal should be a valid natural number")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// An integer literal expression.
+  fn literal(n: u64) -> Expr {
+    Expr::Literal(Natural::from(n))
+  }
+
+  /// Assert that each pair of inputs successfully parse to the same expression.
+  macro_rules! assert_same_parse {
+    ($($left:literal == $right:literal),* $(,)?) => {
+      $(
+        let left = parse($left).into_result();
+        assert!(left.is_ok(), "{:?} should parse", $left);
+        assert_eq!(
+          left,
+          parse($right).into_result(),
+          "{:?} and {:?} should parse identically",
+          $left,
+          $right,
+        );
+      )*
+    };
+  }
+
+  /// Assert that parsing the input fails with a single error with the given
+  /// message.
+  #[track_caller]
+  fn assert_parse_error(input: &str, message: &str) {
+    let errs = parse(input).into_errors();
+    assert_eq!(errs.len(), 1, "{input:?} should produce a single error");
+    assert_eq!(errs[0].to_string(), message, "unexpected error for {input:?}");
+  }
+
+  #[test]
+  fn test_parse_missing_operand() {
+    for input in ["2 +", "2 *", "2 ^", "2 ×", "2 + 3 *  ", "-"] {
+      assert_parse_error(input, MISSING_OPERAND);
+    }
+  }
+
+  #[test]
+  fn test_parse_scientific_literals() {
+    let cases = [
+      ("1e6", literal(1_000_000)),
+      ("25E2", literal(2500)),
+      ("3e+2", literal(300)),
+      ("7e0", literal(7)),
+      ("0e100", literal(0)),
+      ("1e-3", Expr::Float(0.001)),
+      ("15e-1", Expr::Float(1.5)),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(parse(input).into_result(), Ok(expected), "{input:?}");
+    }
+  }
+
+  #[test]
+  fn test_parse_decimal_literals() {
+    let cases = [
+      ("0.5", Expr::Float(0.5)),
+      (".5", Expr::Float(0.5)),
+      ("5.", Expr::Float(5.0)),
+      ("1.5e3", Expr::Float(1500.0)),
+      (".5e-1", Expr::Float(0.05)),
+      ("5.e2", Expr::Float(500.0)),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(parse(input).into_result(), Ok(expected), "{input:?}");
+    }
+
+    assert_same_parse!(".5 + .5" == "0.5 + 0.5", "2 * 5." == "2 * 5.0");
+    assert!(parse(".").into_result().is_err());
+    assert!(parse("1 + .").into_result().is_err());
+    assert!(parse("1..5").into_result().is_err());
+  }
+
+  #[test]
+  fn test_parse_inf_and_nan() {
+    assert_eq!(parse("inf").into_result(), Ok(Expr::Float(f64::INFINITY)));
+    assert_eq!(
+      parse("-inf").into_result(),
+      Ok(Expr::Neg(Box::new(Expr::Float(f64::INFINITY))))
+    );
+    assert!(
+      matches!(parse("nan").into_result(), Ok(Expr::Float(x)) if x.is_nan())
+    );
+
+    // Identifiers that merely start with a keyword are still variables.
+    assert_eq!(parse("info").into_result(), Ok(Expr::Var("info".to_string())));
+    assert_eq!(parse("nano").into_result(), Ok(Expr::Var("nano".to_string())));
+  }
+
+  #[test]
+  fn test_parse_large_literals() {
+    let cases = [
+      ("9223372036854775808", Natural::from(1_u128 << 63)),
+      ("18446744073709551616", Natural::from(1_u128 << 64)),
+      ("1e30", Natural::pow10(30)),
+      (
+        "123456789012345678901234567890e5",
+        Natural::pow10(5) * "123456789012345678901234567890".parse().unwrap(),
+      ),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(
+        parse(input).into_result(),
+        Ok(Expr::Literal(expected)),
+        "{input:?} should parse exactly"
+      );
+    }
+  }
+
+  #[test]
+  fn test_parse_literal_too_large() {
+    assert_parse_error("1e100001", "Number '1e100001' is too large");
+    assert_parse_error("1e4294967296", "Number '1e4294967296' is too large");
+    assert!(parse("1e100000").into_result().is_ok());
+  }
+
+  #[test]
+  fn test_parse_unknown_unit() {
+    assert_parse_error("1 km in parsec", "Unknown unit 'parsec'");
+  }
+
+  #[test]
+  fn test_depth_and_node_count() {
+    let cases = [
+      ("1", 1, 1),
+      ("1 + 2 + 3 + 4", 4, 7),
+      ("f(1, 2, 3)", 2, 4),
+      ("((((1 + 2) * 3) ^ 4) - 5)", 5, 9),
+      ("-(-(-(-1)))", 5, 5),
+      ("f(x) = x * x", 3, 4),
+    ];
+
+    for (input, depth, node_count) in cases {
+      let expr = parse(input).into_result().expect("input should parse");
+      assert_eq!(expr.depth(), depth, "depth of {input:?}");
+      assert_eq!(expr.node_count(), node_count, "node count of {input:?}");
+    }
+
+    let input = format!("{}1{}", "(1 + ".repeat(100), ")".repeat(100));
+    let expr = parse(&input).into_result().expect("input should parse");
+    assert_eq!(expr.depth(), 101);
+    assert_eq!(expr.node_count(), 201);
+  }
+
+  #[test]
+  fn test_fold() {
+    let expr = parse("-1 + f(2, x) * 3 ^ 4").into_result().unwrap();
+    let literals = expr.fold(&mut |expr, counts: Vec<usize>| {
+      let count = usize::from(matches!(expr, Expr::Literal(_)));
+      count + counts.into_iter().sum::<usize>()
+    });
+    assert_eq!(literals, 4);
+
+    let vars = expr.fold(&mut |expr, vars: Vec<Vec<String>>| match expr {
+      Expr::Var(name) => vec![name.clone()],
+      _ => vars.concat(),
+    });
+    assert_eq!(vars, ["x"]);
+  }
+
+  #[test]
+  fn test_transform() {
+    let expr = parse("-1 + f(2, x) * 3").into_result().unwrap();
+    let doubled = expr.transform(&mut |expr| match expr {
+      Expr::Literal(n) => Expr::Literal(n * Natural::from(2_u64)),
+      expr => expr,
+    });
+    assert_eq!(Ok(doubled), parse("-2 + f(4, x) * 6").into_result());
+  }
+
+  #[test]
+  fn test_format_error() {
+    let input = "1 km in x";
+    let errs = parse(input).into_errors();
+    assert_eq!(
+      format_error(input, &errs[0]),
+      "Unknown unit 'x'\n  1 km in x\n          ^"
+    );
+
+    // The multiplication sign takes two bytes but only one column.
+    let input = "2 × × 3";
+    let errs = parse(input).into_errors();
+    let underline = format_error(input, &errs[0]);
+    assert!(underline.ends_with("\n  2 × × 3\n      ^"), "{underline}");
+  }
+
+  #[test]
+  fn test_format_error_wide_characters() {
+    // Each CJK character takes two columns and the combining acute accent
+    // takes none.
+    let input = "你好 + e\u{301} + )";
+    let start = input.find(')').unwrap();
+    let err = Rich::custom((start..start + 1).into(), "Unexpected ')'");
+    assert_eq!(
+      format_error(input, &err),
+      format!("Unexpected ')'\n  {input}\n  {}^", " ".repeat(11))
+    );
+  }
+
+  #[test]
+  fn test_parse_leading_zeros() {
+    let strict =
+      ParseOptions { strict_leading_zeros: true, ..Default::default() };
+    let parse_strict = |input| parse_with(input, strict).into_result();
+
+    assert_eq!(parse("0123").into_result(), Ok(literal(123)));
+    assert_eq!(parse("00.5").into_result(), Ok(Expr::Float(0.5)));
+    assert!(parse_strict("0123").is_err());
+    assert!(parse_strict("1 + 007").is_err());
+    assert!(parse_strict("00.5").is_err());
+    assert!(parse_strict("01e3").is_err());
+
+    for input in ["0", "0.5", "0e3", "10", "100.5", ".5"] {
+      assert_eq!(
+        parse_strict(input),
+        parse(input).into_result(),
+        "{input} should parse the same in strict mode"
+      );
+      assert!(parse_strict(input).is_ok(), "{input} should parse");
+    }
+  }
+
+  #[test]
+  fn test_parse_space_grouping() {
+    let grouped = ParseOptions { space_grouping: true, ..Default::default() };
+    let parse_grouped = |input| parse_with(input, grouped).into_result();
+
+    assert_eq!(parse_grouped("1 000"), Ok(literal(1000)));
+    assert_eq!(parse_grouped("1 000 000"), Ok(literal(1_000_000)));
+    assert_eq!(parse_grouped("12 345e2"), Ok(literal(1_234_500)));
+    assert_eq!(parse_grouped("1 000 * 2"), parse("1000 * 2").into_result());
+    assert_eq!(parse_grouped("1 + 000"), parse("1 + 0").into_result());
+
+    assert!(parse_grouped("1  000").is_err());
+    assert!(parse("1 000").into_result().is_err());
+  }
+
+  #[test]
+  fn test_parse_prefix() {
+    let parse_prefix =
+      |input| parse_prefix(input, ParseOptions::default()).into_result();
+
+    assert_eq!(parse_prefix("2+2 foo"), Ok((parse("2+2").unwrap(), "foo")));
+    assert_eq!(
+      parse_prefix("1 km in m!"),
+      Ok((parse("1 km in m").unwrap(), "!"))
+    );
+    assert_eq!(
+      parse_prefix("f(x) = x)"),
+      Ok((parse("f(x) = x").unwrap(), ")"))
+    );
+    assert_eq!(parse_prefix("3"), Ok((literal(3), "")));
+
+    assert!(parse("2+2 foo").into_result().is_err());
+    assert!(parse_prefix("+ 1").is_err());
+  }
+
+  #[test]
+  fn test_debug() {
+    let expr = parse("f(-1.5, 2 ^ 70) km in m").into_result().unwrap();
+    assert_eq!(
+      format!("{expr:?}"),
+      "Convert(Call(\"f\", [Neg(Float(1.5)), Pow(Literal(2), Literal(70))]), \
+       Kilometre, Metre)"
+    );
+
+    let expr = parse("123456789012345678901234567890").into_result().unwrap();
+    assert_eq!(format!("{expr:?}"), "Literal(123456789012345678901234567890)");
+    assert_eq!(
+      format!("{expr:#?}"),
+      "Literal(\n    123456789012345678901234567890,\n)"
+    );
+  }
+
+  #[test]
+  fn test_to_compact() {
+    let cases = [
+      ("1 + 2 * 3", "1 + (2 * 3)"),
+      ("(1 + 2) * 3", "(1 + 2) * 3"),
+      ("1 - 2 - 3", "(1 - 2) - 3"),
+      ("2 ^ 3 ^ 2", "2 ^ (3 ^ 2)"),
+      ("-2 ^ 3", "-(2 ^ 3)"),
+      ("--x", "-(-x)"),
+      ("1.5 + 2e-1 + 2.", "(1.5 + 0.2) + 2.0"),
+      ("f(1 + 2, x)", "f(1 + 2, x)"),
+      ("2 * f()", "2 * f()"),
+      ("10 % 3 + 1", "(10 % 3) + 1"),
+      ("(-7) mod 3", "(-7) mod 3"),
+      ("7 // 2 * 2", "(7 // 2) * 2"),
+      ("(1 + 2) km in m", "(1 + 2) km in m"),
+      ("f(x, y) = x * y + 1", "f(x, y) = (x * y) + 1"),
+    ];
+
+    for (input, expected) in cases {
+      let expr = parse(input).into_result().expect("input should parse");
+      assert_eq!(to_compact(&expr), expected, "{input:?}");
+    }
+  }
+
+  #[test]
+  fn test_to_sexpr() {
+    let cases = [
+      ("1 + 2 * 3", "(+ 1 (* 2 3))"),
+      ("(1 + 2) * 3", "(* (+ 1 2) 3)"),
+      ("6 / 2 / 3", "(/ (/ 6 2) 3)"),
+      ("-2 ^ 3 - x", "(- (- (^ 2 3)) x)"),
+      ("f(1, 2e-1)", "(f 1 0.2)"),
+      ("1 km in m", "(convert km m 1)"),
+      ("f(x, y) = x * y", "(define (f x y) (* x y))"),
+    ];
+
+    for (input, expected) in cases {
+      let expr = parse(input).into_result().expect("input should parse");
+      assert_eq!(to_sexpr(&expr), expected, "{input:?}");
+    }
+  }
+
+  #[test]
+  fn test_to_dot() {
+    let expr = parse("1 + 2 * 3").into_result().unwrap();
+    let dot = to_dot(&expr);
+
+    assert!(dot.starts_with("digraph {\n") && dot.ends_with("}\n"));
+    assert_eq!(dot.matches("[label=").count(), 5);
+    assert_eq!(dot.matches(" -> ").count(), 4);
+    assert!(dot.contains(r#"[label="+"]"#));
+    assert!(dot.contains(r#"[label="*"]"#));
+
+    // The root is visited last, after every subexpression.
+    assert!(dot.contains("  n4 -> n0;\n  n4 -> n3;\n"));
+  }
+
+  #[test]
+  fn test_to_dot_labels() {
+    let expr = parse("f(x, y) = y - x ^ 2.5 km in m").into_result().unwrap();
+    let dot = to_dot(&expr);
+
+    for label in ["f(x, y) =", "km in m", "-", "^", "x", "2.5"] {
+      assert!(dot.contains(&format!("[label=\"{label}\"]")), "{label:?}");
+    }
+  }
+
+  #[test]
+  fn test_to_sexpr_indented() {
+    let expr = parse(
+      "function(1111111111, 2222222222, 3333333333) + 4444444444 * 5555555555",
+    )
+    .into_result()
+    .unwrap();
+    assert_eq!(
+      to_sexpr(&expr),
+      "(+\n  (function 1111111111 2222222222 3333333333)\n  (* 4444444444 5555555555))"
+    );
+
+    let expr = parse(
+      "g(function(111111111111111111, 222222222222222222, 333333333333333333))",
+    )
+    .into_result()
+    .unwrap();
+    assert_eq!(
+      to_sexpr(&expr),
+      "(g\n  (function\n    111111111111111111\n    222222222222222222\n    333333333333333333))"
+    );
+  }
+
+  #[test]
+  fn test_parse_function_definition() {
+    assert_eq!(
+      parse("f(x, y) = x * y").into_result(),
+      Ok(Expr::FnDef(
+        "f".to_string(),
+        vec!["x".to_string(), "y".to_string()],
+        Box::new(Expr::Mul(
+          Box::new(Expr::Var("x".to_string())),
+          Box::new(Expr::Var("y".to_string()))
+        ))
+      ))
+    );
+    assert!(parse("1 + (f(x) = x)").has_errors());
+    assert!(parse("f(1) = 1").has_errors());
+  }
+
+  #[test]
+  fn test_parse_unicode_times() {
+    assert_same_parse! {
+      "2 × 3" == "2 * 3",
+      "2×3×4" == "2*3*4",
+      "-2 × 3" == "-2 * 3",
+      "1 + 2 × 3" == "1 + 2 * 3",
+      "2 × 3 ^ 2" == "2 * 3 ^ 2",
+    };
+  }
+
+  #[test]
+  fn test_parse_floor_division() {
+    assert_eq!(
+      parse("7 // 2").into_result(),
+      Ok(Expr::FloorDiv(Box::new(literal(7)), Box::new(literal(2))))
+    );
+    assert_same_parse! {
+      "7//2" == "7 // 2",
+      "8 // 4 // 2" == "(8 // 4) // 2",
+      "8 // 4 / 2" == "(8 // 4) / 2",
+      "8 / 4 // 2" == "(8 / 4) // 2",
+      "1 + 7 // 2" == "1 + (7 // 2)",
+      "7 // 2 ^ 2" == "7 // (2 ^ 2)",
+    };
+    assert_parse_error("7 //", MISSING_OPERAND);
+    assert!(parse("7 / / 2").into_result().is_err());
+  }
+
+  #[test]
+  fn test_parse_remainder() {
+    assert_eq!(
+      parse("10 % 3").into_result(),
+      Ok(Expr::Rem(Box::new(literal(10)), Box::new(literal(3))))
+    );
+    assert_same_parse! {
+      "10%3" == "10 % 3",
+      "10 % 4 % 3" == "(10 % 4) % 3",
+      "2 * 7 % 4" == "(2 * 7) % 4",
+      "7 % 4 / 2" == "(7 % 4) / 2",
+      "1 + 7 % 4" == "1 + (7 % 4)",
+      "7 % 2 ^ 2" == "7 % (2 ^ 2)",
+    };
+    assert_parse_error("10 %", MISSING_OPERAND);
+  }
+
+  #[test]
+  fn test_parse_modulo() {
+    assert_eq!(
+      parse("10 mod 3").into_result(),
+      Ok(Expr::Mod(Box::new(literal(10)), Box::new(literal(3))))
+    );
+    assert_same_parse! {
+      "(-7)mod(3)" == "(-7) mod 3",
+      "10 mod 4 mod 3" == "(10 mod 4) mod 3",
+      "10 % 4 mod 3" == "(10 % 4) mod 3",
+      "2 * 7 mod 4" == "(2 * 7) mod 4",
+      "1 + 7 mod 4" == "1 + (7 mod 4)",
+      "x mod y" == "(x) mod (y)",
+    };
+    assert_parse_error("10 mod", MISSING_OPERAND);
+    // The operator is a keyword, so it must be separate from any identifier.
+    assert!(parse("10 modulo 3").into_result().is_err());
+    assert!(parse("x mody").into_result().is_err());
+  }
+
+  #[test]
+  fn test_parse_division() {
+    assert_eq!(
+      parse("6 / 2").into_result(),
+      Ok(Expr::Div(Box::new(literal(6)), Box::new(literal(2))))
+    );
+    assert_same_parse! {
+      "6 ÷ 2" == "6 / 2",
+      "8 / 4 / 2" == "(8 / 4) / 2",
+      "2 * 3 / 4" == "(2 * 3) / 4",
+      "1 + 6 / 2" == "1 + (6 / 2)",
+      "6 / 2 ^ 2" == "6 / (2 ^ 2)",
+      "-6 / 2" == "-(6 / 2)",
+    };
+    assert_parse_error("6 /", MISSING_OPERAND);
+  }
+
+  #[test]
+  fn test_parse_negation_after_whitespace() {
+    assert_same_parse! {
+      "2 ^ -3" == "2^(-3)",
+      "2 * - 3" == "2*(-3)",
+      "1 - -1" == "1-(-1)",
+      "f(x) = -x" == "f(x)=(-x)",
+      "f(1, -2)" == "f(1,(-2))",
+    };
+  }
+
+  mod fuzz {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+    use crate::random::{DEFAULT_FUZZ_SEED, Rng, fuzz_seed};
+
+    /// The number of generated inputs that are parsed.
+    const CASES: usize = 2000;
+    /// The maximum number of fragments in a generated input.
+    const MAX_FRAGMENTS: i64 = 24;
+
+    /// Inputs that have tripped up parsers like this one, or exercise the
+    /// edges of the grammar, which are parsed before any generated input.
+    const CORPUS: &[&str] = &[
+      "",
+      " ",
+      "9223372036854775807",
+      "9223372036854775808",
+      "99999999999999999999999999999999",
+      "1e19",
+      "1e4294967296",
+      "1e-4294967296",
+      "0e99999999999999999999",
+      "1.5e999999",
+      ".",
+      "..",
+      "1..5",
+      ".e5",
+      "5.e",
+      "1e",
+      "1e+",
+      "00.5",
+      "1 000 000",
+      "1  000",
+      "--1",
+      "-",
+      "^^",
+      "2 ^ -",
+      "((((1))))",
+      "(()",
+      "())",
+      "f(",
+      "f(,)",
+      "f(1,)",
+      "f(x) =",
+      "f(x) = f(x) = 1",
+      "= 1",
+      "1 km in",
+      "1 km in km in m",
+      "in",
+      "inf",
+      "-inf",
+      "nan nan",
+      "1 ×",
+      "×",
+      "é",
+      "日本",
+      "1 + 😀",
+      "\0",
+      "\u{202e}1 + 2",
+    ];
+
+    /// Fragments that generated inputs are built from, which are mostly
+    /// pieces of valid expressions so that the parser gets past the first few
+    /// characters.
+    const FRAGMENTS: &[&str] = &[
+      "0",
+      "1",
+      "7",
+      "42",
+      "9223372036854775807",
+      "9223372036854775808",
+      "1e6",
+      "e",
+      "E",
+      "+",
+      "-",
+      "*",
+      "×",
+      "/",
+      "÷",
+      "//",
+      "%",
+      " mod ",
+      "^",
+      ".",
+      ",",
+      "(",
+      ")",
+      "=",
+      " ",
+      "  ",
+      "x",
+      "f",
+      "pi",
+      "inf",
+      "nan",
+      "km",
+      "m",
+      " in ",
+      "é",
+      "\t",
+      "\n",
+      "😀",
+      "\0",
+    ];
+
+    /// Generate an input, either by joining fragments or, occasionally, from
+    /// arbitrary bytes.
+    fn input(rng: &Rng) -> String {
+      let len = rng.int_between(0, MAX_FRAGMENTS) as usize;
+      if rng.int_between(0, 9) == 0 {
+        let bytes: Vec<u8> = (0..len).map(|_| rng.next_u64() as u8).collect();
+        return String::from_utf8_lossy(&bytes).into_owned();
+      }
+
+      (0..len)
+        .map(|_| {
+          FRAGMENTS[rng.int_between(0, FRAGMENTS.len() as i64 - 1) as usize]
+        })
+        .collect()
+    }
+
+    /// Assert that parsing an input with every combination of options
+    /// returns, whether with an expression or with errors, rather than
+    /// panicking.
+    #[track_caller]
+    fn assert_no_panic(input: &str, seed: u64) {
+      for space_grouping in [false, true] {
+        for strict_leading_zeros in [false, true] {
+          let options = ParseOptions { space_grouping, strict_leading_zeros };
+          let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _ = parse_with(input, options).into_output_errors();
+            let _ = parse_prefix(input, options).into_output_errors();
+          }));
+          assert!(
+            result.is_ok(),
+            "parsing {input:?} with {options:?} panicked (RKN_FUZZ_SEED={seed})"
+          );
+        }
+      }
+    }
+
+    #[test]
+    fn test_parse_corpus_never_panics() {
+      for input in CORPUS {
+        assert_no_panic(input, DEFAULT_FUZZ_SEED);
+      }
+    }
+
+    #[test]
+    fn test_parse_generated_never_panics() {
+      let seed = fuzz_seed();
+      let rng = Rng::from_seed(seed);
+      for _ in 0..CASES {
+        assert_no_panic(&input(&rng), seed);
+      }
+    }
+  }
 }
@@ -0,0 +1,90 @@
+// SPDX-FileCopyrightText: 2025 Severen Redwood <sev@severen.dev>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fmt::{self, Display, Formatter};
+
+/// A physical quantity that a [`Unit`] measures.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Dimension {
+  Length,
+  Mass,
+}
+
+impl Display for Dimension {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Dimension::Length => write!(f, "length"),
+      Dimension::Mass => write!(f, "mass"),
+    }
+  }
+}
+
+/// A unit of measurement.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Unit {
+  Millimetre,
+  Centimetre,
+  Metre,
+  Kilometre,
+  Foot,
+  Mile,
+  Milligram,
+  Gram,
+  Kilogram,
+  Pound,
+  Ounce,
+}
+
+/// The table of every supported unit.
+///
+/// Each entry consists of the unit itself, the symbol used to refer to it in
+/// expressions, the quantity it measures, and its size as a multiple of the SI
+/// base unit for that quantity (that is, metres or kilograms).
+const UNITS: &[(Unit, &str, Dimension, f64)] = &[
+  (Unit::Millimetre, "mm", Dimension::Length, 1e-3),
+  (Unit::Centimetre, "cm", Dimension::Length, 1e-2),
+  (Unit::Metre, "m", Dimension::Length, 1.0),
+  (Unit::Kilometre, "km", Dimension::Length, 1e3),
+  (Unit::Foot, "ft", Dimension::Length, 0.3048),
+  (Unit::Mile, "mi", Dimension::Length, 1609.344),
+  (Unit::Milligram, "mg", Dimension::Mass, 1e-6),
+  (Unit::Gram, "g", Dimension::Mass, 1e-3),
+  (Unit::Kilogram, "kg", Dimension::Mass, 1.0),
+  (Unit::Pound, "lb", Dimension::Mass, 0.453_592_37),
+  (Unit::Ounce, "oz", Dimension::Mass, 0.028_349_523_125),
+];
+
+impl Unit {
+  /// Look up the unit with the given symbol, such as `km` or `lb`.
+  pub fn from_symbol(symbol: &str) -> Option<Self> {
+    UNITS.iter().find(|(_, s, ..)| *s == symbol).map(|(unit, ..)| *unit)
+  }
+
+  /// The symbol used to refer to this unit in expressions.
+  pub fn symbol(self) -> &'static str {
+    self.entry().1
+  }
+
+  /// The physical quantity measured by this unit.
+  pub fn dimension(self) -> Dimension {
+    self.entry().2
+  }
+
+  /// The size of this unit as a multiple of the SI base unit of its dimension.
+  pub fn scale(self) -> f64 {
+    self.entry().3
+  }
+
+  fn entry(self) -> &'static (Unit, &'static str, Dimension, f64) {
+    UNITS
+      .iter()
+      .find(|(unit, ..)| *unit == self)
+      .expect("every unit should have an entry in the unit table")
+  }
+}
+
+impl Display for Unit {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.symbol())
+  }
+}